@@ -0,0 +1,122 @@
+//! Example of using ADC1 and ADC2 in regular simultaneous mode
+//!
+//! Two channels are sampled at exactly the same time by ADC1 and ADC2. The
+//! packed results are transferred from the common data register by the DMA.
+//!
+//! For an example of using ADC1 and ADC2 independently, see examples/adc12.rs
+
+#![no_main]
+#![no_std]
+
+use core::mem::MaybeUninit;
+
+#[macro_use]
+mod utilities;
+
+use cortex_m_rt::entry;
+use log::info;
+use stm32h7xx_hal::adc::{
+    self, AdcDmaMode, AdcSampleTime, DualMode, RegularSequence, RegularTrigger,
+};
+use stm32h7xx_hal::dma::{
+    dma::{DmaConfig, StreamsTuple},
+    PeripheralToMemory, Transfer,
+};
+use stm32h7xx_hal::{delay::Delay, pac, prelude::*};
+
+const SAMPLES: usize = 64;
+
+// DMA1/DMA2 cannot interact with our stack. Instead, buffers for use with the
+// DMA must be placed somewhere that DMA1/DMA2 can access. In this case we use
+// AXI SRAM.
+//
+// The runtime does not initialise these SRAM banks
+#[link_section = ".axisram.buffers"]
+static mut BUFFER: MaybeUninit<[u32; SAMPLES]> = MaybeUninit::uninit();
+
+#[entry]
+fn main() -> ! {
+    utilities::logger::init();
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Constrain and Freeze power
+    info!("Setup PWR...                  ");
+    let pwr = dp.PWR.constrain();
+    let pwrcfg = example_power!(pwr).freeze();
+
+    // Constrain and Freeze clock
+    info!("Setup RCC...                  ");
+    let rcc = dp.RCC.constrain();
+
+    let ccdr = rcc
+        .sys_ck(100.mhz())
+        .pll2_p_ck(4.mhz()) // Default adc_ker_ck_input
+        .freeze(pwrcfg, &dp.SYSCFG);
+
+    info!("");
+    info!("stm32h7xx-hal example - ADC1 and ADC2 dual mode");
+    info!("");
+
+    let mut delay = Delay::new(cp.SYST, ccdr.clocks);
+
+    // Setup ADC1 and ADC2
+    let (adc1, adc2) = adc::adc12(
+        dp.ADC1,
+        dp.ADC2,
+        &mut delay,
+        ccdr.peripheral.ADC12,
+        &ccdr.clocks,
+    );
+
+    let mut adc1 = adc1.enable();
+    adc1.set_resolution(adc::Resolution::SIXTEENBIT);
+
+    let mut adc2 = adc2.enable();
+    adc2.set_resolution(adc::Resolution::SIXTEENBIT);
+
+    // Setup GPIOC
+    let gpioc = dp.GPIOC.split(ccdr.peripheral.GPIOC);
+    let phase_a = gpioc.pc4.into_analog(); // AIN 4
+    let phase_b = gpioc.pc5.into_analog(); // AIN 8
+
+    // ADC1 samples phase A and ADC2 samples phase B
+    let mut dual = adc::adc12_dual(adc1, adc2, DualMode::RegularSimultaneous);
+    dual.setup_regular_sequence(
+        &RegularSequence::new().channel(&phase_a, AdcSampleTime::T_16),
+        &RegularSequence::new().channel(&phase_b, AdcSampleTime::T_16),
+        RegularTrigger::Continuous,
+        AdcDmaMode::OneShot,
+    );
+
+    // Initialise the buffer, without taking any references to uninitialised
+    // memory
+    let buffer: &'static mut [u32; SAMPLES] = unsafe {
+        BUFFER.as_mut_ptr().write([0; SAMPLES]);
+        &mut *BUFFER.as_mut_ptr()
+    };
+
+    // Setup the DMA transfer on stream 0
+    let streams = StreamsTuple::new(dp.DMA1, ccdr.peripheral.DMA1);
+    let config = DmaConfig::default().memory_increment(true);
+
+    let mut transfer: Transfer<_, _, PeripheralToMemory, _, _> =
+        Transfer::init(streams.0, dual, &mut buffer[..], None, config);
+
+    transfer.start(|dual| dual.start_regular_sequence());
+
+    // Wait for transfer to complete
+    while !transfer.get_transfer_complete_flag() {}
+
+    let (_stream, mut dual, buffer, _) = transfer.free();
+    dual.stop_regular_sequence();
+
+    for sample in buffer.iter() {
+        // ADC1 result in the lower half-word, ADC2 in the upper half-word
+        info!("A: {}, B: {}", sample & 0xFFFF, sample >> 16);
+    }
+
+    loop {
+        cortex_m::asm::nop()
+    }
+}
@@ -0,0 +1,155 @@
+//! Example of streaming a regular sequence of eight ADC1 channels to memory
+//! using the DMA
+//!
+//! The sequence is converted continuously, and the results are written into
+//! two buffers alternately. Each buffer holds four complete sequences.
+//!
+//! For an example of reading a single channel, see examples/adc.rs
+
+#![no_main]
+#![no_std]
+
+use core::mem::MaybeUninit;
+use core::sync::atomic::{fence, Ordering};
+
+use cortex_m_rt::entry;
+#[macro_use]
+mod utilities;
+use stm32h7xx_hal::adc::{
+    self, AdcDmaMode, AdcSampleTime, RegularSequence, RegularTrigger,
+};
+use stm32h7xx_hal::dma::{
+    dma::{DmaConfig, StreamsTuple},
+    PeripheralToMemory, Transfer,
+};
+use stm32h7xx_hal::{delay::Delay, pac, prelude::*};
+
+use log::info;
+
+const CHANNELS: usize = 8;
+const SEQUENCES: usize = 4;
+
+// DMA1/DMA2 cannot interact with our stack. Instead, buffers for use with the
+// DMA must be placed somewhere that DMA1/DMA2 can access. In this case we use
+// AXI SRAM.
+//
+// The runtime does not initialise these SRAM banks
+#[link_section = ".axisram.buffers"]
+static mut BUFFER0: MaybeUninit<[u32; CHANNELS * SEQUENCES]> =
+    MaybeUninit::uninit();
+#[link_section = ".axisram.buffers"]
+static mut BUFFER1: MaybeUninit<[u32; CHANNELS * SEQUENCES]> =
+    MaybeUninit::uninit();
+
+#[entry]
+fn main() -> ! {
+    utilities::logger::init();
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Constrain and Freeze power
+    info!("Setup PWR...                  ");
+    let pwr = dp.PWR.constrain();
+    let pwrcfg = example_power!(pwr).freeze();
+
+    // Constrain and Freeze clock
+    info!("Setup RCC...                  ");
+    let rcc = dp.RCC.constrain();
+
+    let ccdr = rcc
+        .sys_ck(200.mhz())
+        .pll2_p_ck(32.mhz()) // Default adc_ker_ck_input
+        .freeze(pwrcfg, &dp.SYSCFG);
+
+    info!("");
+    info!("stm32h7xx-hal example - ADC DMA");
+    info!("");
+
+    let mut delay = Delay::new(cp.SYST, ccdr.clocks);
+
+    // Setup ADC1
+    let mut adc1 = adc::Adc::adc1(
+        dp.ADC1,
+        &mut delay,
+        ccdr.peripheral.ADC12,
+        &ccdr.clocks,
+    )
+    .enable();
+    adc1.set_resolution(adc::Resolution::SIXTEENBIT);
+
+    // Setup GPIO
+    let gpioa = dp.GPIOA.split(ccdr.peripheral.GPIOA);
+    let gpiob = dp.GPIOB.split(ccdr.peripheral.GPIOB);
+    let gpioc = dp.GPIOC.split(ccdr.peripheral.GPIOC);
+
+    let pa3 = gpioa.pa3.into_analog(); // AIN 15
+    let pa6 = gpioa.pa6.into_analog(); // AIN 3
+    let pa7 = gpioa.pa7.into_analog(); // AIN 7
+    let pb0 = gpiob.pb0.into_analog(); // AIN 9
+    let pb1 = gpiob.pb1.into_analog(); // AIN 5
+    let pc0 = gpioc.pc0.into_analog(); // AIN 10
+    let pc4 = gpioc.pc4.into_analog(); // AIN 4
+    let pc5 = gpioc.pc5.into_analog(); // AIN 8
+
+    // Eight channels, each with its own sampling time
+    let sequence = RegularSequence::new()
+        .channel(&pa3, AdcSampleTime::T_16)
+        .channel(&pa6, AdcSampleTime::T_16)
+        .channel(&pa7, AdcSampleTime::T_16)
+        .channel(&pb0, AdcSampleTime::T_16)
+        .channel(&pb1, AdcSampleTime::T_32)
+        .channel(&pc0, AdcSampleTime::T_32)
+        .channel(&pc4, AdcSampleTime::T_64)
+        .channel(&pc5, AdcSampleTime::T_64);
+
+    // The ADC generates a DMA request for every conversion
+    adc1.setup_regular_sequence(
+        &sequence,
+        RegularTrigger::Continuous,
+        AdcDmaMode::Circular,
+    );
+
+    // Initialise the buffers, without taking any references to uninitialised
+    // memory
+    let buffer0: &'static mut [u32; CHANNELS * SEQUENCES] = unsafe {
+        BUFFER0.as_mut_ptr().write([0; CHANNELS * SEQUENCES]);
+        &mut *BUFFER0.as_mut_ptr()
+    };
+    let buffer1: &'static mut [u32; CHANNELS * SEQUENCES] = unsafe {
+        BUFFER1.as_mut_ptr().write([0; CHANNELS * SEQUENCES]);
+        &mut *BUFFER1.as_mut_ptr()
+    };
+
+    // Setup the DMA transfer on stream 0, double buffered
+    let streams = StreamsTuple::new(dp.DMA1, ccdr.peripheral.DMA1);
+    let config = DmaConfig::default()
+        .memory_increment(true)
+        .double_buffer(true);
+
+    let mut transfer: Transfer<_, _, PeripheralToMemory, _, _> = Transfer::init(
+        streams.0,
+        adc1,
+        &mut buffer0[..],
+        Some(&mut buffer1[..]),
+        config,
+    );
+
+    transfer.start(|adc| adc.start_regular_sequence());
+
+    loop {
+        // Process each buffer when the DMA has finished writing it
+        let result = unsafe {
+            transfer.next_dbm_transfer_with(|buffer, _current| {
+                fence(Ordering::SeqCst);
+                let first = [buffer[0], buffer[1], buffer[2], buffer[3]];
+                fence(Ordering::SeqCst);
+                first
+            })
+        };
+
+        match result {
+            Ok(first) => info!("Sequence starts: {:?}", first),
+            Err(e) => info!("DMA error: {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Example of using an analog watchdog on ADC1 to detect an overcurrent
+//! condition without polling
+//!
+//! The current sense voltage on PC0 is converted continuously with 16x
+//! oversampling. Analog watchdog 1 raises an interrupt flag if the result
+//! exceeds a threshold.
+
+#![no_main]
+#![no_std]
+
+#[macro_use]
+mod utilities;
+
+use cortex_m_rt::entry;
+use log::info;
+use stm32h7xx_hal::adc::{
+    self, AdcDmaMode, AdcOversampling, AdcSampleTime, Event, RegularSequence,
+    RegularTrigger,
+};
+use stm32h7xx_hal::{delay::Delay, pac, prelude::*};
+
+#[entry]
+fn main() -> ! {
+    utilities::logger::init();
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Constrain and Freeze power
+    info!("Setup PWR...                  ");
+    let pwr = dp.PWR.constrain();
+    let pwrcfg = example_power!(pwr).freeze();
+
+    // Constrain and Freeze clock
+    info!("Setup RCC...                  ");
+    let rcc = dp.RCC.constrain();
+
+    let ccdr = rcc
+        .sys_ck(100.mhz())
+        .pll2_p_ck(4.mhz()) // Default adc_ker_ck_input
+        .freeze(pwrcfg, &dp.SYSCFG);
+
+    info!("");
+    info!("stm32h7xx-hal example - ADC analog watchdog");
+    info!("");
+
+    let mut delay = Delay::new(cp.SYST, ccdr.clocks);
+
+    // Setup ADC1
+    let mut adc1 = adc::Adc::adc1(
+        dp.ADC1,
+        &mut delay,
+        ccdr.peripheral.ADC12,
+        &ccdr.clocks,
+    )
+    .enable();
+    adc1.set_resolution(adc::Resolution::TWELVEBIT);
+
+    // Setup GPIOC
+    let gpioc = dp.GPIOC.split(ccdr.peripheral.GPIOC);
+    let channel = gpioc.pc0.into_analog(); // ANALOG IN 10
+
+    // Accumulate 16 conversions and shift right by 4 bits, so the result is
+    // still 12 bits wide
+    adc1.set_oversampling(Some(AdcOversampling::new(16, 4)));
+
+    // Flag any result above 3/4 of full scale
+    adc1.enable_awd1_channel(&channel, 0, 3 * 4096 / 4);
+    adc1.listen(Event::AnalogWatchdog1);
+
+    // Convert continuously. The results are not read here; after an overrun
+    // the data register keeps its old value, but the conversions and the
+    // analog watchdog continue
+    let sequence =
+        RegularSequence::new().channel(&channel, AdcSampleTime::T_64);
+    adc1.setup_regular_sequence(
+        &sequence,
+        RegularTrigger::Continuous,
+        AdcDmaMode::Circular,
+    );
+    adc1.start_regular_sequence();
+
+    loop {
+        if adc1.is_pending(Event::AnalogWatchdog1) {
+            info!("Overcurrent!");
+            adc1.clear_irq(Event::AnalogWatchdog1);
+        }
+        delay.delay_ms(100_u16);
+    }
+}
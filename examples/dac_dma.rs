@@ -0,0 +1,110 @@
+//! Example of waveform generation with the DAC
+//!
+//! Channel 1 outputs a 1kHz sine wave from a table in memory, written to the
+//! DAC by the DMA on each TIM6 update. Channel 2 outputs a triangle wave from
+//! the built-in generator, also triggered by TIM6.
+
+#![no_main]
+#![no_std]
+
+use core::mem::MaybeUninit;
+
+use cortex_m_rt::entry;
+#[macro_use]
+mod utilities;
+use stm32h7xx_hal::dac::Waveform;
+use stm32h7xx_hal::dma::{
+    dma::{DmaConfig, StreamsTuple},
+    MemoryToPeripheral, Transfer,
+};
+use stm32h7xx_hal::timer::BasicMasterMode;
+use stm32h7xx_hal::traits::DacOut;
+use stm32h7xx_hal::{pac, prelude::*};
+
+use log::info;
+
+const SINE: [u16; 32] = [
+    2048, 2447, 2831, 3185, 3495, 3750, 3939, 4056, 4095, 4056, 3939, 3750,
+    3495, 3185, 2831, 2447, 2048, 1649, 1265, 911, 601, 346, 157, 40, 1, 40,
+    157, 346, 601, 911, 1265, 1649,
+];
+
+// DMA1/DMA2 cannot interact with our stack. Instead, buffers for use with the
+// DMA must be placed somewhere that DMA1/DMA2 can access. In this case we use
+// AXI SRAM.
+//
+// The runtime does not initialise these SRAM banks
+#[link_section = ".axisram.buffers"]
+static mut TABLE: MaybeUninit<[u16; 32]> = MaybeUninit::uninit();
+
+#[entry]
+fn main() -> ! {
+    utilities::logger::init();
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Constrain and Freeze power
+    info!("Setup PWR...                  ");
+    let pwr = dp.PWR.constrain();
+    let pwrcfg = example_power!(pwr).freeze();
+
+    // Constrain and Freeze clock
+    info!("Setup RCC...                  ");
+    let rcc = dp.RCC.constrain();
+    let ccdr = rcc.sys_ck(200.mhz()).freeze(pwrcfg, &dp.SYSCFG);
+
+    let gpioa = dp.GPIOA.split(ccdr.peripheral.GPIOA);
+
+    info!("");
+    info!("stm32h7xx-hal example - DAC DMA");
+    info!("");
+
+    // TIM6 updates at 32kHz: 32 samples per period of the sine wave
+    let mut timer = dp.TIM6.timer(32.khz(), ccdr.peripheral.TIM6, &ccdr.clocks);
+
+    // Use the update event as TRGO
+    let trgo = timer.master_mode(BasicMasterMode::Update);
+
+    #[cfg(not(feature = "rm0455"))]
+    let (mut c1, mut c2) = dp.DAC.dac(
+        (gpioa.pa4.into_analog(), gpioa.pa5.into_analog()),
+        ccdr.peripheral.DAC12,
+    );
+    #[cfg(feature = "rm0455")]
+    let (mut c1, mut c2) = dp.DAC1.dac(
+        (gpioa.pa4.into_analog(), gpioa.pa5.into_analog()),
+        ccdr.peripheral.DAC1,
+    );
+
+    // Channel 1: written by the DMA on each trigger
+    c1.set_trigger(Some(trgo.into()));
+    c1.enable_dma();
+    let c1 = c1.enable();
+
+    // Channel 2: 10-bit triangle wave, offset to mid-scale
+    c2.set_trigger(Some(trgo.into()));
+    c2.set_waveform(Waveform::Triangle { bits: 10 });
+    let mut c2 = c2.enable();
+    c2.set_value(1536);
+
+    // Initialise the table, without taking any references to uninitialised
+    // memory
+    let table: &'static mut [u16; 32] = unsafe {
+        TABLE.as_mut_ptr().write(SINE);
+        &mut *TABLE.as_mut_ptr()
+    };
+
+    // Circular transfer from the table to the DAC
+    let streams = StreamsTuple::new(dp.DMA1, ccdr.peripheral.DMA1);
+    let config = DmaConfig::default()
+        .memory_increment(true)
+        .circular_buffer(true);
+
+    let mut transfer: Transfer<_, _, MemoryToPeripheral, _, _> =
+        Transfer::init(streams.0, c1, &mut table[..], None, config);
+
+    transfer.start(|_| {});
+
+    loop {
+        cortex_m::asm::nop()
+    }
+}
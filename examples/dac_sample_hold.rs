@@ -0,0 +1,59 @@
+//! Example of holding a DAC output voltage in Stop mode, using the sample
+//! and hold mode
+//!
+//! A capacitor of about 100nF should be connected to PA4.
+
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+#[macro_use]
+mod utilities;
+use stm32h7xx_hal::dac::SampleHoldTiming;
+use stm32h7xx_hal::traits::DacOut;
+use stm32h7xx_hal::{pac, prelude::*};
+
+use log::info;
+
+#[entry]
+fn main() -> ! {
+    utilities::logger::init();
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Constrain and Freeze power
+    info!("Setup PWR...                  ");
+    let pwr = dp.PWR.constrain();
+    let pwrcfg = example_power!(pwr).freeze();
+
+    // Constrain and Freeze clock
+    info!("Setup RCC...                  ");
+    let rcc = dp.RCC.constrain();
+    let ccdr = rcc.use_lsi().sys_ck(100.mhz()).freeze(pwrcfg, &dp.SYSCFG);
+
+    let gpioa = dp.GPIOA.split(ccdr.peripheral.GPIOA);
+
+    info!("");
+    info!("stm32h7xx-hal example - DAC Sample and Hold");
+    info!("");
+
+    #[cfg(not(feature = "rm0455"))]
+    let dac = dp.DAC.dac(gpioa.pa4.into_analog(), ccdr.peripheral.DAC12);
+    #[cfg(feature = "rm0455")]
+    let dac = dp.DAC1.dac(gpioa.pa4.into_analog(), ccdr.peripheral.DAC1);
+
+    // Charge the capacitor for 1ms, then hold for 20ms
+    let timing = SampleHoldTiming {
+        sample: 1.ms().into(),
+        hold: 20.ms().into(),
+        refresh: 100.us(),
+    };
+    let mut dac = dac.enable_sample_hold(timing, &ccdr.clocks);
+
+    // Hold a bias voltage of about 1/4 of VREF+
+    dac.set_value(1024);
+
+    loop {
+        // The DAC output is maintained while the core sleeps
+        cortex_m::asm::wfi();
+    }
+}
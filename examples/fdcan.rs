@@ -0,0 +1,66 @@
+//! Example of using FDCAN1 in CAN-FD mode with bit rate switching
+//!
+//! Frames received with a standard ID are echoed back on the bus with the
+//! ID incremented by one.
+
+#![no_main]
+#![no_std]
+
+#[macro_use]
+mod utilities;
+use stm32h7xx_hal::{fdcan, pac, prelude::*, rcc::rec};
+
+use cortex_m_rt::entry;
+use embedded_can::{nb::Can, Frame, Id, StandardId};
+use nb::block;
+
+use log::info;
+
+#[entry]
+fn main() -> ! {
+    utilities::logger::init();
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Constrain and Freeze power
+    info!("Setup PWR...                  ");
+    let pwr = dp.PWR.constrain();
+    let pwrcfg = example_power!(pwr).freeze();
+
+    // Constrain and Freeze clock
+    info!("Setup RCC...                  ");
+    let rcc = dp.RCC.constrain();
+    let ccdr = rcc
+        .sys_ck(400.mhz())
+        .pll1_q_ck(80.mhz())
+        .freeze(pwrcfg, &dp.SYSCFG);
+
+    // Select PLL1 Q as the FDCAN kernel clock
+    let fdcan_prec = ccdr
+        .peripheral
+        .FDCAN
+        .kernel_clk_mux(rec::FdcanClkSel::PLL1_Q);
+
+    let gpioh = dp.GPIOH.split(ccdr.peripheral.GPIOH);
+    let tx = gpioh.ph13.into_alternate_af9();
+    let rx = gpioh.ph14.into_alternate_af9();
+
+    info!("");
+    info!("stm32h7xx-hal example - FDCAN");
+    info!("");
+
+    // 500kbit/s nominal, 2Mbit/s data phase
+    let config = fdcan::Config::new(500.khz()).data_bitrate(2.mhz());
+    let mut can = dp.FDCAN1.fdcan((tx, rx), config, fdcan_prec, &ccdr.clocks);
+
+    loop {
+        let frame = block!(can.receive()).unwrap();
+
+        if let Id::Standard(id) = frame.id() {
+            info!("Received {} bytes from {:#x}", frame.dlc(), id.as_raw());
+
+            let id = StandardId::new((id.as_raw() + 1) & 0x7FF).unwrap();
+            let reply = fdcan::Frame::new_fd(id, frame.data(), true).unwrap();
+            block!(can.transmit(&reply)).unwrap();
+        }
+    }
+}
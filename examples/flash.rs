@@ -0,0 +1,62 @@
+//! Example of storing persistent data in the last sector of flash bank 2
+
+#![no_main]
+#![no_std]
+
+#[macro_use]
+mod utilities;
+use stm32h7xx_hal::flash::{Bank, SECTOR_SIZE};
+use stm32h7xx_hal::{pac, prelude::*};
+
+use cortex_m_rt::entry;
+
+use log::info;
+
+#[entry]
+fn main() -> ! {
+    utilities::logger::init();
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Constrain and Freeze power
+    info!("Setup PWR...                  ");
+    let pwr = dp.PWR.constrain();
+    let pwrcfg = example_power!(pwr).freeze();
+
+    // Constrain and Freeze clock
+    info!("Setup RCC...                  ");
+    let rcc = dp.RCC.constrain();
+    let _ccdr = rcc.sys_ck(96.mhz()).freeze(pwrcfg, &dp.SYSCFG);
+
+    info!("");
+    info!("stm32h7xx-hal example - Flash");
+    info!("");
+
+    let mut flash = dp.FLASH.constrain();
+    info!("Banks swapped: {}", flash.banks_swapped());
+
+    let mut bank = flash.unlock(Bank::Bank2);
+    let sector = bank.bank().sectors() - 1;
+    let offset = sector * SECTOR_SIZE;
+
+    // Increment a boot counter stored in the first word of the sector
+    let count = u32::from_le_bytes([
+        bank.read()[offset],
+        bank.read()[offset + 1],
+        bank.read()[offset + 2],
+        bank.read()[offset + 3],
+    ]);
+    let count = if count == u32::MAX { 0 } else { count + 1 };
+    info!("Boot count: {}", count);
+
+    bank.erase_sector(sector as u8).unwrap();
+    bank.program(offset, &count.to_le_bytes()).unwrap();
+    drop(bank);
+
+    if let Some(ecc) = flash.ecc_error(Bank::Bank2) {
+        info!("ECC event: {:?}", ecc);
+    }
+
+    loop {
+        cortex_m::asm::nop()
+    }
+}
@@ -0,0 +1,123 @@
+//! Example of two phase interleaved PWM using the HRTIM
+//!
+//! Timer A and Timer B run at 100kHz, with Timer B shifted by half a period
+//! relative to Timer A. Each timer drives a complementary pair of outputs
+//! with dead time. An ADC trigger is generated at the midpoint of the on
+//! time of Timer A.
+
+#![no_main]
+#![no_std]
+
+#[macro_use]
+mod utilities;
+use stm32h7xx_hal::hrtim::{
+    AdcTrigger, AdcTrigger13Events, Compare, CounterReset, Events, FaultInput,
+    FaultState, Timers,
+};
+use stm32h7xx_hal::pwm::{FaultMonitor, Polarity};
+use stm32h7xx_hal::{pac, prelude::*};
+
+use cortex_m_rt::entry;
+
+use log::info;
+
+#[entry]
+fn main() -> ! {
+    utilities::logger::init();
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Constrain and Freeze power
+    info!("Setup PWR...                  ");
+    let pwr = dp.PWR.constrain();
+    let pwrcfg = example_power!(pwr).freeze();
+
+    // Constrain and Freeze clock
+    info!("Setup RCC...                  ");
+    let rcc = dp.RCC.constrain();
+    let ccdr = rcc.sys_ck(400.mhz()).freeze(pwrcfg, &dp.SYSCFG);
+
+    let gpioa = dp.GPIOA.split(ccdr.peripheral.GPIOA);
+    let gpioc = dp.GPIOC.split(ccdr.peripheral.GPIOC);
+
+    info!("");
+    info!("stm32h7xx-hal example - HRTIM");
+    info!("");
+
+    let hrtim = dp.HRTIM_COMMON.hrtim(
+        dp.HRTIM_MASTER,
+        (
+            dp.HRTIM_TIMA,
+            dp.HRTIM_TIMB,
+            dp.HRTIM_TIMC,
+            dp.HRTIM_TIMD,
+            dp.HRTIM_TIME,
+        ),
+        ccdr.peripheral.HRTIM,
+        &ccdr.clocks,
+    );
+    let mut control = hrtim.control;
+    info!("HRTIM clock: {} Hz", control.clk().0);
+
+    // Fault 1 on PA15, active low
+    control.fault_input(
+        gpioa.pa15.into_alternate_af2(),
+        Polarity::ActiveLow,
+        3,
+    );
+
+    // The master timer sets the period of both phases
+    let mut master = hrtim.master.frequency(100.khz()).finalize();
+    let period = master.get_period();
+    master.set_compare(Compare::Compare1, period / 2);
+
+    // Phase 1: reset on the master period
+    let mut tima = hrtim
+        .tima
+        .frequency(100.khz())
+        .compare(Compare::Compare1, period / 4)
+        .compare(Compare::Compare3, period / 8)
+        .reset_on(CounterReset::MASTER_PERIOD)
+        .output1(
+            gpioc.pc6.into_alternate_af1(),
+            Events::PERIOD | Events::MASTER_PERIOD,
+            Events::COMPARE1,
+        )
+        .output2(gpioc.pc7.into_alternate_af1(), Events::NONE, Events::NONE)
+        .deadtime(100.ns(), 100.ns())
+        .fault(FaultInput::Fault1, FaultState::Inactive)
+        .finalize();
+
+    // Phase 2: reset on master compare 1, half a period later
+    let mut timb = hrtim
+        .timb
+        .frequency(100.khz())
+        .compare(Compare::Compare1, period / 4)
+        .reset_on(CounterReset::MASTER_COMPARE1)
+        .output1(
+            gpioc.pc8.into_alternate_af1(),
+            Events::PERIOD | Events::MASTER_COMPARE1,
+            Events::COMPARE1,
+        )
+        .output2(gpioa.pa8.into_alternate_af2(), Events::NONE, Events::NONE)
+        .deadtime(100.ns(), 100.ns())
+        .fault(FaultInput::Fault1, FaultState::Inactive)
+        .finalize();
+
+    // ADC trigger 1 in the middle of the on time of phase 1
+    control
+        .adc_trigger(AdcTrigger::Trigger1(AdcTrigger13Events::TIMA_COMPARE3));
+
+    control.monitor(&tima);
+    control.monitor(&timb);
+    tima.enable_outputs();
+    timb.enable_outputs();
+    control.start(Timers::MASTER | Timers::A | Timers::B);
+
+    loop {
+        if control.is_fault_active() {
+            info!("Fault!");
+            control.clear_fault_pending(FaultInput::Fault1);
+            control.clear_fault();
+        }
+    }
+}
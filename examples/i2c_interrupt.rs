@@ -0,0 +1,83 @@
+//! Example of interrupt driven I2C transactions in RTIC
+//!
+//! Reads the chip ID register of a sensor at address 0x76, without blocking
+//! the idle task.
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+use rtic::app;
+
+#[macro_use]
+#[allow(unused)]
+mod utilities;
+
+use stm32h7xx_hal::i2c::{I2cEngine, Transaction};
+use stm32h7xx_hal::prelude::*;
+use stm32h7xx_hal::stm32::I2C1;
+
+use log::info;
+
+// Chip ID register
+static REGISTER: [u8; 1] = [0xD0];
+
+#[app(device = stm32h7xx_hal::stm32, peripherals = true)]
+const APP: () = {
+    struct Resources {
+        engine: I2cEngine<I2C1>,
+    }
+
+    #[init]
+    fn init(ctx: init::Context) -> init::LateResources {
+        static mut BUFFER: [u8; 1] = [0; 1];
+
+        utilities::logger::init();
+        let pwr = ctx.device.PWR.constrain();
+        let pwrcfg = example_power!(pwr).freeze();
+
+        let rcc = ctx.device.RCC.constrain();
+        let ccdr = rcc.sys_ck(100.mhz()).freeze(pwrcfg, &ctx.device.SYSCFG);
+
+        let gpiob = ctx.device.GPIOB.split(ccdr.peripheral.GPIOB);
+        let scl = gpiob.pb8.into_alternate_af4().set_open_drain();
+        let sda = gpiob.pb9.into_alternate_af4().set_open_drain();
+
+        let i2c = ctx.device.I2C1.i2c(
+            (scl, sda),
+            100.khz(),
+            ccdr.peripheral.I2C1,
+            &ccdr.clocks,
+        );
+        let mut engine = i2c.engine();
+
+        engine.write_read(0x76, &REGISTER, BUFFER).unwrap();
+
+        init::LateResources { engine }
+    }
+
+    #[task(binds = I2C1_EV, resources = [engine])]
+    fn i2c1_ev(ctx: i2c1_ev::Context) {
+        ctx.resources.engine.on_interrupt();
+    }
+
+    #[task(binds = I2C1_ER, resources = [engine])]
+    fn i2c1_er(ctx: i2c1_er::Context) {
+        ctx.resources.engine.on_interrupt();
+    }
+
+    #[idle(resources = [engine])]
+    fn idle(mut ctx: idle::Context) -> ! {
+        loop {
+            let completion =
+                ctx.resources.engine.lock(|engine| engine.completed());
+
+            match completion.map(|c| (c.result, c.transaction)) {
+                Some((Ok(()), Transaction::WriteRead(_, buffer))) => {
+                    info!("Chip ID {:#x}", buffer[0]);
+                }
+                Some((Err(e), _)) => info!("Error {:?}", e),
+                _ => {}
+            }
+        }
+    }
+};
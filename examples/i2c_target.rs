@@ -0,0 +1,75 @@
+//! Example of an I2C target that implements a small register file
+//!
+//! The controller writes a register index, optionally followed by data to
+//! store from that index. A read returns data from the last index written.
+
+#![no_main]
+#![no_std]
+
+#[macro_use]
+mod utilities;
+use stm32h7xx_hal::i2c::{OwnAddress, TargetConfig, TargetEvent};
+use stm32h7xx_hal::{pac, prelude::*};
+
+use cortex_m_rt::entry;
+
+use log::info;
+
+#[entry]
+fn main() -> ! {
+    utilities::logger::init();
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Constrain and Freeze power
+    info!("Setup PWR...                  ");
+    let pwr = dp.PWR.constrain();
+    let pwrcfg = example_power!(pwr).freeze();
+
+    // Constrain and Freeze clock
+    info!("Setup RCC...                  ");
+    let rcc = dp.RCC.constrain();
+    let ccdr = rcc.sys_ck(100.mhz()).freeze(pwrcfg, &dp.SYSCFG);
+    let gpiob = dp.GPIOB.split(ccdr.peripheral.GPIOB);
+
+    // Configure the SCL and the SDA pin for our I2C bus
+    let scl = gpiob.pb8.into_alternate_af4().set_open_drain();
+    let sda = gpiob.pb9.into_alternate_af4().set_open_drain();
+
+    info!("");
+    info!("stm32h7xx-hal example - I2C Target");
+    info!("");
+
+    let config = TargetConfig::new(OwnAddress::SevenBit(0x42));
+    let mut target = dp.I2C1.i2c_target(
+        (scl, sda),
+        config,
+        100.khz(),
+        ccdr.peripheral.I2C1,
+        &ccdr.clocks,
+    );
+
+    let mut registers = [0u8; 16];
+    let mut index = 0;
+    let mut first_byte = false;
+
+    loop {
+        match nb::block!(target.poll()) {
+            Ok(TargetEvent::Write { .. }) => first_byte = true,
+            Ok(TargetEvent::Received(byte)) => {
+                if first_byte {
+                    index = byte as usize % registers.len();
+                    first_byte = false;
+                } else {
+                    registers[index] = byte;
+                    index = (index + 1) % registers.len();
+                }
+            }
+            Ok(TargetEvent::TransmitRequest) => {
+                target.write(registers[index]);
+                index = (index + 1) % registers.len();
+            }
+            Ok(_) => {}
+            Err(e) => info!("Error {:?}", e),
+        }
+    }
+}
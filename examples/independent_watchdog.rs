@@ -0,0 +1,63 @@
+#![no_main]
+#![no_std]
+
+#[macro_use]
+mod utilities;
+use stm32h7xx_hal::{pac, prelude::*, watchdog::IndependentWatchdog};
+
+use cortex_m_rt::entry;
+
+use log::info;
+
+#[entry]
+fn main() -> ! {
+    utilities::logger::init();
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Constrain and Freeze power
+    info!("Setup PWR...                  ");
+    let pwr = dp.PWR.constrain();
+    let pwrcfg = example_power!(pwr).freeze();
+
+    // Constrain and Freeze clock
+    info!("Setup RCC...                  ");
+    let rcc = dp.RCC.constrain();
+    let ccdr = rcc.sys_ck(96.mhz()).freeze(pwrcfg, &dp.SYSCFG);
+
+    #[cfg(any(feature = "rm0433", feature = "rm0455"))]
+    let mut watchdog = IndependentWatchdog::new(dp.IWDG, &ccdr.clocks);
+
+    // Dual core parts
+    #[cfg(all(feature = "rm0399", feature = "cm7"))]
+    let mut watchdog = IndependentWatchdog::new(dp.IWDG1, &ccdr.clocks);
+    #[cfg(all(feature = "rm0399", feature = "cm4"))]
+    let mut watchdog = IndependentWatchdog::new(dp.IWDG2, &ccdr.clocks);
+
+    info!("");
+    info!("stm32h7xx-hal example - Independent Watchdog");
+    info!("");
+
+    // If the watchdog is working correctly this print should
+    // appear again and again as the chip gets restarted
+    info!("Watchdog restarted!           ");
+
+    // Enable the watchdog with a window of 50 ms to 100 ms
+    watchdog.start_windowed(50.ms(), 100.ms());
+
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let mut delay = cp.SYST.delay(ccdr.clocks);
+
+    // Feeding inside the window keeps the chip running
+    for _ in 0..20 {
+        delay.delay_ms(75_u16);
+        watchdog.feed();
+    }
+
+    // Feeding too early resets the chip
+    info!("Feeding too early...          ");
+    watchdog.feed();
+
+    loop {
+        cortex_m::asm::nop()
+    }
+}
@@ -0,0 +1,114 @@
+//! Example of streaming PWM duty cycles with the DMA
+//!
+//! Drives a strip of WS2812 LEDs from TIM3 channel 1 on PA6. Each bit is one
+//! 800kHz PWM period, with a long pulse for a 1 and a short pulse for a 0. The
+//! duty cycle is written by the DMA on each update event, so no interrupt is
+//! needed per bit.
+
+#![no_main]
+#![no_std]
+
+use core::mem::MaybeUninit;
+
+use cortex_m_rt::entry;
+#[macro_use]
+mod utilities;
+use stm32h7xx_hal::dma::{
+    dma::{DmaConfig, StreamsTuple},
+    MemoryToPeripheral, Transfer,
+};
+use stm32h7xx_hal::{pac, prelude::*};
+
+use log::info;
+
+const LEDS: usize = 8;
+// 24 bits per LED, followed by at least 50µs low to latch the data
+const RESET: usize = 48;
+const LEN: usize = LEDS * 24 + RESET;
+
+// DMA1/DMA2 cannot interact with our stack. Instead, buffers for use with the
+// DMA must be placed somewhere that DMA1/DMA2 can access. In this case we use
+// AXI SRAM.
+//
+// The runtime does not initialise these SRAM banks
+#[link_section = ".axisram.buffers"]
+static mut BUFFER: MaybeUninit<[u16; LEN]> = MaybeUninit::uninit();
+
+#[entry]
+fn main() -> ! {
+    utilities::logger::init();
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Constrain and Freeze power
+    info!("Setup PWR...                  ");
+    let pwr = dp.PWR.constrain();
+    let pwrcfg = example_power!(pwr).freeze();
+
+    // Constrain and Freeze clock
+    info!("Setup RCC...                  ");
+    let rcc = dp.RCC.constrain();
+    let ccdr = rcc.sys_ck(200.mhz()).freeze(pwrcfg, &dp.SYSCFG);
+
+    let gpioa = dp.GPIOA.split(ccdr.peripheral.GPIOA);
+
+    info!("");
+    info!("stm32h7xx-hal example - PWM DMA");
+    info!("");
+
+    // 800kHz PWM on PA6
+    let (mut control, c1) = dp
+        .TIM3
+        .pwm_advanced(
+            gpioa.pa6.into_alternate_af2(),
+            ccdr.peripheral.TIM3,
+            &ccdr.clocks,
+        )
+        .frequency(800.khz())
+        .finalize();
+
+    // Write the next duty cycle once per period
+    control.cc_dma_on_update(true);
+
+    // WS2812 timing: 0.8µs high for a 1, 0.4µs high for a 0
+    let max = c1.get_max_duty();
+    let one = max * 2 / 3;
+    let zero = max / 3;
+
+    // Fill the buffer with a colour gradient, without taking any references
+    // to uninitialised memory
+    let buffer: &'static mut [u16; LEN] = unsafe {
+        BUFFER.as_mut_ptr().write([0; LEN]);
+        &mut *BUFFER.as_mut_ptr()
+    };
+    for led in 0..LEDS {
+        // GRB order, most significant bit first
+        let grb: u32 = ((led as u32 * 32) << 16) | (255 - led as u32 * 32);
+        for bit in 0..24 {
+            buffer[led * 24 + bit] = if grb & (1 << (23 - bit)) != 0 {
+                one
+            } else {
+                zero
+            };
+        }
+    }
+
+    let streams = StreamsTuple::new(dp.DMA1, ccdr.peripheral.DMA1);
+    let config = DmaConfig::default().memory_increment(true);
+
+    let mut transfer: Transfer<_, _, MemoryToPeripheral, _, _> =
+        Transfer::init(streams.0, c1, &mut buffer[..], None, config);
+
+    transfer.start(|c1| {
+        c1.enable_dma();
+        c1.enable();
+    });
+
+    // The output stays low after the last bit
+    while !transfer.get_transfer_complete_flag() {}
+
+    info!("Done");
+
+    loop {
+        cortex_m::asm::nop()
+    }
+}
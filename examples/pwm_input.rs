@@ -0,0 +1,71 @@
+//! Measure the frequency and duty cycle of a PWM signal
+//!
+//! TIM1 generates a 1kHz PWM signal on PA8, which should be connected to
+//! PA0. TIM5 measures it in PWM input mode.
+
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+#[macro_use]
+mod utilities;
+use stm32h7xx_hal::capture::CaptureFilter;
+use stm32h7xx_hal::{block, pac, prelude::*};
+
+use log::info;
+
+#[entry]
+fn main() -> ! {
+    utilities::logger::init();
+    let dp = pac::Peripherals::take().expect("Cannot take peripherals");
+
+    // Constrain and Freeze power
+    info!("Setup PWR...                  ");
+    let pwr = dp.PWR.constrain();
+    let pwrcfg = example_power!(pwr).freeze();
+
+    // Constrain and Freeze clock
+    info!("Setup RCC...                  ");
+    let rcc = dp.RCC.constrain();
+    let ccdr = rcc.sys_ck(200.mhz()).freeze(pwrcfg, &dp.SYSCFG);
+
+    let gpioa = dp.GPIOA.split(ccdr.peripheral.GPIOA);
+
+    info!("");
+    info!("stm32h7xx-hal example - PWM input");
+    info!("");
+
+    // Generate a 1kHz signal with a 25% duty cycle on PA8
+    let mut pwm = dp.TIM1.pwm(
+        gpioa.pa8.into_alternate_af1(),
+        1.khz(),
+        ccdr.peripheral.TIM1,
+        &ccdr.clocks,
+    );
+    let max = pwm.get_max_duty();
+    pwm.set_duty(max / 4);
+    pwm.enable();
+
+    // Measure it on PA0
+    let mut pwm_input = dp
+        .TIM5
+        .pwm_input(
+            gpioa.pa0.into_alternate_af2(),
+            ccdr.peripheral.TIM5,
+            &ccdr.clocks,
+        )
+        .filter(CaptureFilter::CkIntN8)
+        .finalize();
+
+    loop {
+        match block!(pwm_input.measure()) {
+            Ok(m) => info!(
+                "Frequency {} Hz, duty cycle {}%",
+                m.frequency.0,
+                m.duty_cycle * 100.0
+            ),
+            Err(e) => info!("Measurement error: {:?}", e),
+        }
+    }
+}
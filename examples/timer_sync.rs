@@ -0,0 +1,117 @@
+//! Example of timer synchronisation
+//!
+//! TIM1 and TIM8 generate phase-locked 20kHz PWM: every TIM1 update event
+//! resets the TIM8 counter. TIM1 channel 4 triggers an ADC conversion in the
+//! middle of each TIM1 channel 1 pulse.
+//!
+//! PE9  - TIM1 CH1 - 50% duty
+//! PE14 - TIM1 CH4 - 25% duty, ADC trigger on its falling edge
+//! PC6  - TIM8 CH1 - 50% duty
+//! PC0  - ADC1 INP10
+
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+#[macro_use]
+mod utilities;
+use stm32h7xx_hal::adc::{
+    self, AdcSampleTime, InjectedSequence, InjectedTrigger, TriggerEdge,
+};
+use stm32h7xx_hal::timer::{MasterMode, MasterMode2, SlaveMode};
+use stm32h7xx_hal::{delay::Delay, pac, prelude::*};
+
+use log::info;
+
+#[entry]
+fn main() -> ! {
+    utilities::logger::init();
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = pac::Peripherals::take().expect("Cannot take peripherals");
+
+    // Constrain and Freeze power
+    info!("Setup PWR...                  ");
+    let pwr = dp.PWR.constrain();
+    let pwrcfg = example_power!(pwr).freeze();
+
+    // Constrain and Freeze clock
+    info!("Setup RCC...                  ");
+    let rcc = dp.RCC.constrain();
+    let ccdr = rcc
+        .sys_ck(200.mhz())
+        .pll2_p_ck(32.mhz()) // Default adc_ker_ck_input
+        .freeze(pwrcfg, &dp.SYSCFG);
+
+    info!("");
+    info!("stm32h7xx-hal example - Timer synchronisation");
+    info!("");
+
+    let mut delay = Delay::new(cp.SYST, ccdr.clocks);
+
+    let gpioc = dp.GPIOC.split(ccdr.peripheral.GPIOC);
+    let gpioe = dp.GPIOE.split(ccdr.peripheral.GPIOE);
+
+    // Setup ADC1
+    let mut adc1 = adc::Adc::adc1(
+        dp.ADC1,
+        &mut delay,
+        ccdr.peripheral.ADC12,
+        &ccdr.clocks,
+    )
+    .enable();
+    adc1.set_resolution(adc::Resolution::TWELVEBIT);
+    let channel = gpioc.pc0.into_analog();
+
+    // Setup PWM
+    let (mut tim1, (mut t1c1, mut t1c4)) = dp
+        .TIM1
+        .pwm_advanced(
+            (
+                gpioe.pe9.into_alternate_af1(),
+                gpioe.pe14.into_alternate_af1(),
+            ),
+            ccdr.peripheral.TIM1,
+            &ccdr.clocks,
+        )
+        .frequency(20.khz())
+        .finalize();
+    let (mut tim8, mut t8c1) = dp
+        .TIM8
+        .pwm_advanced(
+            gpioc.pc6.into_alternate_af3(),
+            ccdr.peripheral.TIM8,
+            &ccdr.clocks,
+        )
+        .frequency(20.khz())
+        .finalize();
+
+    // TIM8 is reset by each TIM1 update event
+    let trgo = tim1.master_mode(MasterMode::Update);
+    tim8.slave_mode(SlaveMode::Reset, trgo);
+
+    let max = t1c1.get_max_duty();
+    t1c1.set_duty(max / 2);
+    t1c4.set_duty(max / 4);
+    t8c1.set_duty(t8c1.get_max_duty() / 2);
+    t1c1.enable();
+    t1c4.enable();
+    t8c1.enable();
+
+    // Trigger ADC1 on the falling edge of OC4REF
+    let trgo2 = tim1.master_mode2(MasterMode2::Oc4Ref);
+    let sequence =
+        InjectedSequence::new().channel(&channel, AdcSampleTime::T_16);
+    adc1.setup_injected_sequence(
+        &sequence,
+        InjectedTrigger::External(trgo2.into(), TriggerEdge::Falling),
+    );
+    adc1.start_injected_sequence();
+
+    loop {
+        if adc1.is_injected_sequence_complete() {
+            let value = adc1.read_injected(0);
+            info!("ADC1: {}", value);
+        }
+    }
+}
@@ -2,15 +2,56 @@
 //!
 //! ADC1 and ADC2 share a reset line. To initialise both of them, use the
 //! [`adc12`](adc12) method.
+//!
+//! # Regular sequences
+//!
+//! Single conversions can be performed with the blocking
+//! [`OneShot`](embedded_hal::adc::OneShot) trait. For higher sample rates, a
+//! [`RegularSequence`](RegularSequence) of up to 16 conversions can be
+//! converted continuously or on an external trigger, with the results
+//! transferred to memory by the DMA:
+//!
+//! ```
+//! let sequence = RegularSequence::new()
+//!     .channel(&pa6, AdcSampleTime::T_8)
+//!     .channel(&pc4, AdcSampleTime::T_64);
+//! adc1.setup_regular_sequence(
+//!     &sequence,
+//!     RegularTrigger::Continuous,
+//!     AdcDmaMode::Circular,
+//! );
+//!
+//! let mut transfer: Transfer<_, _, PeripheralToMemory, _, _> =
+//!     Transfer::init(stream, adc1, buffer0, Some(buffer1), config);
+//! transfer.start(|adc| adc.start_regular_sequence());
+//! ```
+//!
+//! # Injected sequences
+//!
+//! An [`InjectedSequence`](InjectedSequence) of up to 4 conversions can
+//! interrupt the regular sequence, for example on a timer trigger. Each
+//! result has its own data register, see
+//! [`read_injected`](Adc::read_injected).
+//!
+//! # Oversampling and analog watchdogs
+//!
+//! The hardware oversampler accumulates up to 1024 conversions, see
+//! [`AdcOversampling`](AdcOversampling). The three analog watchdogs compare
+//! conversion results against thresholds without CPU intervention, and can
+//! generate an interrupt using [`listen`](Adc::listen).
+//!
+//! # Dual mode
+//!
+//! ADC1 and ADC2 can convert simultaneously, or interleaved on the same
+//! channel, using [`adc12_dual`](adc12_dual). The results of both ADCs are
+//! packed into the common data register, which can be read by the DMA.
 
 use crate::hal::adc::{Channel, OneShot};
 use crate::hal::blocking::delay::DelayUs;
 
 use core::marker::PhantomData;
 
-#[cfg(feature = "rm0455")]
-use crate::stm32::ADC12_COMMON;
-use crate::stm32::{ADC1, ADC2};
+use crate::stm32::{ADC1, ADC12_COMMON, ADC2};
 #[cfg(not(feature = "rm0455"))]
 use crate::stm32::{ADC3, ADC3_COMMON};
 
@@ -162,6 +203,455 @@ impl AdcCalLinear {
     }
 }
 
+/// Maximum number of conversions in a regular sequence
+pub const MAX_SEQUENCE_LENGTH: usize = 16;
+
+/// A regular sequence of conversions
+///
+/// Each conversion in the sequence has its own sampling time. The same
+/// channel may appear more than once in a sequence, in which case the last
+/// sampling time given for that channel is used.
+pub struct RegularSequence<ADC> {
+    channels: [u8; MAX_SEQUENCE_LENGTH],
+    sample_times: [AdcSampleTime; MAX_SEQUENCE_LENGTH],
+    len: usize,
+    _adc: PhantomData<ADC>,
+}
+
+impl<ADC> RegularSequence<ADC> {
+    /// Create an empty sequence
+    pub fn new() -> Self {
+        Self {
+            channels: [0; MAX_SEQUENCE_LENGTH],
+            sample_times: [AdcSampleTime::default(); MAX_SEQUENCE_LENGTH],
+            len: 0,
+            _adc: PhantomData,
+        }
+    }
+
+    /// Append a conversion of `pin` to the sequence
+    ///
+    /// Panics if the sequence already contains 16 conversions
+    pub fn channel<PIN>(
+        mut self,
+        _pin: &PIN,
+        sample_time: AdcSampleTime,
+    ) -> Self
+    where
+        PIN: Channel<ADC, ID = u8>,
+    {
+        assert!(
+            self.len < MAX_SEQUENCE_LENGTH,
+            "A regular sequence has at most 16 conversions"
+        );
+
+        self.channels[self.len] = PIN::channel();
+        self.sample_times[self.len] = sample_time;
+        self.len += 1;
+        self
+    }
+
+    /// The number of conversions in the sequence
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the sequence contains no conversions
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<ADC> Default for RegularSequence<ADC> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the values of the SQR1 - SQR4 registers for a sequence
+//
+// Refer to RM0433 Rev 7 - Chapter 25.6.11
+fn sequence_registers(channels: &[u8]) -> [u32; 4] {
+    assert!(!channels.is_empty() && channels.len() <= MAX_SEQUENCE_LENGTH);
+
+    // L[3:0] is the sequence length - 1
+    let mut sqr = [channels.len() as u32 - 1, 0, 0, 0];
+
+    for (i, &chan) in channels.iter().enumerate() {
+        // SQ1 - SQ4 follow L in SQR1, then five per register
+        let (reg, pos) = ((i + 1) / 5, (i + 1) % 5);
+        sqr[reg] |= u32::from(chan) << (6 * pos);
+    }
+
+    sqr
+}
+
+/// External trigger for a regular sequence
+///
+/// Only triggers that exist on this part are listed.
+//
+// Refer to RM0433 Rev 7 - Chapter 25.4.19 (Table 213)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AdcExternalTrigger {
+    Tim1Oc1 = 0,
+    Tim1Oc2 = 1,
+    Tim1Oc3 = 2,
+    Tim2Oc2 = 3,
+    Tim3Trgo = 4,
+    Tim4Oc4 = 5,
+    Exti11 = 6,
+    Tim8Trgo = 7,
+    Tim8Trgo2 = 8,
+    Tim1Trgo = 9,
+    Tim1Trgo2 = 10,
+    Tim2Trgo = 11,
+    Tim4Trgo = 12,
+    Tim6Trgo = 13,
+    Tim15Trgo = 14,
+    Tim3Oc4 = 15,
+    #[cfg(not(feature = "rm0455"))]
+    HrtimAdcTrg1 = 16,
+    #[cfg(not(feature = "rm0455"))]
+    HrtimAdcTrg3 = 17,
+    Lptim1Out = 18,
+    Lptim2Out = 19,
+    Lptim3Out = 20,
+}
+
+/// Active edge for an external trigger
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TriggerEdge {
+    /// Rising edge
+    Rising = 0b01,
+    /// Falling edge
+    Falling = 0b10,
+    /// Both rising and falling edges
+    Both = 0b11,
+}
+
+/// Conversion mode for a regular sequence
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RegularTrigger {
+    /// The sequence is converted once each time it is started
+    Single,
+    /// The sequence is converted repeatedly once started, as fast as the
+    /// sampling times allow
+    Continuous,
+    /// Once started, the sequence is converted once on each active edge of
+    /// the external trigger
+    External(AdcExternalTrigger, TriggerEdge),
+}
+
+/// Management of the converted data by the DMA
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AdcDmaMode {
+    /// DMA requests are generated until the DMA transfer is complete, and
+    /// then stop
+    OneShot,
+    /// DMA requests are generated for every conversion. Use this mode with
+    /// circular or double buffered DMA transfers
+    Circular,
+}
+
+// Refer to RM0433 Rev 7 - Chapter 25.4.27
+impl From<AdcDmaMode> for u8 {
+    fn from(val: AdcDmaMode) -> u8 {
+        match val {
+            AdcDmaMode::OneShot => 0b01,
+            AdcDmaMode::Circular => 0b11,
+        }
+    }
+}
+
+/// Maximum number of conversions in an injected sequence
+pub const MAX_INJECTED_LENGTH: usize = 4;
+
+/// An injected sequence of conversions
+///
+/// Injected conversions interrupt the regular sequence when they are
+/// triggered, and their results are held in four separate data registers.
+pub struct InjectedSequence<ADC> {
+    channels: [u8; MAX_INJECTED_LENGTH],
+    sample_times: [AdcSampleTime; MAX_INJECTED_LENGTH],
+    len: usize,
+    _adc: PhantomData<ADC>,
+}
+
+impl<ADC> InjectedSequence<ADC> {
+    /// Create an empty sequence
+    pub fn new() -> Self {
+        Self {
+            channels: [0; MAX_INJECTED_LENGTH],
+            sample_times: [AdcSampleTime::default(); MAX_INJECTED_LENGTH],
+            len: 0,
+            _adc: PhantomData,
+        }
+    }
+
+    /// Append a conversion of `pin` to the sequence
+    ///
+    /// Panics if the sequence already contains 4 conversions
+    pub fn channel<PIN>(
+        mut self,
+        _pin: &PIN,
+        sample_time: AdcSampleTime,
+    ) -> Self
+    where
+        PIN: Channel<ADC, ID = u8>,
+    {
+        assert!(
+            self.len < MAX_INJECTED_LENGTH,
+            "An injected sequence has at most 4 conversions"
+        );
+
+        self.channels[self.len] = PIN::channel();
+        self.sample_times[self.len] = sample_time;
+        self.len += 1;
+        self
+    }
+
+    /// The number of conversions in the sequence
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the sequence contains no conversions
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<ADC> Default for InjectedSequence<ADC> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// External trigger for an injected sequence
+///
+/// Only triggers that exist on this part are listed.
+//
+// Refer to RM0433 Rev 7 - Chapter 25.4.19 (Table 214)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AdcInjectedTrigger {
+    Tim1Trgo = 0,
+    Tim1Oc4 = 1,
+    Tim2Trgo = 2,
+    Tim2Oc1 = 3,
+    Tim3Oc4 = 4,
+    Tim4Trgo = 5,
+    Exti15 = 6,
+    Tim8Oc4 = 7,
+    Tim1Trgo2 = 8,
+    Tim8Trgo = 9,
+    Tim8Trgo2 = 10,
+    Tim3Oc3 = 11,
+    Tim3Trgo = 12,
+    Tim3Oc1 = 13,
+    Tim6Trgo = 14,
+    Tim15Trgo = 15,
+    #[cfg(not(feature = "rm0455"))]
+    HrtimAdcTrg2 = 16,
+    #[cfg(not(feature = "rm0455"))]
+    HrtimAdcTrg4 = 17,
+    Lptim1Out = 18,
+    Lptim2Out = 19,
+    Lptim3Out = 20,
+}
+
+/// Conversion mode for an injected sequence
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InjectedTrigger {
+    /// The sequence is converted once each time it is started
+    Software,
+    /// Once started, the sequence is converted once on each active edge of
+    /// the external trigger
+    External(AdcInjectedTrigger, TriggerEdge),
+}
+
+/// Returns the value of the JSQR register for a sequence
+//
+// Refer to RM0433 Rev 7 - Chapter 25.6.29
+fn injected_sequence_register(
+    channels: &[u8],
+    trigger: InjectedTrigger,
+) -> u32 {
+    assert!(!channels.is_empty() && channels.len() <= MAX_INJECTED_LENGTH);
+
+    // JL[1:0] is the sequence length - 1
+    let jl = channels.len() as u32 - 1;
+    let (jextsel, jexten) = match trigger {
+        InjectedTrigger::Software => (0, 0),
+        InjectedTrigger::External(jextsel, edge) => {
+            (jextsel as u32, edge as u32)
+        }
+    };
+
+    channels
+        .iter()
+        .enumerate()
+        .fold(jl | jextsel << 2 | jexten << 7, |jsqr, (i, &chan)| {
+            jsqr | u32::from(chan) << (9 + 6 * i)
+        })
+}
+
+/// Hardware oversampling
+///
+/// The ADC accumulates `ratio` conversions, and then shifts the sum right by
+/// `shift` bits. The accumulated result can be up to 26 bits wide, so the
+/// result may be larger than [`max_sample`](Adc::max_sample) unless it is
+/// shifted right by enough bits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdcOversampling {
+    ratio: u16,
+    shift: u8,
+}
+
+impl AdcOversampling {
+    /// Oversampling by `ratio` conversions, in the range 1..=1024, with a
+    /// right shift by `shift` bits, in the range 0..=11
+    pub fn new(ratio: u16, shift: u8) -> Self {
+        assert!(
+            (1..=1024).contains(&ratio),
+            "Oversampling ratio must be in range of 1..=1024"
+        );
+        assert!(shift <= 11, "Oversampling shift must be in range of 0..=11");
+
+        AdcOversampling { ratio, shift }
+    }
+
+    /// Oversampling ratio
+    pub fn ratio(self) -> u16 {
+        self.ratio
+    }
+
+    /// Right shift
+    pub fn shift(self) -> u8 {
+        self.shift
+    }
+
+    /// Returns the OVSR and OVSS bits of the CFGR2 register
+    //
+    // Refer to RM0433 Rev 7 - Chapter 25.6.4
+    fn cfgr2_bits(self) -> u32 {
+        u32::from(self.ratio - 1) << 16 | u32::from(self.shift) << 5
+    }
+}
+
+/// Maximum value of the analog watchdog thresholds
+pub const AWD_THRESHOLD_MAX: u32 = 0x03FF_FFFF;
+
+/// Analog watchdogs
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnalogWatchdog {
+    /// Analog watchdog 1 monitors a single channel, or all channels
+    Awd1,
+    /// Analog watchdog 2 monitors any set of channels
+    Awd2,
+    /// Analog watchdog 3 monitors any set of channels
+    Awd3,
+}
+
+/// A set of channels monitored by analog watchdog 2 or 3
+pub struct ChannelMask<ADC> {
+    mask: u32,
+    _adc: PhantomData<ADC>,
+}
+
+impl<ADC> ChannelMask<ADC> {
+    /// Create an empty set of channels
+    pub fn new() -> Self {
+        ChannelMask {
+            mask: 0,
+            _adc: PhantomData,
+        }
+    }
+
+    /// Add the channel of `pin` to the set
+    pub fn channel<PIN>(mut self, _pin: &PIN) -> Self
+    where
+        PIN: Channel<ADC, ID = u8>,
+    {
+        self.mask |= 1 << PIN::channel();
+        self
+    }
+
+    /// Returns the raw channel mask
+    pub fn bits(&self) -> u32 {
+        self.mask
+    }
+}
+
+impl<ADC> Default for ChannelMask<ADC> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// ADC interrupt events
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    /// End of a regular conversion
+    EndOfConversion,
+    /// End of a regular sequence
+    EndOfSequence,
+    /// Regular data overrun
+    Overrun,
+    /// End of an injected conversion
+    InjectedEndOfConversion,
+    /// End of an injected sequence
+    InjectedEndOfSequence,
+    /// Analog watchdog 1 threshold crossed
+    AnalogWatchdog1,
+    /// Analog watchdog 2 threshold crossed
+    AnalogWatchdog2,
+    /// Analog watchdog 3 threshold crossed
+    AnalogWatchdog3,
+}
+
+impl Event {
+    /// Bit position in the IER and ISR registers
+    //
+    // Refer to RM0433 Rev 7 - Chapter 25.6.2
+    fn bit(self) -> u32 {
+        match self {
+            Event::EndOfConversion => 1 << 2,
+            Event::EndOfSequence => 1 << 3,
+            Event::Overrun => 1 << 4,
+            Event::InjectedEndOfConversion => 1 << 5,
+            Event::InjectedEndOfSequence => 1 << 6,
+            Event::AnalogWatchdog1 => 1 << 7,
+            Event::AnalogWatchdog2 => 1 << 8,
+            Event::AnalogWatchdog3 => 1 << 9,
+        }
+    }
+}
+
+impl From<AnalogWatchdog> for Event {
+    fn from(awd: AnalogWatchdog) -> Event {
+        match awd {
+            AnalogWatchdog::Awd1 => Event::AnalogWatchdog1,
+            AnalogWatchdog::Awd2 => Event::AnalogWatchdog2,
+            AnalogWatchdog::Awd3 => Event::AnalogWatchdog3,
+        }
+    }
+}
+
+// CFGR bits for analog watchdog 1
+//
+// Refer to RM0433 Rev 7 - Chapter 25.6.4
+const CFGR_AWD1SGL: u32 = 1 << 22;
+const CFGR_AWD1EN: u32 = 1 << 23;
+const CFGR_JAWD1EN: u32 = 1 << 24;
+const CFGR_AWD1CH_SHIFT: u32 = 26;
+const CFGR_AWD1_MASK: u32 =
+    CFGR_AWD1SGL | CFGR_AWD1EN | CFGR_JAWD1EN | 0x1F << CFGR_AWD1CH_SHIFT;
+
+// CFGR2 bits for oversampling
+const CFGR2_ROVSE: u32 = 1 << 0;
+const CFGR2_JOVSE: u32 = 1 << 1;
+const CFGR2_OVS_MASK: u32 = CFGR2_ROVSE | CFGR2_JOVSE | 0xF << 5 | 0x3FF << 16;
+
 macro_rules! adc_pins {
     ($ADC:ident, $($input:ty => $chan:expr),+ $(,)*) => {
         $(
@@ -394,6 +884,243 @@ pub fn free_adc12<ED>(
     )
 }
 
+/// Dual ADC modes for ADC1 (master) and ADC2 (slave)
+//
+// Refer to RM0433 Rev 7 - Chapter 25.4.32
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DualMode {
+    /// Both ADCs convert their regular sequences simultaneously, on the
+    /// trigger of ADC1
+    RegularSimultaneous,
+    /// Both ADCs convert their injected sequences simultaneously, on the
+    /// trigger of ADC1
+    InjectedSimultaneous,
+    /// Combined regular simultaneous and injected simultaneous modes
+    RegularInjectedSimultaneous,
+    /// ADC2 converts the same channel as ADC1, `delay` + 1 ADC clock cycles
+    /// after ADC1 has finished sampling. `delay` is in the range 0..=15.
+    /// This doubles the sample rate of a single channel
+    Interleaved { delay: u8 },
+}
+
+impl DualMode {
+    /// Returns the DUAL and DELAY bits of the CCR register
+    fn ccr_bits(self) -> u32 {
+        match self {
+            DualMode::RegularInjectedSimultaneous => 0b00001,
+            DualMode::InjectedSimultaneous => 0b00101,
+            DualMode::RegularSimultaneous => 0b00110,
+            DualMode::Interleaved { delay } => {
+                assert!(delay <= 15, "DELAY must be in range of 0..=15");
+                0b00111 | u32::from(delay) << 8
+            }
+        }
+    }
+}
+
+// CCR fields for dual mode
+const CCR_DUAL_MASK: u32 = 0x1F | 0xF << 8 | 0b11 << 14;
+const CCR_DAMDF_SHIFT: u32 = 14;
+
+/// ADC1 and ADC2 operating together in a dual mode
+///
+/// ADC1 is the master and ADC2 is the slave. Conversions are started and
+/// triggered by ADC1. The results of both ADCs are packed into the common
+/// data register: ADC1 in the lower half-word and ADC2 in the upper
+/// half-word (or the lower and upper byte for 8-bit resolution). The common
+/// data register can be read using the DMA, with the ADC1 request line.
+pub struct DualAdc {
+    master: Adc<ADC1, Enabled>,
+    slave: Adc<ADC2, Enabled>,
+    mode: DualMode,
+}
+
+/// Operate ADC1 and ADC2 together in a dual mode
+///
+/// Both ADCs must use the same resolution. Panics if any conversions are
+/// ongoing.
+pub fn adc12_dual(
+    adc1: Adc<ADC1, Enabled>,
+    adc2: Adc<ADC2, Enabled>,
+    mode: DualMode,
+) -> DualAdc {
+    adc1.check_conversion_conditions();
+    adc2.check_conversion_conditions();
+    assert!(
+        adc1.get_resolution() == adc2.get_resolution(),
+        "ADC1 and ADC2 must use the same resolution in dual mode"
+    );
+
+    // Packed data format for the common data register
+    let damdf = match adc1.get_resolution() {
+        Resolution::EIGHTBIT => 0b11,
+        _ => 0b10,
+    };
+
+    // unsafe: ADC1 and ADC2 are owned, and only the dual mode fields are
+    // modified
+    let common = unsafe { &*ADC12_COMMON::ptr() };
+    common.ccr.modify(|r, w| unsafe {
+        w.bits(
+            (r.bits() & !CCR_DUAL_MASK)
+                | mode.ccr_bits()
+                | damdf << CCR_DAMDF_SHIFT,
+        )
+    });
+
+    DualAdc {
+        master: adc1,
+        slave: adc2,
+        mode,
+    }
+}
+
+impl DualAdc {
+    /// The dual mode
+    pub fn mode(&self) -> DualMode {
+        self.mode
+    }
+
+    /// Configure the regular sequences of both ADCs
+    ///
+    /// Both sequences must have the same length. For simultaneous modes,
+    /// the same channel must not be converted by both ADCs at the same
+    /// time. For interleaved mode, both sequences should contain the same
+    /// channels. `dma` configures the DMA requests for the common data
+    /// register.
+    pub fn setup_regular_sequence(
+        &mut self,
+        master: &RegularSequence<ADC1>,
+        slave: &RegularSequence<ADC2>,
+        trigger: RegularTrigger,
+        dma: AdcDmaMode,
+    ) {
+        assert!(
+            master.len() == slave.len(),
+            "Master and slave sequences must have the same length"
+        );
+
+        // The slave trigger is ignored in dual mode
+        self.master.setup_regular_sequence(master, trigger, dma);
+        self.slave.setup_regular_sequence(slave, trigger, dma);
+
+        // DMA requests are generated for the common data register, using the
+        // master request line
+        self.slave
+            .rb
+            .cfgr
+            .modify(|_, w| unsafe { w.dmngt().bits(0b00) });
+    }
+
+    /// Start the regular sequences of both ADCs
+    pub fn start_regular_sequence(&mut self) {
+        // The slave is started by the master
+        self.master.start_regular_sequence();
+    }
+
+    /// Stop the regular sequences of both ADCs
+    pub fn stop_regular_sequence(&mut self) {
+        self.master.stop_regular_sequence();
+        self.slave.stop_regular_sequence();
+    }
+
+    /// Configure the injected sequences of both ADCs
+    ///
+    /// Both sequences must have the same length.
+    pub fn setup_injected_sequence(
+        &mut self,
+        master: &InjectedSequence<ADC1>,
+        slave: &InjectedSequence<ADC2>,
+        trigger: InjectedTrigger,
+    ) {
+        assert!(
+            master.len() == slave.len(),
+            "Master and slave sequences must have the same length"
+        );
+
+        // The slave trigger is ignored in dual mode
+        self.master.setup_injected_sequence(master, trigger);
+        self.slave.setup_injected_sequence(slave, trigger);
+    }
+
+    /// Start the injected sequences of both ADCs
+    pub fn start_injected_sequence(&mut self) {
+        // The slave is started by the master
+        self.master.start_injected_sequence();
+    }
+
+    /// Stop the injected sequences of both ADCs
+    pub fn stop_injected_sequence(&mut self) {
+        self.master.stop_injected_sequence();
+        self.slave.stop_injected_sequence();
+    }
+
+    /// Returns `true` once the injected sequences of both ADCs have
+    /// completed
+    pub fn is_injected_sequence_complete(&self) -> bool {
+        self.master.is_injected_sequence_complete()
+            && self.slave.is_injected_sequence_complete()
+    }
+
+    /// Read the results of conversion `rank` of the injected sequences, in
+    /// the range 0..4. Returns (master, slave)
+    pub fn read_injected(&mut self, rank: usize) -> (u32, u32) {
+        (
+            self.master.read_injected(rank),
+            self.slave.read_injected(rank),
+        )
+    }
+
+    /// Read the common data register. Returns (master, slave)
+    ///
+    /// The common data register holds the last pair of regular conversion
+    /// results.
+    pub fn read_common_data(&self) -> (u32, u32) {
+        // unsafe: read only
+        let cdr = unsafe { (*ADC12_COMMON::ptr()).cdr.read().bits() };
+
+        match self.master.get_resolution() {
+            Resolution::EIGHTBIT => (cdr & 0xFF, (cdr >> 8) & 0xFF),
+            _ => (cdr & 0xFFFF, cdr >> 16),
+        }
+    }
+
+    /// Returns a reference to the master ADC
+    pub fn master(&self) -> &Adc<ADC1, Enabled> {
+        &self.master
+    }
+
+    /// Returns a mutable reference to the master ADC
+    pub fn master_mut(&mut self) -> &mut Adc<ADC1, Enabled> {
+        &mut self.master
+    }
+
+    /// Returns a reference to the slave ADC
+    pub fn slave(&self) -> &Adc<ADC2, Enabled> {
+        &self.slave
+    }
+
+    /// Returns a mutable reference to the slave ADC
+    pub fn slave_mut(&mut self) -> &mut Adc<ADC2, Enabled> {
+        &mut self.slave
+    }
+
+    /// Return to independent mode, and release ADC1 and ADC2
+    pub fn free(mut self) -> (Adc<ADC1, Enabled>, Adc<ADC2, Enabled>) {
+        self.stop_regular_sequence();
+        self.stop_injected_sequence();
+
+        // unsafe: ADC1 and ADC2 are owned, and only the dual mode fields are
+        // modified
+        let common = unsafe { &*ADC12_COMMON::ptr() };
+        common
+            .ccr
+            .modify(|r, w| unsafe { w.bits(r.bits() & !CCR_DUAL_MASK) });
+
+        (self.master, self.slave)
+    }
+}
+
 #[cfg(not(feature = "rm0455"))]
 /// Freeing both the peripheral and PREC is possible for ADC3
 impl<ED> Adc<ADC3, ED> {
@@ -584,28 +1311,29 @@ macro_rules! adc_hal {
                     while self.rb.cr.read().jadstp().bit_is_set() {}
                 }
 
-                fn set_chan_smp(&mut self, chan: u8) {
+                fn set_chan_smp(&mut self, chan: u8, t_samp: AdcSampleTime) {
+                    let smp: u8 = t_samp.into();
                     match chan {
-                        0 => self.rb.smpr1.modify(|_, w| w.smp0().bits(self.get_sample_time().into())),
-                        1 => self.rb.smpr1.modify(|_, w| w.smp1().bits(self.get_sample_time().into())),
-                        2 => self.rb.smpr1.modify(|_, w| w.smp2().bits(self.get_sample_time().into())),
-                        3 => self.rb.smpr1.modify(|_, w| w.smp3().bits(self.get_sample_time().into())),
-                        4 => self.rb.smpr1.modify(|_, w| w.smp4().bits(self.get_sample_time().into())),
-                        5 => self.rb.smpr1.modify(|_, w| w.smp5().bits(self.get_sample_time().into())),
-                        6 => self.rb.smpr1.modify(|_, w| w.smp6().bits(self.get_sample_time().into())),
-                        7 => self.rb.smpr1.modify(|_, w| w.smp7().bits(self.get_sample_time().into())),
-                        8 => self.rb.smpr1.modify(|_, w| w.smp8().bits(self.get_sample_time().into())),
-                        9 => self.rb.smpr1.modify(|_, w| w.smp9().bits(self.get_sample_time().into())),
-                        10 => self.rb.smpr2.modify(|_, w| w.smp10().bits(self.get_sample_time().into())),
-                        11 => self.rb.smpr2.modify(|_, w| w.smp11().bits(self.get_sample_time().into())),
-                        12 => self.rb.smpr2.modify(|_, w| w.smp12().bits(self.get_sample_time().into())),
-                        13 => self.rb.smpr2.modify(|_, w| w.smp13().bits(self.get_sample_time().into())),
-                        14 => self.rb.smpr2.modify(|_, w| w.smp14().bits(self.get_sample_time().into())),
-                        15 => self.rb.smpr2.modify(|_, w| w.smp15().bits(self.get_sample_time().into())),
-                        16 => self.rb.smpr2.modify(|_, w| w.smp16().bits(self.get_sample_time().into())),
-                        17 => self.rb.smpr2.modify(|_, w| w.smp17().bits(self.get_sample_time().into())),
-                        18 => self.rb.smpr2.modify(|_, w| w.smp18().bits(self.get_sample_time().into())),
-                        19 => self.rb.smpr2.modify(|_, w| w.smp19().bits(self.get_sample_time().into())),
+                        0 => self.rb.smpr1.modify(|_, w| w.smp0().bits(smp)),
+                        1 => self.rb.smpr1.modify(|_, w| w.smp1().bits(smp)),
+                        2 => self.rb.smpr1.modify(|_, w| w.smp2().bits(smp)),
+                        3 => self.rb.smpr1.modify(|_, w| w.smp3().bits(smp)),
+                        4 => self.rb.smpr1.modify(|_, w| w.smp4().bits(smp)),
+                        5 => self.rb.smpr1.modify(|_, w| w.smp5().bits(smp)),
+                        6 => self.rb.smpr1.modify(|_, w| w.smp6().bits(smp)),
+                        7 => self.rb.smpr1.modify(|_, w| w.smp7().bits(smp)),
+                        8 => self.rb.smpr1.modify(|_, w| w.smp8().bits(smp)),
+                        9 => self.rb.smpr1.modify(|_, w| w.smp9().bits(smp)),
+                        10 => self.rb.smpr2.modify(|_, w| w.smp10().bits(smp)),
+                        11 => self.rb.smpr2.modify(|_, w| w.smp11().bits(smp)),
+                        12 => self.rb.smpr2.modify(|_, w| w.smp12().bits(smp)),
+                        13 => self.rb.smpr2.modify(|_, w| w.smp13().bits(smp)),
+                        14 => self.rb.smpr2.modify(|_, w| w.smp14().bits(smp)),
+                        15 => self.rb.smpr2.modify(|_, w| w.smp15().bits(smp)),
+                        16 => self.rb.smpr2.modify(|_, w| w.smp16().bits(smp)),
+                        17 => self.rb.smpr2.modify(|_, w| w.smp17().bits(smp)),
+                        18 => self.rb.smpr2.modify(|_, w| w.smp18().bits(smp)),
+                        19 => self.rb.smpr2.modify(|_, w| w.smp19().bits(smp)),
                         _ => unreachable!(),
                     }
                 }
@@ -623,7 +1351,7 @@ macro_rules! adc_hal {
 
                     // Select channel (with preselection, refer to RM0433 Rev 6 - Chapter 24.4.12)
                     self.rb.pcsel.modify(|r, w| unsafe { w.pcsel().bits(r.pcsel().bits() | (1 << chan)) });
-                    self.set_chan_smp(chan);
+                    self.set_chan_smp(chan, self.get_sample_time());
                     self.rb.sqr1.modify(|_, w| unsafe {
                         w.sq1().bits(chan)
                             .l().bits(0)
@@ -643,6 +1371,271 @@ macro_rules! adc_hal {
                     result
                 }
 
+                /// Configure a regular sequence of conversions, with the
+                /// results transferred by the DMA
+                ///
+                /// The current resolution and lshift settings are used. The
+                /// sequence is started by
+                /// [`start_regular_sequence`](#method.start_regular_sequence),
+                /// typically from the closure passed to `Transfer::start`.
+                ///
+                /// Panics if the sequence is empty.
+                pub fn setup_regular_sequence(
+                    &mut self,
+                    sequence: &RegularSequence<$ADC>,
+                    trigger: RegularTrigger,
+                    dma: AdcDmaMode,
+                ) {
+                    self.check_conversion_conditions();
+
+                    let channels = &sequence.channels[..sequence.len];
+                    let sqr = sequence_registers(channels);
+
+                    // Set resolution
+                    self.rb.cfgr.modify(|_, w| unsafe { w.res().bits(self.get_resolution().into()) });
+
+                    // Set LSHIFT[3:0]
+                    self.rb.cfgr2.modify(|_, w| w.lshift().bits(self.get_lshift().value()));
+
+                    // Preselect channels and set sampling times
+                    let mut pcsel = 0;
+                    for (&chan, &t_samp) in channels.iter().zip(sequence.sample_times.iter()) {
+                        pcsel |= 1 << chan;
+                        self.set_chan_smp(chan, t_samp);
+                    }
+                    self.rb.pcsel.modify(|r, w| unsafe { w.pcsel().bits(r.pcsel().bits() | pcsel) });
+
+                    // Sequence
+                    self.rb.sqr1.write(|w| unsafe { w.bits(sqr[0]) });
+                    self.rb.sqr2.write(|w| unsafe { w.bits(sqr[1]) });
+                    self.rb.sqr3.write(|w| unsafe { w.bits(sqr[2]) });
+                    self.rb.sqr4.write(|w| unsafe { w.bits(sqr[3]) });
+
+                    // Conversion mode and DMA management
+                    // Refer to RM0433 Rev 7 - Chapters 25.4.15, 25.4.19, 25.4.27
+                    let (cont, exten, extsel) = match trigger {
+                        RegularTrigger::Single => (false, 0, 0),
+                        RegularTrigger::Continuous => (true, 0, 0),
+                        RegularTrigger::External(extsel, edge) =>
+                            (false, edge as u8, extsel as u8),
+                    };
+                    self.rb.cfgr.modify(|_, w| unsafe {
+                        w.cont().bit(cont)
+                            .discen().clear_bit()
+                            .exten().bits(exten)
+                            .extsel().bits(extsel)
+                            .dmngt().bits(dma.into())
+                    });
+                }
+
+                /// Start the regular sequence configured by
+                /// [`setup_regular_sequence`](#method.setup_regular_sequence)
+                ///
+                /// With an external trigger, conversions start on the next
+                /// active edge of the trigger.
+                pub fn start_regular_sequence(&mut self) {
+                    // Clear any overrun from a previous sequence
+                    self.rb.isr.write(|w| w.ovr().set_bit());
+                    self.rb.cr.modify(|_, w| w.adstart().set_bit());
+                }
+
+                /// Stop the regular sequence, and restore the single
+                /// conversion mode used by [`OneShot`](embedded_hal::adc::OneShot)
+                pub fn stop_regular_sequence(&mut self) {
+                    if self.rb.cr.read().adstart().bit_is_set() {
+                        self.stop_regular_conversion();
+                    }
+
+                    self.rb.cfgr.modify(|_, w| unsafe { w.dmngt().bits(0b00) });
+                    self.rb.pcsel.reset();
+                    self.configure();
+                }
+
+                /// Returns `true` if a conversion result was overwritten
+                /// before it was read. This happens if the DMA cannot keep up
+                /// with the conversions
+                pub fn is_overrun(&self) -> bool {
+                    self.rb.isr.read().ovr().bit_is_set()
+                }
+
+                /// Clear the overrun flag
+                pub fn clear_overrun(&mut self) {
+                    self.rb.isr.write(|w| w.ovr().set_bit());
+                }
+
+                /// Configure an injected sequence of up to 4 conversions
+                ///
+                /// The current resolution and lshift settings are used. The
+                /// sequence is started by
+                /// [`start_injected_sequence`](#method.start_injected_sequence).
+                ///
+                /// Panics if the sequence is empty.
+                pub fn setup_injected_sequence(
+                    &mut self,
+                    sequence: &InjectedSequence<$ADC>,
+                    trigger: InjectedTrigger,
+                ) {
+                    self.check_conversion_conditions();
+
+                    let channels = &sequence.channels[..sequence.len];
+
+                    // Set resolution
+                    self.rb.cfgr.modify(|_, w| unsafe { w.res().bits(self.get_resolution().into()) });
+
+                    // Set LSHIFT[3:0]
+                    self.rb.cfgr2.modify(|_, w| w.lshift().bits(self.get_lshift().value()));
+
+                    // Preselect channels and set sampling times
+                    let mut pcsel = 0;
+                    for (&chan, &t_samp) in channels.iter().zip(sequence.sample_times.iter()) {
+                        pcsel |= 1 << chan;
+                        self.set_chan_smp(chan, t_samp);
+                    }
+                    self.rb.pcsel.modify(|r, w| unsafe { w.pcsel().bits(r.pcsel().bits() | pcsel) });
+
+                    let jsqr = injected_sequence_register(channels, trigger);
+                    self.rb.jsqr.write(|w| unsafe { w.bits(jsqr) });
+                }
+
+                /// Start the injected sequence configured by
+                /// [`setup_injected_sequence`](#method.setup_injected_sequence)
+                ///
+                /// With an external trigger, conversions start on the next
+                /// active edge of the trigger.
+                pub fn start_injected_sequence(&mut self) {
+                    self.rb.isr.write(|w| w.jeos().set_bit().jeoc().set_bit());
+                    self.rb.cr.modify(|_, w| w.jadstart().set_bit());
+                }
+
+                /// Stop the injected sequence
+                pub fn stop_injected_sequence(&mut self) {
+                    if self.rb.cr.read().jadstart().bit_is_set() {
+                        self.stop_injected_conversion();
+                    }
+                }
+
+                /// Returns `true` once all conversions in the injected
+                /// sequence have completed
+                pub fn is_injected_sequence_complete(&self) -> bool {
+                    self.rb.isr.read().jeos().bit_is_set()
+                }
+
+                /// Read the result of conversion `rank` of the injected
+                /// sequence, in the range 0..4. Clears the end of injected
+                /// sequence flag
+                pub fn read_injected(&mut self, rank: usize) -> u32 {
+                    self.rb.isr.write(|w| w.jeos().set_bit());
+
+                    match rank {
+                        0 => self.rb.jdr1.read().bits(),
+                        1 => self.rb.jdr2.read().bits(),
+                        2 => self.rb.jdr3.read().bits(),
+                        3 => self.rb.jdr4.read().bits(),
+                        _ => panic!("An injected sequence has at most 4 conversions"),
+                    }
+                }
+
+                /// Enable hardware oversampling for both regular and injected
+                /// conversions, or disable it with `None`
+                ///
+                /// Note: no conversions may be ongoing
+                pub fn set_oversampling(&mut self, oversampling: Option<AdcOversampling>) {
+                    self.check_conversion_conditions();
+
+                    let bits = match oversampling {
+                        Some(ovs) => ovs.cfgr2_bits() | CFGR2_ROVSE | CFGR2_JOVSE,
+                        None => 0,
+                    };
+                    self.rb.cfgr2.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !CFGR2_OVS_MASK) | bits)
+                    });
+                }
+
+                /// Enable analog watchdog 1 on the channel of `pin`
+                ///
+                /// The watchdog flag is set when a conversion result is
+                /// outside the range `low..=high`. The thresholds are compared
+                /// with the result after oversampling, but before lshift.
+                ///
+                /// Note: no conversions may be ongoing
+                pub fn enable_awd1_channel<PIN>(&mut self, _pin: &PIN, low: u32, high: u32)
+                where
+                    PIN: Channel<$ADC, ID = u8>,
+                {
+                    let awd1ch = u32::from(PIN::channel()) << CFGR_AWD1CH_SHIFT;
+                    self.enable_awd1(CFGR_AWD1SGL | awd1ch, low, high);
+                }
+
+                /// Enable analog watchdog 1 on all channels
+                ///
+                /// The watchdog flag is set when a conversion result is
+                /// outside the range `low..=high`. The thresholds are compared
+                /// with the result after oversampling, but before lshift.
+                ///
+                /// Note: no conversions may be ongoing
+                pub fn enable_awd1_all(&mut self, low: u32, high: u32) {
+                    self.enable_awd1(0, low, high);
+                }
+
+                fn enable_awd1(&mut self, channels: u32, low: u32, high: u32) {
+                    self.check_conversion_conditions();
+                    assert!(low <= high && high <= AWD_THRESHOLD_MAX);
+
+                    self.rb.ltr1.write(|w| unsafe { w.bits(low) });
+                    self.rb.htr1.write(|w| unsafe { w.bits(high) });
+
+                    // Both regular and injected conversions are monitored
+                    self.rb.cfgr.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !CFGR_AWD1_MASK)
+                               | channels | CFGR_AWD1EN | CFGR_JAWD1EN)
+                    });
+                }
+
+                /// Enable analog watchdog 2 on a set of channels
+                ///
+                /// The watchdog flag is set when a conversion result on any
+                /// of the channels is outside the range `low..=high`.
+                ///
+                /// Note: no conversions may be ongoing
+                pub fn enable_awd2(&mut self, channels: ChannelMask<$ADC>, low: u32, high: u32) {
+                    self.check_conversion_conditions();
+                    assert!(low <= high && high <= AWD_THRESHOLD_MAX);
+
+                    self.rb.ltr2.write(|w| unsafe { w.bits(low) });
+                    self.rb.htr2.write(|w| unsafe { w.bits(high) });
+                    self.rb.awd2cr.write(|w| unsafe { w.bits(channels.bits()) });
+                }
+
+                /// Enable analog watchdog 3 on a set of channels
+                ///
+                /// The watchdog flag is set when a conversion result on any
+                /// of the channels is outside the range `low..=high`.
+                ///
+                /// Note: no conversions may be ongoing
+                pub fn enable_awd3(&mut self, channels: ChannelMask<$ADC>, low: u32, high: u32) {
+                    self.check_conversion_conditions();
+                    assert!(low <= high && high <= AWD_THRESHOLD_MAX);
+
+                    self.rb.ltr3.write(|w| unsafe { w.bits(low) });
+                    self.rb.htr3.write(|w| unsafe { w.bits(high) });
+                    self.rb.awd3cr.write(|w| unsafe { w.bits(channels.bits()) });
+                }
+
+                /// Disable an analog watchdog
+                ///
+                /// Note: no conversions may be ongoing
+                pub fn disable_awd(&mut self, awd: AnalogWatchdog) {
+                    self.check_conversion_conditions();
+
+                    match awd {
+                        AnalogWatchdog::Awd1 => self.rb.cfgr.modify(|r, w| unsafe {
+                            w.bits(r.bits() & !CFGR_AWD1_MASK)
+                        }),
+                        AnalogWatchdog::Awd2 => self.rb.awd2cr.reset(),
+                        AnalogWatchdog::Awd3 => self.rb.awd3cr.reset(),
+                    }
+                }
+
                 fn check_conversion_conditions(&self) {
                     // Ensure that no conversions are ongoing
                     if self.rb.cr.read().adstart().bit_is_set() {
@@ -739,6 +1732,36 @@ macro_rules! adc_hal {
                     self.lshift = lshift;
                 }
 
+                /// Start listening for `event`
+                pub fn listen(&mut self, event: Event) {
+                    self.rb.ier.modify(|r, w| unsafe { w.bits(r.bits() | event.bit()) });
+                }
+
+                /// Stop listening for `event`
+                pub fn unlisten(&mut self, event: Event) {
+                    self.rb.ier.modify(|r, w| unsafe { w.bits(r.bits() & !event.bit()) });
+                }
+
+                /// Returns `true` if `event` is pending
+                pub fn is_pending(&self, event: Event) -> bool {
+                    self.rb.isr.read().bits() & event.bit() != 0
+                }
+
+                /// Clears the interrupt flag for `event`
+                pub fn clear_irq(&mut self, event: Event) {
+                    self.rb.isr.write(|w| unsafe { w.bits(event.bit()) });
+                }
+
+                /// Returns a reference to the inner peripheral
+                pub fn inner(&self) -> &$ADC {
+                    &self.rb
+                }
+
+                /// Returns a mutable reference to the inner peripheral
+                pub fn inner_mut(&mut self) -> &mut $ADC {
+                    &mut self.rb
+                }
+
                 /// Returns the largest possible sample value for the current settings
                 pub fn max_sample(&self) -> u32 {
                     ((1 << self.get_resolution().number_of_bits() as u32) - 1) << self.get_lshift().value() as u32
@@ -827,3 +1850,55 @@ adc_hal!(
 
 #[cfg(not(feature = "rm0455"))]
 adc_hal!(ADC3: (adc3, Adc3));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequence() {
+        assert_eq!(sequence_registers(&[5]), [5 << 6, 0, 0, 0]);
+        assert_eq!(
+            sequence_registers(&[1, 2, 3, 4, 5]),
+            [4 | 1 << 6 | 2 << 12 | 3 << 18 | 4 << 24, 5, 0, 0]
+        );
+        let all: [u8; 16] =
+            [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+        assert_eq!(
+            sequence_registers(&all),
+            [
+                15 | 1 << 12 | 2 << 18 | 3 << 24,
+                4 | 5 << 6 | 6 << 12 | 7 << 18 | 8 << 24,
+                9 | 10 << 6 | 11 << 12 | 12 << 18 | 13 << 24,
+                14 | 15 << 6
+            ]
+        );
+    }
+
+    #[test]
+    fn injected_sequence() {
+        assert_eq!(
+            injected_sequence_register(&[7], InjectedTrigger::Software),
+            7 << 9
+        );
+        assert_eq!(
+            injected_sequence_register(
+                &[1, 2, 3, 19],
+                InjectedTrigger::External(
+                    AdcInjectedTrigger::Tim1Trgo2,
+                    TriggerEdge::Rising
+                )
+            ),
+            3 | 8 << 2 | 1 << 7 | 1 << 9 | 2 << 15 | 3 << 21 | 19 << 27
+        );
+    }
+
+    #[test]
+    fn oversampling() {
+        assert_eq!(AdcOversampling::new(1, 0).cfgr2_bits(), 0);
+        assert_eq!(
+            AdcOversampling::new(1024, 10).cfgr2_bits(),
+            1023 << 16 | 10 << 5
+        );
+    }
+}
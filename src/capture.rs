@@ -0,0 +1,832 @@
+//! Input Capture
+//!
+//! Input capture is available on the advanced control timers (`TIM1`,
+//! `TIM8`) and the general purpose timers (`TIM[2-5]`, `TIM[12-17]`). Each
+//! capture channel latches the value of the timer counter when an edge
+//! occurs on its input pin.
+//!
+//! The same pins that can be used for PWM output can be used for input
+//! capture, see the [Pins](../pwm/trait.Pins.html) trait.
+//!
+//! ## Usage
+//!
+//! ```
+//! let pin = gpioa.pa0.into_alternate_af2();
+//!
+//! let mut capture = dp.TIM5
+//!     .capture(pin, ccdr.peripheral.TIM5, &ccdr.clocks)
+//!     .edge(CaptureEdge::Rising)
+//!     .filter(CaptureFilter::CkIntN8)
+//!     .finalize();
+//!
+//! // Raw counter value at the last edge
+//! let ticks = block!(capture.capture()).unwrap();
+//!
+//! // Frequency of the input signal, from the time between two edges
+//! let freq: Hertz = block!(capture.frequency()).unwrap();
+//! ```
+//!
+//! ## PWM input
+//!
+//! Timers with a slave mode controller and at least two channels (`TIM1`,
+//! `TIM8`, `TIM[2-5]`, `TIM12` and `TIM15`) can also measure both the period
+//! and the duty cycle of a PWM signal on channel 1. Channel 1 captures the
+//! period and channel 2 captures the pulse width, and the counter is reset
+//! at the start of every period.
+//!
+//! ```
+//! let mut pwm_input = dp.TIM1
+//!     .pwm_input(gpioa.pa8.into_alternate_af1(), ccdr.peripheral.TIM1, &ccdr.clocks)
+//!     .min_frequency(10.hz())
+//!     .finalize();
+//!
+//! let m = block!(pwm_input.measure()).unwrap();
+//! info!("{} Hz, duty cycle {}", m.frequency.0, m.duty_cycle);
+//! ```
+//!
+//! The lowest measurable frequency is determined by the counter width and
+//! the timer prescaler. It can be set directly with `prescaler`, or
+//! calculated from the lowest expected input frequency with
+//! `min_frequency`. A smaller prescaler gives a higher measurement
+//! resolution.
+
+use core::marker::PhantomData;
+
+use crate::pwm::{Pins, Polarity, C1, C2, C3, C4};
+use crate::rcc::{rec, CoreClocks, ResetEnable};
+use crate::stm32::{
+    TIM1, TIM12, TIM13, TIM14, TIM15, TIM16, TIM17, TIM2, TIM3, TIM4, TIM5,
+    TIM8,
+};
+use crate::time::Hertz;
+use crate::timer::GetClk;
+
+/// Input capture errors
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A capture occurred before the previous one was read, so at least one
+    /// measurement was lost
+    Overcapture,
+    /// The counter overflowed between two edges of the input signal. The
+    /// signal is either absent or slower than the measurement range
+    Overflow,
+}
+
+/// Input capture prescaler
+///
+/// A capture is performed once every N edges on the input
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CapturePrescaler {
+    /// Capture on every edge
+    Div1 = 0b00,
+    /// Capture once every 2 edges
+    Div2 = 0b01,
+    /// Capture once every 4 edges
+    Div4 = 0b10,
+    /// Capture once every 8 edges
+    Div8 = 0b11,
+}
+
+impl CapturePrescaler {
+    /// Number of input edges per capture
+    fn events(self) -> u32 {
+        1 << (self as u32)
+    }
+}
+
+/// Input capture digital filter
+///
+/// The input is sampled at the given frequency, and an edge is only
+/// validated after N consecutive samples at the new level. f<sub>DTS</sub>
+/// is the timer kernel clock divided by CR1.CKD, which is 1 unless dead time
+/// has been configured
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CaptureFilter {
+    /// No filter, sampling at f<sub>DTS</sub>
+    NoFilter = 0b0000,
+    /// f<sub>CK_INT</sub>, N = 2
+    CkIntN2 = 0b0001,
+    /// f<sub>CK_INT</sub>, N = 4
+    CkIntN4 = 0b0010,
+    /// f<sub>CK_INT</sub>, N = 8
+    CkIntN8 = 0b0011,
+    /// f<sub>DTS</sub>/2, N = 6
+    DtsDiv2N6 = 0b0100,
+    /// f<sub>DTS</sub>/2, N = 8
+    DtsDiv2N8 = 0b0101,
+    /// f<sub>DTS</sub>/4, N = 6
+    DtsDiv4N6 = 0b0110,
+    /// f<sub>DTS</sub>/4, N = 8
+    DtsDiv4N8 = 0b0111,
+    /// f<sub>DTS</sub>/8, N = 6
+    DtsDiv8N6 = 0b1000,
+    /// f<sub>DTS</sub>/8, N = 8
+    DtsDiv8N8 = 0b1001,
+    /// f<sub>DTS</sub>/16, N = 5
+    DtsDiv16N5 = 0b1010,
+    /// f<sub>DTS</sub>/16, N = 6
+    DtsDiv16N6 = 0b1011,
+    /// f<sub>DTS</sub>/16, N = 8
+    DtsDiv16N8 = 0b1100,
+    /// f<sub>DTS</sub>/32, N = 5
+    DtsDiv32N5 = 0b1101,
+    /// f<sub>DTS</sub>/32, N = 6
+    DtsDiv32N6 = 0b1110,
+    /// f<sub>DTS</sub>/32, N = 8
+    DtsDiv32N8 = 0b1111,
+}
+
+/// Input capture edge
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CaptureEdge {
+    Rising,
+    Falling,
+    Both,
+}
+
+impl CaptureEdge {
+    /// CCxP and CCxNP bits, relative to CCxE
+    pub(crate) fn ccer_bits(self) -> u32 {
+        match self {
+            CaptureEdge::Rising => 0,
+            CaptureEdge::Falling => CCER_CCXP,
+            CaptureEdge::Both => CCER_CCXP | CCER_CCXNP,
+        }
+    }
+}
+
+// Offsets within the 4-bit CCER field of each channel
+const CCER_CCXE: u32 = 1 << 0;
+const CCER_CCXP: u32 = 1 << 1;
+const CCER_CCXNP: u32 = 1 << 3;
+
+// CCxS values for an input channel
+const CCMR_CCXS_TI_SAME: u32 = 0b01;
+const CCMR_CCXS_TI_OTHER: u32 = 0b10;
+
+// SMCR TS = TI1FP1, SMS = Reset mode
+const SMCR_TS_TI1FP1: u32 = 0b101 << 4;
+const SMCR_SMS_RESET: u32 = 0b100;
+
+// SR bits
+const SR_UIF: u32 = 1 << 0;
+
+/// CCMRx byte for an input capture channel
+fn ccmr_input_bits(
+    ccxs: u32,
+    prescaler: CapturePrescaler,
+    filter: CaptureFilter,
+) -> u32 {
+    ccxs | (prescaler as u32) << 2 | (filter as u32) << 4
+}
+
+/// Calculates the smallest timer prescaler (PSC register value) such that
+/// one period at `min_freq` fits into a counter with maximum value
+/// `counter_max`
+fn calculate_prescaler(clk: u32, min_freq: u32, counter_max: u32) -> u16 {
+    let ticks = u64::from(clk / min_freq.max(1));
+    let range = u64::from(counter_max) + 1;
+
+    // Division factor is (PSC + 1)
+    let div = (ticks + range - 1) / range;
+
+    div.saturating_sub(1).min(u64::from(u16::MAX)) as u16
+}
+
+/// Calculates the frequency of a signal with a period of `ticks` counts of
+/// a counter running at `tick_hz`. Rounds to the nearest Hertz
+fn calculate_frequency(tick_hz: u32, ticks: u32) -> Option<Hertz> {
+    if ticks == 0 {
+        return None;
+    }
+
+    Some(Hertz((tick_hz + ticks / 2) / ticks))
+}
+
+/// Calculates a duty cycle as a fraction of the period, from 0.0 to 1.0
+fn calculate_duty_cycle(pulse: u32, period: u32) -> Option<f32> {
+    if period == 0 {
+        return None;
+    }
+
+    Some((pulse.min(period) as f32) / (period as f32))
+}
+
+/// Result of a PWM input measurement
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PwmMeasurement {
+    /// Frequency of the input signal
+    pub frequency: Hertz,
+    /// Fraction of the period that the input signal is active, from 0.0
+    /// to 1.0
+    pub duty_cycle: f32,
+}
+
+/// Configures an input capture channel
+pub struct CaptureBuilder<TIM, CHANNEL> {
+    tim: TIM,
+    _channel: PhantomData<CHANNEL>,
+    clk: u32,
+    timer_prescaler: Option<u16>,
+    min_frequency: Option<Hertz>,
+    prescaler: CapturePrescaler,
+    filter: CaptureFilter,
+    edge: CaptureEdge,
+}
+
+/// An input capture channel
+pub struct Capture<TIM, CHANNEL> {
+    tim: TIM,
+    _channel: PhantomData<CHANNEL>,
+    tick_hz: u32,
+    events: u32,
+    last: Option<u32>,
+}
+
+/// Configures PWM input mode
+pub struct PwmInputBuilder<TIM> {
+    tim: TIM,
+    clk: u32,
+    timer_prescaler: Option<u16>,
+    min_frequency: Option<Hertz>,
+    filter: CaptureFilter,
+    polarity: Polarity,
+}
+
+/// A timer in PWM input mode
+pub struct PwmInput<TIM> {
+    tim: TIM,
+    tick_hz: u32,
+}
+
+/// Allows the capture() method to be added to the peripheral register
+/// structs from the device crate
+pub trait CaptureExt: Sized {
+    type Rec: ResetEnable;
+
+    /// Configures a timer channel for input capture. Only a single pin may
+    /// be passed
+    fn capture<PIN, CHANNEL, COMP>(
+        self,
+        _pin: PIN,
+        prec: Self::Rec,
+        clocks: &CoreClocks,
+    ) -> CaptureBuilder<Self, CHANNEL>
+    where
+        PIN: Pins<Self, CHANNEL, COMP>;
+}
+
+/// Allows the pwm_input() method to be added to the peripheral register
+/// structs from the device crate
+pub trait PwmInputExt: Sized {
+    type Rec: ResetEnable;
+
+    /// Configures a timer to measure the period and duty cycle of a PWM
+    /// signal on its channel 1 pin
+    fn pwm_input<PIN, COMP>(
+        self,
+        _pin: PIN,
+        prec: Self::Rec,
+        clocks: &CoreClocks,
+    ) -> PwmInputBuilder<Self>
+    where
+        PIN: Pins<Self, C1, COMP>;
+}
+
+macro_rules! capture_hal {
+    ($($TIMX:ident: ($Rec:ident, $typ:ty),)+) => {
+        $(
+            impl CaptureExt for $TIMX {
+                type Rec = rec::$Rec;
+
+                fn capture<PIN, CHANNEL, COMP>(
+                    self,
+                    _pin: PIN,
+                    prec: Self::Rec,
+                    clocks: &CoreClocks,
+                ) -> CaptureBuilder<Self, CHANNEL>
+                where
+                    PIN: Pins<Self, CHANNEL, COMP>,
+                {
+                    prec.enable().reset();
+
+                    let clk = $TIMX::get_clk(clocks)
+                        .expect("Timer input clock not running!")
+                        .0;
+
+                    CaptureBuilder {
+                        tim: self,
+                        _channel: PhantomData,
+                        clk,
+                        timer_prescaler: None,
+                        min_frequency: None,
+                        prescaler: CapturePrescaler::Div1,
+                        filter: CaptureFilter::NoFilter,
+                        edge: CaptureEdge::Rising,
+                    }
+                }
+            }
+
+            impl<CHANNEL> CaptureBuilder<$TIMX, CHANNEL> {
+                /// Set the timer prescaler; the counter runs at
+                /// kernel_clock/(prescaler+1). Overrides `min_frequency`
+                pub fn prescaler(mut self, prescaler: u16) -> Self {
+                    self.timer_prescaler = Some(prescaler);
+                    self
+                }
+
+                /// Choose the timer prescaler so that the period of the
+                /// slowest expected input signal fits in the counter
+                pub fn min_frequency<T: Into<Hertz>>(mut self, freq: T) -> Self {
+                    self.min_frequency = Some(freq.into());
+                    self
+                }
+
+                /// Set the input capture prescaler
+                pub fn input_prescaler(mut self, prescaler: CapturePrescaler) -> Self {
+                    self.prescaler = prescaler;
+                    self
+                }
+
+                /// Set the input digital filter
+                pub fn filter(mut self, filter: CaptureFilter) -> Self {
+                    self.filter = filter;
+                    self
+                }
+
+                /// Set the edge that triggers a capture
+                pub fn edge(mut self, edge: CaptureEdge) -> Self {
+                    self.edge = edge;
+                    self
+                }
+
+                /// Returns the timer prescaler to use
+                fn timer_prescaler(&self) -> u16 {
+                    match (self.timer_prescaler, self.min_frequency) {
+                        (Some(psc), _) => psc,
+                        (None, Some(freq)) => calculate_prescaler(
+                            self.clk, freq.0, <$typ>::MAX as u32),
+                        (None, None) => 0,
+                    }
+                }
+            }
+
+            impl<CHANNEL> Capture<$TIMX, CHANNEL> {
+                /// Returns the frequency that the counter runs at. This is
+                /// the resolution of the capture values
+                pub fn tick_frequency(&self) -> Hertz {
+                    Hertz(self.tick_hz)
+                }
+
+                /// Releases the TIM peripheral
+                pub fn free(self) -> $TIMX {
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.tim
+                }
+            }
+
+            impl PwmInputBuilder<$TIMX> {
+                /// Set the timer prescaler; the counter runs at
+                /// kernel_clock/(prescaler+1). Overrides `min_frequency`
+                pub fn prescaler(mut self, prescaler: u16) -> Self {
+                    self.timer_prescaler = Some(prescaler);
+                    self
+                }
+
+                /// Choose the timer prescaler so that the period of the
+                /// slowest expected input signal fits in the counter
+                pub fn min_frequency<T: Into<Hertz>>(mut self, freq: T) -> Self {
+                    self.min_frequency = Some(freq.into());
+                    self
+                }
+
+                /// Set the input digital filter
+                pub fn filter(mut self, filter: CaptureFilter) -> Self {
+                    self.filter = filter;
+                    self
+                }
+
+                /// Set the polarity of the input signal. For an active high
+                /// signal the period starts at the rising edge and the duty
+                /// cycle is the fraction of time that the input is high
+                pub fn polarity(mut self, polarity: Polarity) -> Self {
+                    self.polarity = polarity;
+                    self
+                }
+            }
+        )+
+    }
+}
+
+macro_rules! capture_channel_hal {
+    ($($TIMX:ident: ($CH:ty, $index:expr, $ccmrx_output:ident, $ccrx:ident, $typ:ty),)+) => {
+        $(
+            impl CaptureBuilder<$TIMX, $CH> {
+                /// Configures the timer and starts capturing
+                pub fn finalize(self) -> Capture<$TIMX, $CH> {
+                    let psc = self.timer_prescaler();
+                    let tim = self.tim;
+
+                    tim.psc.write(|w| w.psc().bits(psc));
+                    tim.arr.write(|w| unsafe { w.bits(<$typ>::MAX as u32) });
+
+                    // Map ICx to TIx, with prescaler and filter
+                    let shift = 8 * (($index - 1) % 2);
+                    let ccmr = ccmr_input_bits(CCMR_CCXS_TI_SAME, self.prescaler, self.filter);
+                    tim.$ccmrx_output().modify(|r, w| unsafe {
+                        w.bits((r.bits() & !(0xFF << shift)) | ccmr << shift)
+                    });
+
+                    // Edge selection and capture enable
+                    let shift = 4 * ($index - 1);
+                    let ccer = self.edge.ccer_bits() | CCER_CCXE;
+                    tim.ccer.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !(0xF << shift)) | ccer << shift)
+                    });
+
+                    // Generate an update event to load the prescaler
+                    tim.egr.write(|w| w.ug().set_bit());
+                    tim.cr1.modify(|_, w| w.cen().set_bit());
+
+                    Capture {
+                        tim,
+                        _channel: PhantomData,
+                        tick_hz: self.clk / (u32::from(psc) + 1),
+                        events: self.prescaler.events(),
+                        last: None,
+                    }
+                }
+            }
+
+            impl Capture<$TIMX, $CH> {
+                /// Returns the counter value at the most recent capture
+                ///
+                /// Returns `WouldBlock` if there has not been a new capture
+                /// since the previous call
+                pub fn capture(&mut self) -> nb::Result<$typ, Error> {
+                    let sr = self.tim.sr.read().bits();
+
+                    if sr & (1 << $index) == 0 {
+                        return Err(nb::Error::WouldBlock);
+                    }
+
+                    // Reading CCRx clears CCxIF
+                    let value = self.tim.$ccrx.read().bits() as $typ;
+
+                    if sr & (1 << ($index + 8)) != 0 {
+                        // Clear CCxOF
+                        self.tim.sr.write(|w| unsafe { w.bits(!(1 << ($index + 8))) });
+                        self.last = None;
+                        return Err(nb::Error::Other(Error::Overcapture));
+                    }
+
+                    Ok(value)
+                }
+
+                /// Returns the frequency of the input signal, calculated
+                /// from the time between the two most recent captures
+                ///
+                /// Returns `WouldBlock` until two consecutive captures have
+                /// been made. Signals with a period longer than the counter
+                /// range give incorrect results
+                pub fn frequency(&mut self) -> nb::Result<Hertz, Error> {
+                    let value = self.capture()?;
+                    let previous = self.last.replace(value as u32);
+
+                    let ticks = match previous {
+                        Some(previous) => (value.wrapping_sub(previous as $typ)) as u32,
+                        None => return Err(nb::Error::WouldBlock),
+                    };
+
+                    // Multiple input edges per capture if the input
+                    // prescaler is used
+                    let tick_hz = self.tick_hz.saturating_mul(self.events);
+
+                    calculate_frequency(tick_hz, ticks)
+                        .ok_or(nb::Error::WouldBlock)
+                }
+
+                /// Enable the capture interrupt
+                pub fn listen(&mut self) {
+                    self.tim.dier.modify(|r, w| unsafe {
+                        w.bits(r.bits() | 1 << $index)
+                    });
+                }
+
+                /// Disable the capture interrupt
+                pub fn unlisten(&mut self) {
+                    self.tim.dier.modify(|r, w| unsafe {
+                        w.bits(r.bits() & !(1 << $index))
+                    });
+                    let _ = self.tim.dier.read();
+                    let _ = self.tim.dier.read(); // Delay 2 peripheral clocks
+                }
+
+                /// Returns true if a capture is waiting to be read
+                pub fn is_pending(&self) -> bool {
+                    self.tim.sr.read().bits() & (1 << $index) != 0
+                }
+
+                /// Clears the capture and overcapture flags, and discards
+                /// the stored capture used for frequency measurement
+                pub fn clear_irq(&mut self) {
+                    self.tim.sr.write(|w| unsafe {
+                        w.bits(!(1 << $index | 1 << ($index + 8)))
+                    });
+                    self.last = None;
+                    let _ = self.tim.sr.read();
+                    let _ = self.tim.sr.read(); // Delay 2 peripheral clocks
+                }
+            }
+        )+
+    }
+}
+
+macro_rules! pwm_input_hal {
+    ($($TIMX:ident: ($Rec:ident, $typ:ty),)+) => {
+        $(
+            impl PwmInputExt for $TIMX {
+                type Rec = rec::$Rec;
+
+                fn pwm_input<PIN, COMP>(
+                    self,
+                    _pin: PIN,
+                    prec: Self::Rec,
+                    clocks: &CoreClocks,
+                ) -> PwmInputBuilder<Self>
+                where
+                    PIN: Pins<Self, C1, COMP>,
+                {
+                    prec.enable().reset();
+
+                    let clk = $TIMX::get_clk(clocks)
+                        .expect("Timer input clock not running!")
+                        .0;
+
+                    PwmInputBuilder {
+                        tim: self,
+                        clk,
+                        timer_prescaler: None,
+                        min_frequency: None,
+                        filter: CaptureFilter::NoFilter,
+                        polarity: Polarity::ActiveHigh,
+                    }
+                }
+            }
+
+            impl PwmInputBuilder<$TIMX> {
+                /// Configures the timer and starts measuring
+                pub fn finalize(self) -> PwmInput<$TIMX> {
+                    let psc = match (self.timer_prescaler, self.min_frequency) {
+                        (Some(psc), _) => psc,
+                        (None, Some(freq)) => calculate_prescaler(
+                            self.clk, freq.0, <$typ>::MAX as u32),
+                        (None, None) => 0,
+                    };
+                    let tim = self.tim;
+
+                    tim.psc.write(|w| w.psc().bits(psc));
+                    tim.arr.write(|w| unsafe { w.bits(<$typ>::MAX as u32) });
+
+                    // IC1 and IC2 are both mapped to TI1
+                    let ic1 = ccmr_input_bits(CCMR_CCXS_TI_SAME, CapturePrescaler::Div1, self.filter);
+                    let ic2 = ccmr_input_bits(CCMR_CCXS_TI_OTHER, CapturePrescaler::Div1, self.filter);
+                    tim.ccmr1_output().write(|w| unsafe { w.bits(ic1 | ic2 << 8) });
+
+                    // IC1 captures the period on the active edge, IC2
+                    // captures the pulse width on the opposite edge
+                    let (cc1, cc2) = match self.polarity {
+                        Polarity::ActiveHigh => (CaptureEdge::Rising, CaptureEdge::Falling),
+                        Polarity::ActiveLow => (CaptureEdge::Falling, CaptureEdge::Rising),
+                    };
+                    let ccer = (cc1.ccer_bits() | CCER_CCXE) | (cc2.ccer_bits() | CCER_CCXE) << 4;
+                    tim.ccer.write(|w| unsafe { w.bits(ccer) });
+
+                    // Reset the counter on the active edge of TI1
+                    tim.smcr.write(|w| unsafe { w.bits(SMCR_TS_TI1FP1 | SMCR_SMS_RESET) });
+
+                    // Generate an update event to load the prescaler. With
+                    // URS set, only counter overflows set the update flag,
+                    // not the slave mode resets
+                    tim.cr1.modify(|_, w| w.urs().set_bit());
+                    tim.egr.write(|w| w.ug().set_bit());
+                    tim.sr.write(|w| unsafe { w.bits(!SR_UIF) });
+                    tim.cr1.modify(|_, w| w.cen().set_bit());
+
+                    PwmInput {
+                        tim,
+                        tick_hz: self.clk / (u32::from(psc) + 1),
+                    }
+                }
+            }
+
+            impl PwmInput<$TIMX> {
+                /// Returns the frequency that the counter runs at. This is
+                /// the resolution of the measurements
+                pub fn tick_frequency(&self) -> Hertz {
+                    Hertz(self.tick_hz)
+                }
+
+                /// Returns the period of the most recently measured cycle,
+                /// in counter ticks
+                pub fn period_ticks(&self) -> $typ {
+                    self.tim.ccr1.read().bits() as $typ
+                }
+
+                /// Returns the pulse width of the most recently measured
+                /// cycle, in counter ticks
+                pub fn pulse_ticks(&self) -> $typ {
+                    self.tim.ccr2.read().bits() as $typ
+                }
+
+                /// Returns the frequency and duty cycle of the input signal
+                ///
+                /// Returns `WouldBlock` until a new period has been
+                /// measured since the previous call
+                pub fn measure(&mut self) -> nb::Result<PwmMeasurement, Error> {
+                    let sr = self.tim.sr.read().bits();
+
+                    if sr & SR_UIF != 0 {
+                        // The counter overflowed before the end of a period
+                        self.tim.sr.write(|w| unsafe { w.bits(!(SR_UIF | 0b11 << 1)) });
+                        return Err(nb::Error::Other(Error::Overflow));
+                    }
+                    if sr & (1 << 1) == 0 {
+                        return Err(nb::Error::WouldBlock);
+                    }
+
+                    // Reading CCR1 clears CC1IF
+                    let pulse = self.pulse_ticks() as u32;
+                    let period = self.period_ticks() as u32;
+
+                    if sr & (1 << 9) != 0 {
+                        // Clear CC1OF
+                        self.tim.sr.write(|w| unsafe { w.bits(!(1 << 9)) });
+                        return Err(nb::Error::Other(Error::Overcapture));
+                    }
+
+                    match (calculate_frequency(self.tick_hz, period),
+                           calculate_duty_cycle(pulse, period)) {
+                        (Some(frequency), Some(duty_cycle)) => {
+                            Ok(PwmMeasurement { frequency, duty_cycle })
+                        }
+                        _ => Err(nb::Error::WouldBlock),
+                    }
+                }
+
+                /// Enable the interrupt at the end of each measured period
+                pub fn listen(&mut self) {
+                    self.tim.dier.modify(|r, w| unsafe { w.bits(r.bits() | 1 << 1) });
+                }
+
+                /// Disable the interrupt at the end of each measured period
+                pub fn unlisten(&mut self) {
+                    self.tim.dier.modify(|r, w| unsafe { w.bits(r.bits() & !(1 << 1)) });
+                    let _ = self.tim.dier.read();
+                    let _ = self.tim.dier.read(); // Delay 2 peripheral clocks
+                }
+
+                /// Returns true if a new period has been measured
+                pub fn is_pending(&self) -> bool {
+                    self.tim.sr.read().bits() & (1 << 1) != 0
+                }
+
+                /// Releases the TIM peripheral
+                pub fn free(self) -> $TIMX {
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.tim.smcr.reset();
+                    self.tim
+                }
+            }
+        )+
+    }
+}
+
+capture_hal! {
+    TIM1: (Tim1, u16),
+    TIM2: (Tim2, u32),
+    TIM3: (Tim3, u16),
+    TIM4: (Tim4, u16),
+    TIM5: (Tim5, u32),
+    TIM8: (Tim8, u16),
+    TIM12: (Tim12, u16),
+    TIM13: (Tim13, u16),
+    TIM14: (Tim14, u16),
+    TIM15: (Tim15, u16),
+    TIM16: (Tim16, u16),
+    TIM17: (Tim17, u16),
+}
+
+// Quad channel timers
+capture_channel_hal! {
+    TIM1: (C1, 1, ccmr1_output, ccr1, u16),
+    TIM1: (C2, 2, ccmr1_output, ccr2, u16),
+    TIM1: (C3, 3, ccmr2_output, ccr3, u16),
+    TIM1: (C4, 4, ccmr2_output, ccr4, u16),
+    TIM2: (C1, 1, ccmr1_output, ccr1, u32),
+    TIM2: (C2, 2, ccmr1_output, ccr2, u32),
+    TIM2: (C3, 3, ccmr2_output, ccr3, u32),
+    TIM2: (C4, 4, ccmr2_output, ccr4, u32),
+    TIM3: (C1, 1, ccmr1_output, ccr1, u16),
+    TIM3: (C2, 2, ccmr1_output, ccr2, u16),
+    TIM3: (C3, 3, ccmr2_output, ccr3, u16),
+    TIM3: (C4, 4, ccmr2_output, ccr4, u16),
+    TIM4: (C1, 1, ccmr1_output, ccr1, u16),
+    TIM4: (C2, 2, ccmr1_output, ccr2, u16),
+    TIM4: (C3, 3, ccmr2_output, ccr3, u16),
+    TIM4: (C4, 4, ccmr2_output, ccr4, u16),
+    TIM5: (C1, 1, ccmr1_output, ccr1, u32),
+    TIM5: (C2, 2, ccmr1_output, ccr2, u32),
+    TIM5: (C3, 3, ccmr2_output, ccr3, u32),
+    TIM5: (C4, 4, ccmr2_output, ccr4, u32),
+    TIM8: (C1, 1, ccmr1_output, ccr1, u16),
+    TIM8: (C2, 2, ccmr1_output, ccr2, u16),
+    TIM8: (C3, 3, ccmr2_output, ccr3, u16),
+    TIM8: (C4, 4, ccmr2_output, ccr4, u16),
+}
+// Dual channel timers
+capture_channel_hal! {
+    TIM12: (C1, 1, ccmr1_output, ccr1, u16),
+    TIM12: (C2, 2, ccmr1_output, ccr2, u16),
+    TIM15: (C1, 1, ccmr1_output, ccr1, u16),
+    TIM15: (C2, 2, ccmr1_output, ccr2, u16),
+}
+// Single channel timers
+capture_channel_hal! {
+    TIM13: (C1, 1, ccmr1_output, ccr1, u16),
+    TIM14: (C1, 1, ccmr1_output, ccr1, u16),
+    TIM16: (C1, 1, ccmr1_output, ccr1, u16),
+    TIM17: (C1, 1, ccmr1_output, ccr1, u16),
+}
+
+// Timers with a slave mode controller and two channels
+pwm_input_hal! {
+    TIM1: (Tim1, u16),
+    TIM2: (Tim2, u32),
+    TIM3: (Tim3, u16),
+    TIM4: (Tim4, u16),
+    TIM5: (Tim5, u32),
+    TIM8: (Tim8, u16),
+    TIM12: (Tim12, u16),
+    TIM15: (Tim15, u16),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prescaler() {
+        // 200MHz, 16-bit counter. 10Hz is 20M ticks
+        assert_eq!(calculate_prescaler(200_000_000, 10, 0xFFFF), 305);
+        // Fits without prescaler
+        assert_eq!(calculate_prescaler(200_000_000, 5_000, 0xFFFF), 0);
+        assert_eq!(calculate_prescaler(65_536, 1, 0xFFFF), 0);
+        assert_eq!(calculate_prescaler(65_537, 1, 0xFFFF), 1);
+        // 32-bit counter
+        assert_eq!(calculate_prescaler(200_000_000, 1, 0xFFFF_FFFF), 0);
+        // Zero frequency treated as 1Hz
+        assert_eq!(calculate_prescaler(200_000_000, 0, 0xFFFF), 3051);
+        // Saturates
+        assert_eq!(calculate_prescaler(u32::MAX, 1, 0xFF), u16::MAX);
+    }
+
+    #[test]
+    fn frequency() {
+        assert_eq!(calculate_frequency(1_000_000, 0), None);
+        assert_eq!(calculate_frequency(1_000_000, 1000), Some(Hertz(1000)));
+        // Rounded to nearest
+        assert_eq!(calculate_frequency(1_000_000, 3), Some(Hertz(333_333)));
+        assert_eq!(calculate_frequency(1_000_000, 6), Some(Hertz(166_667)));
+    }
+
+    #[test]
+    fn duty_cycle() {
+        assert_eq!(calculate_duty_cycle(0, 0), None);
+        assert_eq!(calculate_duty_cycle(0, 100), Some(0.0));
+        assert_eq!(calculate_duty_cycle(25, 100), Some(0.25));
+        assert_eq!(calculate_duty_cycle(100, 100), Some(1.0));
+        assert_eq!(calculate_duty_cycle(101, 100), Some(1.0));
+    }
+
+    #[test]
+    fn ccmr_bits() {
+        assert_eq!(
+            ccmr_input_bits(
+                CCMR_CCXS_TI_SAME,
+                CapturePrescaler::Div1,
+                CaptureFilter::NoFilter
+            ),
+            0b01
+        );
+        assert_eq!(
+            ccmr_input_bits(
+                CCMR_CCXS_TI_OTHER,
+                CapturePrescaler::Div8,
+                CaptureFilter::DtsDiv32N8
+            ),
+            0xFE
+        );
+    }
+}
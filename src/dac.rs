@@ -9,23 +9,27 @@ use crate::gpio::gpioa::{PA4, PA5};
 
 use crate::gpio::Analog;
 use crate::hal::blocking::delay::DelayUs;
-use crate::rcc::{rec, ResetEnable};
+use crate::rcc::{rec, CoreClocks, ResetEnable};
 #[cfg(not(feature = "rm0455"))]
 use crate::stm32::DAC as DAC1;
 #[cfg(feature = "rm0455")]
 use crate::stm32::{DAC1, DAC2};
+use crate::time::MicroSeconds;
 use crate::traits::DacOut;
 
 /// Enabled DAC (type state)
 pub struct Enabled;
 /// Enabled DAC without output buffer (type state)
 pub struct EnabledUnbuffered;
+/// Enabled DAC in sample and hold mode (type state)
+pub struct EnabledSampleHold;
 /// Disabled DAC (type state)
 pub struct Disabled;
 
 pub trait ED {}
 impl ED for Enabled {}
 impl ED for EnabledUnbuffered {}
+impl ED for EnabledSampleHold {}
 impl ED for Disabled {}
 
 pub struct C1<DAC, ED> {
@@ -63,6 +67,118 @@ impl Pins<DAC2> for PA6<Analog> {
     type Output = C1<DAC2, Disabled>;
 }
 
+/// Trigger for the transfer of the data holding register to the output
+//
+// Refer to RM0433 Rev 7 - Chapter 26.4.7 (Table 218)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Trigger {
+    /// Software trigger, see [`software_trigger`](C1::software_trigger)
+    Software = 0,
+    Tim1Trgo = 1,
+    Tim2Trgo = 2,
+    Tim4Trgo = 3,
+    Tim5Trgo = 4,
+    Tim6Trgo = 5,
+    Tim7Trgo = 6,
+    Tim8Trgo = 7,
+    Tim15Trgo = 8,
+    #[cfg(not(feature = "rm0455"))]
+    HrtimDacTrg1 = 9,
+    #[cfg(not(feature = "rm0455"))]
+    HrtimDacTrg2 = 10,
+    Lptim1Out = 11,
+    Lptim2Out = 12,
+    Exti9 = 13,
+}
+
+/// Waveform generation
+///
+/// The generated waveform is added to the value in the data holding
+/// register on each trigger. A trigger must be selected.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Waveform {
+    /// No waveform generation
+    Disabled,
+    /// Pseudo-random noise from a linear feedback shift register. The
+    /// lowest `bits` bits of the LFSR are used, in the range 1..=12
+    Noise { bits: u8 },
+    /// Triangle wave with an amplitude of 2^`bits` - 1, with `bits` in the
+    /// range 1..=12
+    Triangle { bits: u8 },
+}
+
+impl Waveform {
+    /// Returns the WAVE and MAMP bits of the CR register, for channel 1
+    fn cr_bits(self) -> u32 {
+        let (wave, bits) = match self {
+            Waveform::Disabled => return 0,
+            Waveform::Noise { bits } => (0b01, bits),
+            Waveform::Triangle { bits } => (0b10, bits),
+        };
+        assert!((1..=12).contains(&bits), "Amplitude must be 1..=12 bits");
+
+        wave << CR_WAVE_SHIFT | u32::from(bits - 1) << CR_MAMP_SHIFT
+    }
+}
+
+// CR bits, for channel 1. Channel 2 bits are shifted by 16
+//
+// Refer to RM0433 Rev 7 - Chapter 26.7.1
+const CR_TEN: u32 = 1 << 1;
+const CR_TSEL_SHIFT: u32 = 2;
+const CR_WAVE_SHIFT: u32 = 6;
+const CR_MAMP_SHIFT: u32 = 8;
+const CR_DMAEN: u32 = 1 << 12;
+const CR_DMAUDRIE: u32 = 1 << 13;
+const CR_TRIGGER_MASK: u32 = CR_TEN | 0xF << CR_TSEL_SHIFT;
+const CR_WAVE_MASK: u32 = 0b11 << CR_WAVE_SHIFT | 0xF << CR_MAMP_SHIFT;
+
+// SR bits, for channel 1
+const SR_DMAUDR: u32 = 1 << 13;
+
+/// Sample and hold timing
+///
+/// In sample and hold mode, the output buffer charges an external
+/// capacitor during the sample time, and is then switched off for the hold
+/// time. The output is refreshed periodically to compensate for leakage.
+/// The timing is clocked by the LSI, so sample and hold mode continues in
+/// Stop mode.
+///
+/// All times are rounded up to a whole number of LSI cycles.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SampleHoldTiming {
+    /// Sample time. At least one LSI cycle, at most 1023 LSI cycles
+    pub sample: MicroSeconds,
+    /// Hold time. At most 1023 LSI cycles
+    pub hold: MicroSeconds,
+    /// Refresh time. At most 255 LSI cycles
+    pub refresh: MicroSeconds,
+}
+
+/// Returns the TSAMPLE, THOLD and TREFRESH values for `timing` with an LSI
+/// frequency of `lsi_hz`
+//
+// Refer to RM0433 Rev 7 - Chapter 26.4.12
+fn sample_hold_cycles(
+    lsi_hz: u32,
+    timing: &SampleHoldTiming,
+) -> Option<(u16, u16, u8)> {
+    // cycles = us * Hz / 1e6, rounded up
+    let cycles = |us: MicroSeconds| {
+        (u64::from(us.0) * u64::from(lsi_hz) + 999_999) / 1_000_000
+    };
+
+    let sample = cycles(timing.sample).max(1);
+    let hold = cycles(timing.hold);
+    let refresh = cycles(timing.refresh);
+
+    if sample > 0x3FF || hold > 0x3FF || refresh > 0xFF {
+        None
+    } else {
+        Some((sample as u16, hold as u16, refresh as u8))
+    }
+}
+
 pub fn dac<PINS, DAC, REC: ResetEnable>(
     _dac: DAC,
     _pins: PINS,
@@ -82,7 +198,8 @@ where
 
 macro_rules! dac {
     ($DAC:ident, $CX:ident, $en:ident, $cen:ident, $cal_flag:ident, $trim:ident,
-     $mode:ident, $dhrx:ident, $dor:ident, $daccxdhr:ident) => {
+     $mode:ident, $dhrx:ident, $dor:ident, $daccxdhr:ident, $shsrx:ident,
+     $shift:expr) => {
         impl $CX<$DAC, Disabled> {
             pub fn enable(self) -> $CX<$DAC, Enabled> {
                 let dac = unsafe { &(*$DAC::ptr()) };
@@ -107,6 +224,77 @@ macro_rules! dac {
                     _enabled: PhantomData,
                 }
             }
+
+            /// Enable the DAC channel in sample and hold mode, with the
+            /// output buffer enabled
+            ///
+            /// Panics if the LSI is not running (see
+            /// [use_lsi](crate::rcc::Rcc::use_lsi)), or if the timing cannot
+            /// be represented with the LSI frequency
+            pub fn enable_sample_hold(
+                self,
+                timing: SampleHoldTiming,
+                clocks: &CoreClocks,
+            ) -> $CX<$DAC, EnabledSampleHold> {
+                let dac = unsafe { &(*$DAC::ptr()) };
+
+                let lsi = clocks.lsi_ck().expect("LSI is not running!");
+                let (sample, hold, refresh) =
+                    sample_hold_cycles(lsi.0, &timing)
+                        .expect("Sample and hold timing out of range");
+
+                dac.mcr.modify(|_, w| unsafe { w.$mode().bits(0b100) });
+                dac.$shsrx.write(|w| unsafe { w.bits(u32::from(sample)) });
+                dac.shhr.modify(|r, w| unsafe {
+                    w.bits(
+                        (r.bits() & !(0x3FF << $shift))
+                            | u32::from(hold) << $shift,
+                    )
+                });
+                dac.shrr.modify(|r, w| unsafe {
+                    w.bits(
+                        (r.bits() & !(0xFF << $shift))
+                            | u32::from(refresh) << $shift,
+                    )
+                });
+                dac.cr.modify(|_, w| w.$en().set_bit());
+
+                $CX {
+                    _dac: PhantomData,
+                    _enabled: PhantomData,
+                }
+            }
+
+            /// Select the trigger that transfers the data holding register
+            /// to the output. With `None`, the output is updated one APB
+            /// clock cycle after each write
+            pub fn set_trigger(&mut self, trigger: Option<Trigger>) {
+                let dac = unsafe { &(*$DAC::ptr()) };
+
+                let bits = match trigger {
+                    Some(trigger) => CR_TEN | (trigger as u32) << CR_TSEL_SHIFT,
+                    None => 0,
+                };
+                dac.cr.modify(|r, w| unsafe {
+                    w.bits(
+                        (r.bits() & !(CR_TRIGGER_MASK << $shift))
+                            | bits << $shift,
+                    )
+                });
+            }
+
+            /// Select the built-in waveform generator. Waveform generation
+            /// requires a trigger
+            pub fn set_waveform(&mut self, waveform: Waveform) {
+                let dac = unsafe { &(*$DAC::ptr()) };
+
+                let bits = waveform.cr_bits();
+                dac.cr.modify(|r, w| unsafe {
+                    w.bits(
+                        (r.bits() & !(CR_WAVE_MASK << $shift)) | bits << $shift,
+                    )
+                });
+            }
         }
 
         impl<ED> $CX<$DAC, ED> {
@@ -159,6 +347,44 @@ macro_rules! dac {
                     _enabled: PhantomData,
                 }
             }
+
+            /// Trigger a transfer of the data holding register to the
+            /// output, when the software trigger is selected
+            pub fn software_trigger(&mut self) {
+                let dac = unsafe { &(*$DAC::ptr()) };
+                dac.swtrigr.write(|w| unsafe { w.bits(1 << ($shift / 16)) });
+            }
+
+            /// Enable DMA requests. A DMA request is generated on each
+            /// trigger, to write the next value to the data holding
+            /// register. Also enables the DMA underrun interrupt
+            pub fn enable_dma(&mut self) {
+                let dac = unsafe { &(*$DAC::ptr()) };
+                dac.cr.modify(|r, w| unsafe {
+                    w.bits(r.bits() | (CR_DMAEN | CR_DMAUDRIE) << $shift)
+                });
+            }
+
+            /// Disable DMA requests
+            pub fn disable_dma(&mut self) {
+                let dac = unsafe { &(*$DAC::ptr()) };
+                dac.cr.modify(|r, w| unsafe {
+                    w.bits(r.bits() & !((CR_DMAEN | CR_DMAUDRIE) << $shift))
+                });
+            }
+
+            /// Returns `true` if a trigger occurred before the DMA wrote
+            /// the previous value. DMA requests stop after an underrun
+            pub fn is_dma_underrun(&self) -> bool {
+                let dac = unsafe { &(*$DAC::ptr()) };
+                dac.sr.read().bits() & (SR_DMAUDR << $shift) != 0
+            }
+
+            /// Clear the DMA underrun flag
+            pub fn clear_dma_underrun(&mut self) {
+                let dac = unsafe { &(*$DAC::ptr()) };
+                dac.sr.write(|w| unsafe { w.bits(SR_DMAUDR << $shift) });
+            }
         }
 
         /// DacOut implementation available in any Enabled/Disabled state
@@ -176,6 +402,29 @@ macro_rules! dac {
     };
 }
 
+macro_rules! dac_dual {
+    ($DAC:ident) => {
+        impl<ED> C1<$DAC, ED> {
+            /// Write both channels of the DAC simultaneously, using the
+            /// dual data holding register. If both channels use the
+            /// software trigger, both outputs are also updated
+            /// simultaneously
+            pub fn set_dual_value<ED2>(
+                &mut self,
+                _c2: &mut C2<$DAC, ED2>,
+                c1: u16,
+                c2: u16,
+            ) {
+                let dac = unsafe { &(*$DAC::ptr()) };
+                dac.dhr12rd.write(|w| unsafe {
+                    w.bits(u32::from(c1 & 0xFFF) | u32::from(c2 & 0xFFF) << 16)
+                });
+                dac.swtrigr.write(|w| unsafe { w.bits(0b11) });
+            }
+        }
+    };
+}
+
 pub trait DacExt: Sized {
     type REC: ResetEnable;
 
@@ -210,10 +459,53 @@ impl DacExt for DAC2 {
     }
 }
 
-dac!(DAC1, C1, en1, cen1, cal_flag1, otrim1, mode1, dhr12r1, dor1, dacc1dhr);
-dac!(DAC1, C2, en2, cen2, cal_flag2, otrim2, mode2, dhr12r2, dor2, dacc2dhr);
+dac!(
+    DAC1, C1, en1, cen1, cal_flag1, otrim1, mode1, dhr12r1, dor1, dacc1dhr,
+    shsr1, 0
+);
+dac!(
+    DAC1, C2, en2, cen2, cal_flag2, otrim2, mode2, dhr12r2, dor2, dacc2dhr,
+    shsr2, 16
+);
+dac_dual!(DAC1);
 
 #[cfg(feature = "rm0455")]
-dac!(DAC2, C1, en1, cen1, cal_flag1, otrim1, mode1, dhr12r1, dor1, dacc1dhr);
+dac!(
+    DAC2, C1, en1, cen1, cal_flag1, otrim1, mode1, dhr12r1, dor1, dacc1dhr,
+    shsr1, 0
+);
 #[cfg(feature = "rm0455")]
-dac!(DAC2, C2, en2, cen2, cal_flag2, otrim2, mode2, dhr12r2, dor2, dacc2dhr);
+dac!(
+    DAC2, C2, en2, cen2, cal_flag2, otrim2, mode2, dhr12r2, dor2, dacc2dhr,
+    shsr2, 16
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_hold() {
+        // 32kHz LSI, 31.25us per cycle
+        let timing = SampleHoldTiming {
+            sample: MicroSeconds(1_000),
+            hold: MicroSeconds(20_000),
+            refresh: MicroSeconds(100),
+        };
+        assert_eq!(sample_hold_cycles(32_000, &timing), Some((32, 640, 4)));
+
+        let timing = SampleHoldTiming {
+            sample: MicroSeconds(0),
+            hold: MicroSeconds(0),
+            refresh: MicroSeconds(0),
+        };
+        assert_eq!(sample_hold_cycles(32_000, &timing), Some((1, 0, 0)));
+
+        let timing = SampleHoldTiming {
+            sample: MicroSeconds(1_000),
+            hold: MicroSeconds(40_000),
+            refresh: MicroSeconds(100),
+        };
+        assert_eq!(sample_hold_cycles(32_000, &timing), None);
+    }
+}
@@ -10,12 +10,19 @@ use super::{
 use core::marker::PhantomData;
 
 use crate::{
+    adc, dac, i2c,
     pac::{self, DMA1, DMA2, DMAMUX1},
+    pwm,
     rcc::{rec, rec::ResetEnable},
     serial, spi,
 };
 
-use core::ops::Deref;
+use core::ops::{Deref, DerefMut};
+
+#[cfg(not(feature = "rm0455"))]
+use pac::DAC as DAC1;
+#[cfg(feature = "rm0455")]
+use pac::DAC1;
 
 impl Sealed for DMA1 {}
 impl Sealed for DMA2 {}
@@ -809,6 +816,13 @@ macro_rules! peripheral_register_markers {
                     &self.0
                 }
             }
+
+            impl<T> DerefMut for $name<T> {
+                #[inline(always)]
+                fn deref_mut(&mut self) -> &mut T {
+                    &mut self.0
+                }
+            }
         )+
     };
 }
@@ -923,6 +937,21 @@ peripheral_target_address!(
     ),
 );
 
+peripheral_target_address!(
+    (pac::I2C1, rxdr, u8, P2M, DMAReq::I2C1_RX_DMA),
+    (pac::I2C1, txdr, u8, M2P, DMAReq::I2C1_TX_DMA),
+    (pac::I2C2, rxdr, u8, P2M, DMAReq::I2C2_RX_DMA),
+    (pac::I2C2, txdr, u8, M2P, DMAReq::I2C2_TX_DMA),
+    (pac::I2C3, rxdr, u8, P2M, DMAReq::I2C3_RX_DMA),
+    (pac::I2C3, txdr, u8, M2P, DMAReq::I2C3_TX_DMA),
+    (INNER: i2c::I2c<pac::I2C1>, rxdr, u8, P2M, DMAReq::I2C1_RX_DMA),
+    (INNER: i2c::I2c<pac::I2C1>, txdr, u8, M2P, DMAReq::I2C1_TX_DMA),
+    (INNER: i2c::I2c<pac::I2C2>, rxdr, u8, P2M, DMAReq::I2C2_RX_DMA),
+    (INNER: i2c::I2c<pac::I2C2>, txdr, u8, M2P, DMAReq::I2C2_TX_DMA),
+    (INNER: i2c::I2c<pac::I2C3>, rxdr, u8, P2M, DMAReq::I2C3_RX_DMA),
+    (INNER: i2c::I2c<pac::I2C3>, txdr, u8, M2P, DMAReq::I2C3_TX_DMA),
+);
+
 peripheral_target_address!(
     (pac::SAI1, cha.dr, u32, M2P, DMAReq::SAI1A_DMA),
     (pac::SAI1, chb.dr, u32, P2M, DMAReq::SAI1B_DMA),
@@ -934,3 +963,139 @@ peripheral_target_address!(
     (pac::SAI3, cha.dr, u32, M2P, DMAReq::SAI3_A_DMA),
     (pac::SAI3, chb.dr, u32, P2M, DMAReq::SAI3_B_DMA),
 );
+
+peripheral_target_address!(
+    (INNER: adc::Adc<pac::ADC1, adc::Enabled>, dr, u32, P2M, DMAReq::ADC1_DMA),
+    (INNER: adc::Adc<pac::ADC2, adc::Enabled>, dr, u32, P2M, DMAReq::ADC2_DMA),
+);
+#[cfg(not(feature = "rm0455"))]
+peripheral_target_address!((
+    INNER: adc::Adc<pac::ADC3, adc::Enabled>,
+    dr,
+    u32,
+    P2M,
+    DMAReq::ADC3_DMA
+));
+
+unsafe impl TargetAddress<P2M> for adc::DualAdc {
+    #[inline(always)]
+    fn address(&self) -> usize {
+        // unsafe: the common data register is only read by the DMA
+        let common = unsafe { &*pac::ADC12_COMMON::ptr() };
+        &common.cdr as *const _ as usize
+    }
+
+    type MemSize = u32;
+
+    const REQUEST_LINE: Option<u8> = Some(DMAReq::ADC1_DMA as u8);
+}
+
+macro_rules! dac_target_address {
+    ($($CX:ident: ($dhrx:ident, $mux:expr)),+ $(,)*) => {
+        $(
+            unsafe impl<ED> TargetAddress<M2P> for dac::$CX<DAC1, ED> {
+                #[inline(always)]
+                fn address(&self) -> usize {
+                    // unsafe: only this channel accesses its data holding
+                    // register
+                    let dac = unsafe { &*DAC1::ptr() };
+                    &dac.$dhrx as *const _ as usize
+                }
+
+                type MemSize = u16;
+
+                const REQUEST_LINE: Option<u8> = Some($mux as u8);
+            }
+        )+
+    };
+}
+
+dac_target_address!(
+    C1: (dhr12r1, DMAReq::DAC_CH1_DMA),
+    C2: (dhr12r2, DMAReq::DAC_CH2_DMA),
+);
+
+macro_rules! pwm_target_address {
+    ($($TIMX:ident: ($typ:ty, $up:ident, [$($CH:ident: ($ccrx:ident, $mux:ident)),+]),)+) => {
+        $(
+            $(
+                unsafe impl<COMP, POL, NPOL> TargetAddress<M2P>
+                    for pwm::Pwm<pac::$TIMX, pwm::$CH, COMP, POL, NPOL>
+                {
+                    #[inline(always)]
+                    fn address(&self) -> usize {
+                        // unsafe: only this channel accesses its
+                        // capture/compare register
+                        let tim = unsafe { &*pac::$TIMX::ptr() };
+                        &tim.$ccrx as *const _ as usize
+                    }
+
+                    type MemSize = $typ;
+
+                    const REQUEST_LINE: Option<u8> = Some(DMAReq::$mux as u8);
+                }
+            )+
+
+            unsafe impl<FAULT> TargetAddress<M2P>
+                for DMAR<pwm::PwmControl<pac::$TIMX, FAULT>>
+            {
+                #[inline(always)]
+                fn address(&self) -> usize {
+                    // unsafe: the DMA address register is only written by
+                    // the DMA
+                    let tim = unsafe { &*pac::$TIMX::ptr() };
+                    &tim.dmar as *const _ as usize
+                }
+
+                type MemSize = $typ;
+
+                const REQUEST_LINE: Option<u8> = Some(DMAReq::$up as u8);
+            }
+
+            unsafe impl<FAULT> TargetAddress<M2P>
+                for ARR<pwm::PwmControl<pac::$TIMX, FAULT>>
+            {
+                #[inline(always)]
+                fn address(&self) -> usize {
+                    // unsafe: the PwmControl is owned by the transfer, so
+                    // only the DMA writes the auto-reload register
+                    let tim = unsafe { &*pac::$TIMX::ptr() };
+                    &tim.arr as *const _ as usize
+                }
+
+                type MemSize = $typ;
+
+                const REQUEST_LINE: Option<u8> = Some(DMAReq::$up as u8);
+            }
+        )+
+    };
+}
+
+pwm_target_address!(
+    TIM1: (u16, TIM1_UP, [
+        C1: (ccr1, TIM1_CH1), C2: (ccr2, TIM1_CH2),
+        C3: (ccr3, TIM1_CH3), C4: (ccr4, TIM1_CH4)
+    ]),
+    TIM2: (u32, TIM2_UP, [
+        C1: (ccr1, TIM2_CH1), C2: (ccr2, TIM2_CH2),
+        C3: (ccr3, TIM2_CH3), C4: (ccr4, TIM2_CH4)
+    ]),
+    TIM3: (u16, TIM3_UP, [
+        C1: (ccr1, TIM3_CH1), C2: (ccr2, TIM3_CH2),
+        C3: (ccr3, TIM3_CH3), C4: (ccr4, TIM3_CH4)
+    ]),
+    TIM4: (u16, TIM4_UP, [
+        C1: (ccr1, TIM4_CH1), C2: (ccr2, TIM4_CH2), C3: (ccr3, TIM4_CH3)
+    ]),
+    TIM5: (u32, TIM5_UP, [
+        C1: (ccr1, TIM5_CH1), C2: (ccr2, TIM5_CH2),
+        C3: (ccr3, TIM5_CH3), C4: (ccr4, TIM5_CH4)
+    ]),
+    TIM8: (u16, TIM8_UP, [
+        C1: (ccr1, TIM8_CH1), C2: (ccr2, TIM8_CH2),
+        C3: (ccr3, TIM8_CH3), C4: (ccr4, TIM8_CH4)
+    ]),
+    TIM15: (u16, TIM15_UP, [C1: (ccr1, TIM15_CH1)]),
+    TIM16: (u16, TIM16_UP, [C1: (ccr1, TIM16_CH1)]),
+    TIM17: (u16, TIM17_UP, [C1: (ccr1, TIM17_CH1)]),
+);
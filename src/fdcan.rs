@@ -0,0 +1,1376 @@
+//! Controller Area Network with Flexible Data-Rate (FDCAN)
+//!
+//! This module implements the [embedded-can] traits for FDCAN1 and
+//! FDCAN2. Both classical CAN and CAN-FD frames (with or without bit rate
+//! switching) are supported.
+//!
+//! # Usage
+//!
+//! ```
+//! use stm32h7xx_hal::fdcan;
+//!
+//! let dp = ...;                   // Device peripherals
+//! let (tx, rx) = ...;             // GPIO pins
+//!
+//! let config = fdcan::Config::new(500.khz()).data_bitrate(2.mhz());
+//! let mut can = dp.FDCAN1.fdcan((tx, rx), config, ccdr.peripheral.FDCAN, &ccdr.clocks);
+//! ```
+//!
+//! FDCAN1 and FDCAN2 share a single reset and enable line. To initialise
+//! both of them, use the [`fdcan12`](fdcan12) method.
+//!
+//! ## Clocks
+//!
+//! The bit timing is calculated from the FDCAN kernel clock currently
+//! assigned in the RCC CCIP register. The kernel clock must be an integer
+//! multiple of the requested bit rates. The default assignment is __HSE__.
+//!
+//! ## Message RAM
+//!
+//! The 10 KiB of message RAM is shared between FDCAN1 and FDCAN2. Each
+//! instance owns one half (1280 words), which is partitioned into the
+//! standard ID filter list, the extended ID filter list, Rx FIFO 0, Rx FIFO 1
+//! and the Tx buffer queue according to [`RamConfig`](RamConfig). Elements
+//! are sized for 64 data bytes if CAN-FD is enabled, otherwise 8 data bytes.
+//!
+//! ## Filters
+//!
+//! By default all frames that do not match a filter element are stored in Rx
+//! FIFO 0. Filter elements can be written at any time with
+//! [`set_standard_filter`](Fdcan#method.set_standard_filter) and
+//! [`set_extended_filter`](Fdcan#method.set_extended_filter).
+//!
+//! [embedded-can]: https://docs.rs/embedded-can
+
+use core::cmp;
+use core::marker::PhantomData;
+use core::ptr;
+
+use embedded_can::{ExtendedId, Id, StandardId};
+
+use crate::gpio::gpioa::{PA11, PA12};
+use crate::gpio::gpiob::{PB12, PB13, PB5, PB6, PB8, PB9};
+use crate::gpio::gpiod::{PD0, PD1};
+use crate::gpio::gpioh::{PH13, PH14};
+use crate::gpio::{Alternate, AF9};
+use crate::rcc::{rec, CoreClocks, ResetEnable};
+use crate::stm32::{FDCAN1, FDCAN2};
+use crate::time::Hertz;
+use stm32h7::Variant::Val;
+
+/// Start address of the message RAM
+const MESSAGE_RAM_BASE: usize = 0x4000_AC00;
+
+/// Size of the message RAM owned by each FDCAN instance, in 32-bit words
+const MESSAGE_RAM_WORDS: u16 = 1280;
+
+/// FDCAN error
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Stuff error: more than 5 equal bits in a sequence
+    Stuff,
+    /// Form error: fixed format part of a received frame was wrong
+    Form,
+    /// Acknowledge error: the transmitted frame was not acknowledged
+    Acknowledge,
+    /// Bit error: a transmitted bit was read back with a different level
+    Bit,
+    /// CRC error: the CRC of a received frame was wrong
+    Crc,
+    /// The node is in the Bus_Off state
+    BusOff,
+    /// A frame was lost because the Rx FIFO was full
+    Overrun,
+}
+
+impl embedded_can::Error for Error {
+    fn kind(&self) -> embedded_can::ErrorKind {
+        use embedded_can::ErrorKind;
+
+        match self {
+            Error::Stuff => ErrorKind::Stuff,
+            Error::Form => ErrorKind::Form,
+            Error::Acknowledge => ErrorKind::Acknowledge,
+            Error::Bit => ErrorKind::Bit,
+            Error::Crc => ErrorKind::Crc,
+            Error::BusOff => ErrorKind::Other,
+            Error::Overrun => ErrorKind::Overrun,
+        }
+    }
+}
+
+/// Interrupt events
+///
+/// All events are routed to interrupt line 0 (FDCANx_IT0)
+#[derive(Copy, Clone, PartialEq)]
+pub enum Event {
+    /// A new message was written to Rx FIFO 0 (RF0N)
+    RxFifo0NewMessage,
+    /// A message was lost because Rx FIFO 0 was full (RF0L)
+    RxFifo0MessageLost,
+    /// A new message was written to Rx FIFO 1 (RF1N)
+    RxFifo1NewMessage,
+    /// A message was lost because Rx FIFO 1 was full (RF1L)
+    RxFifo1MessageLost,
+    /// A transmission completed (TC)
+    TransmissionComplete,
+    /// The Tx FIFO/queue is empty (TFE)
+    TxQueueEmpty,
+    /// Change of the error warning status (EW)
+    ErrorWarning,
+    /// Change of the error passive status (EP)
+    ErrorPassive,
+    /// Change of the bus off status (BO)
+    BusOff,
+}
+
+/// Frame format used for transmission
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum FrameFormat {
+    /// Classical CAN frames only
+    Classic,
+    /// CAN-FD frames, data phase at the nominal bit rate
+    Fd,
+    /// CAN-FD frames with bit rate switching to the data bit rate
+    FdBrs,
+}
+
+/// Bit timing parameters
+///
+/// All values are in time quanta (tq), not register values.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct BitTiming {
+    /// Bit rate prescaler. The time quantum is `prescaler` kernel clocks
+    pub prescaler: u16,
+    /// Time segment before the sample point, not including the sync segment
+    pub seg1: u16,
+    /// Time segment after the sample point
+    pub seg2: u16,
+    /// (Re)synchronization jump width
+    pub sjw: u16,
+}
+
+/// Limits for a bit timing calculation, in time quanta
+struct BitTimingLimits {
+    prescaler: u16,
+    seg1: (u16, u16),
+    seg2: (u16, u16),
+    sjw: u16,
+}
+
+/// Limits of the nominal bit timing register NBTP
+const NOMINAL_LIMITS: BitTimingLimits = BitTimingLimits {
+    prescaler: 512,
+    seg1: (2, 256),
+    seg2: (2, 128),
+    sjw: 128,
+};
+
+/// Limits of the data bit timing register DBTP
+const DATA_LIMITS: BitTimingLimits = BitTimingLimits {
+    prescaler: 32,
+    seg1: (1, 32),
+    seg2: (1, 16),
+    sjw: 16,
+};
+
+/// Calculate bit timing for `bitrate` from a kernel clock `ker_ck`
+///
+/// The number of time quanta per bit is maximised, with the sample point
+/// placed as close to 87.5% as possible. The kernel clock must be an integer
+/// multiple of the resulting time quantum, otherwise `None` is returned.
+fn calculate_bit_timing(
+    ker_ck: u32,
+    bitrate: u32,
+    limits: &BitTimingLimits,
+) -> Option<BitTiming> {
+    if bitrate == 0 || bitrate > ker_ck {
+        return None;
+    }
+
+    let max_tq = 1 + limits.seg1.1 as u32 + limits.seg2.1 as u32;
+    let min_tq = 1 + limits.seg1.0 as u32 + limits.seg2.0 as u32;
+
+    for tq in (min_tq..=max_tq).rev() {
+        let divisor = bitrate * tq;
+        if divisor > ker_ck || ker_ck % divisor != 0 {
+            continue;
+        }
+        let prescaler = ker_ck / divisor;
+        if prescaler > limits.prescaler as u32 {
+            // Fewer tq per bit only makes the prescaler larger
+            return None;
+        }
+
+        // Sample point at 87.5%, counted in tq from the start of the bit
+        let sample = (tq * 7 + 4) / 8;
+        let seg2 = cmp::max(tq - sample, limits.seg2.0 as u32);
+        let seg1 = tq - 1 - seg2;
+        if seg1 < limits.seg1.0 as u32
+            || seg1 > limits.seg1.1 as u32
+            || seg2 > limits.seg2.1 as u32
+        {
+            continue;
+        }
+
+        return Some(BitTiming {
+            prescaler: prescaler as u16,
+            seg1: seg1 as u16,
+            seg2: seg2 as u16,
+            sjw: cmp::min(seg2, limits.sjw as u32) as u16,
+        });
+    }
+
+    None
+}
+
+/// Partitioning of the message RAM owned by one FDCAN instance
+///
+/// All sizes are in elements.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct RamConfig {
+    /// Number of standard ID filter elements (0 - 128)
+    pub standard_filters: u8,
+    /// Number of extended ID filter elements (0 - 64)
+    pub extended_filters: u8,
+    /// Number of Rx FIFO 0 elements (0 - 64)
+    pub rx_fifo0: u8,
+    /// Number of Rx FIFO 1 elements (0 - 64)
+    pub rx_fifo1: u8,
+    /// Number of Tx buffers used as a Tx queue (1 - 32)
+    pub tx_buffers: u8,
+}
+
+impl Default for RamConfig {
+    fn default() -> Self {
+        RamConfig {
+            standard_filters: 28,
+            extended_filters: 8,
+            rx_fifo0: 32,
+            rx_fifo1: 16,
+            tx_buffers: 16,
+        }
+    }
+}
+
+/// Word offsets of each section within the message RAM
+#[derive(Copy, Clone, Debug)]
+struct RamLayout {
+    standard_filters: u16,
+    extended_filters: u16,
+    rx_fifo0: u16,
+    rx_fifo1: u16,
+    tx_buffers: u16,
+    /// Size of a Rx / Tx element in words
+    element_words: u16,
+    /// End of this layout
+    end: u16,
+}
+
+impl RamConfig {
+    /// Compute the section offsets starting at word `start`. Each Rx / Tx
+    /// element has `data_bytes` of data field.
+    fn layout(&self, start: u16, data_bytes: u16) -> RamLayout {
+        assert!(self.standard_filters <= 128);
+        assert!(self.extended_filters <= 64);
+        assert!(self.rx_fifo0 <= 64);
+        assert!(self.rx_fifo1 <= 64);
+        assert!(self.tx_buffers > 0 && self.tx_buffers <= 32);
+
+        // Two header words, then the data field
+        let element_words = 2 + data_bytes / 4;
+
+        let standard_filters = start;
+        let extended_filters = standard_filters + self.standard_filters as u16;
+        let rx_fifo0 = extended_filters + 2 * self.extended_filters as u16;
+        let rx_fifo1 = rx_fifo0 + element_words * self.rx_fifo0 as u16;
+        let tx_buffers = rx_fifo1 + element_words * self.rx_fifo1 as u16;
+        let end = tx_buffers + element_words * self.tx_buffers as u16;
+
+        assert!(
+            end - start <= MESSAGE_RAM_WORDS,
+            "FDCAN message RAM configuration is too large!"
+        );
+
+        RamLayout {
+            standard_filters,
+            extended_filters,
+            rx_fifo0,
+            rx_fifo1,
+            tx_buffers,
+            element_words,
+            end,
+        }
+    }
+}
+
+/// FDCAN configuration
+#[derive(Copy, Clone, Debug)]
+pub struct Config {
+    nominal_bitrate: Hertz,
+    data_bitrate: Option<Hertz>,
+    frame_format: FrameFormat,
+    automatic_retransmission: bool,
+    reject_non_matching: bool,
+    ram: RamConfig,
+}
+
+impl Config {
+    /// Create a default configuration for classical CAN at
+    /// `nominal_bitrate`
+    pub fn new(nominal_bitrate: impl Into<Hertz>) -> Self {
+        Config {
+            nominal_bitrate: nominal_bitrate.into(),
+            data_bitrate: None,
+            frame_format: FrameFormat::Classic,
+            automatic_retransmission: true,
+            reject_non_matching: false,
+            ram: RamConfig::default(),
+        }
+    }
+
+    /// Enable CAN-FD with bit rate switching. The data phase of frames
+    /// transmitted with the BRS bit set will use `data_bitrate`
+    pub fn data_bitrate(mut self, data_bitrate: impl Into<Hertz>) -> Self {
+        self.data_bitrate = Some(data_bitrate.into());
+        self.frame_format = FrameFormat::FdBrs;
+        self
+    }
+
+    /// Set the format of transmitted frames. Selecting
+    /// [`FdBrs`](FrameFormat::FdBrs) without a data bit rate uses the
+    /// nominal bit rate for the data phase
+    pub fn frame_format(mut self, frame_format: FrameFormat) -> Self {
+        self.frame_format = frame_format;
+        self
+    }
+
+    /// Enable or disable automatic retransmission of frames that lost
+    /// arbitration or were disturbed by errors. Enabled by default
+    pub fn automatic_retransmission(mut self, enable: bool) -> Self {
+        self.automatic_retransmission = enable;
+        self
+    }
+
+    /// Reject frames that do not match any filter element. By default
+    /// these are stored in Rx FIFO 0
+    pub fn reject_non_matching(mut self) -> Self {
+        self.reject_non_matching = true;
+        self
+    }
+
+    /// Set the partitioning of the message RAM for this instance
+    pub fn ram(mut self, ram: RamConfig) -> Self {
+        self.ram = ram;
+        self
+    }
+
+    /// Bit rate of the data phase, if the data bit timing needs to be
+    /// programmed. Classical CAN does not use the data bit timing
+    fn data_phase_bitrate(&self) -> Option<Hertz> {
+        match (self.frame_format, self.data_bitrate) {
+            (FrameFormat::Classic, _) => None,
+            (FrameFormat::FdBrs, None) => Some(self.nominal_bitrate),
+            (_, data_bitrate) => data_bitrate,
+        }
+    }
+}
+
+impl<T: Into<Hertz>> From<T> for Config {
+    fn from(f: T) -> Config {
+        Config::new(f)
+    }
+}
+
+/// Action taken for frames matching a filter element
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum FilterAction {
+    /// Filter element is disabled
+    Disable,
+    /// Store in Rx FIFO 0
+    StoreRxFifo0,
+    /// Store in Rx FIFO 1
+    StoreRxFifo1,
+    /// Reject the frame
+    Reject,
+    /// Set high priority message status
+    SetPriority,
+    /// Set high priority message status and store in Rx FIFO 0
+    SetPriorityRxFifo0,
+    /// Set high priority message status and store in Rx FIFO 1
+    SetPriorityRxFifo1,
+}
+
+impl FilterAction {
+    fn bits(self) -> u32 {
+        match self {
+            FilterAction::Disable => 0b000,
+            FilterAction::StoreRxFifo0 => 0b001,
+            FilterAction::StoreRxFifo1 => 0b010,
+            FilterAction::Reject => 0b011,
+            FilterAction::SetPriority => 0b100,
+            FilterAction::SetPriorityRxFifo0 => 0b101,
+            FilterAction::SetPriorityRxFifo1 => 0b110,
+        }
+    }
+}
+
+/// Matching rule of a filter element
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum FilterType<ID> {
+    /// Match IDs in the inclusive range `from..=to`
+    Range { from: ID, to: ID },
+    /// Match either of two IDs
+    Dual(ID, ID),
+    /// Match `id` for all bits set in `mask`
+    Classic { id: ID, mask: ID },
+}
+
+/// Filter element for standard (11-bit) IDs
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct StandardFilter {
+    pub filter: FilterType<StandardId>,
+    pub action: FilterAction,
+}
+
+/// Filter element for extended (29-bit) IDs
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ExtendedFilter {
+    pub filter: FilterType<ExtendedId>,
+    pub action: FilterAction,
+}
+
+impl StandardFilter {
+    /// Filter element that accepts all standard frames into `fifo0` (if
+    /// true) or FIFO 1
+    pub fn accept_all(fifo0: bool) -> Self {
+        StandardFilter {
+            filter: FilterType::Classic {
+                id: StandardId::ZERO,
+                mask: StandardId::ZERO,
+            },
+            action: if fifo0 {
+                FilterAction::StoreRxFifo0
+            } else {
+                FilterAction::StoreRxFifo1
+            },
+        }
+    }
+
+    /// Encode as a standard filter element. See RM0433 Rev 7 Section
+    /// 56.5.1
+    fn element(&self) -> u32 {
+        let (sft, id1, id2) = match self.filter {
+            FilterType::Range { from, to } => (0b00, from, to),
+            FilterType::Dual(id1, id2) => (0b01, id1, id2),
+            FilterType::Classic { id, mask } => (0b10, id, mask),
+        };
+        (sft << 30)
+            | (self.action.bits() << 27)
+            | ((id1.as_raw() as u32) << 16)
+            | id2.as_raw() as u32
+    }
+}
+
+impl ExtendedFilter {
+    /// Encode as an extended filter element. See RM0433 Rev 7 Section
+    /// 56.5.1
+    fn element(&self) -> [u32; 2] {
+        let (eft, id1, id2) = match self.filter {
+            FilterType::Range { from, to } => (0b11, from, to),
+            FilterType::Dual(id1, id2) => (0b01, id1, id2),
+            FilterType::Classic { id, mask } => (0b10, id, mask),
+        };
+        [
+            (self.action.bits() << 29) | id1.as_raw(),
+            (eft << 30) | id2.as_raw(),
+        ]
+    }
+}
+
+/// Length of the data field for each DLC value
+const DLC_TO_LEN: [u8; 16] =
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 12, 16, 20, 24, 32, 48, 64];
+
+/// Smallest DLC that can hold `len` bytes
+fn len_to_dlc(len: usize) -> Option<u8> {
+    DLC_TO_LEN
+        .iter()
+        .position(|&l| l as usize >= len)
+        .map(|dlc| dlc as u8)
+}
+
+/// A CAN or CAN-FD frame
+#[derive(Copy, Clone)]
+pub struct Frame {
+    id: Id,
+    remote: bool,
+    fd: bool,
+    brs: bool,
+    dlc: u8,
+    data: [u8; 64],
+}
+
+impl Frame {
+    /// Create a new CAN-FD data frame. Data fields longer than 8 bytes are
+    /// padded to the next valid CAN-FD length
+    pub fn new_fd(id: impl Into<Id>, data: &[u8], brs: bool) -> Option<Self> {
+        let dlc = len_to_dlc(data.len())?;
+        let mut frame = Frame {
+            id: id.into(),
+            remote: false,
+            fd: true,
+            brs,
+            dlc,
+            data: [0; 64],
+        };
+        frame.data[..data.len()].copy_from_slice(data);
+        Some(frame)
+    }
+
+    /// Returns true if this is a CAN-FD frame
+    pub fn is_fd_frame(&self) -> bool {
+        self.fd
+    }
+
+    /// Returns true if this CAN-FD frame uses bit rate switching
+    pub fn is_bit_rate_switching(&self) -> bool {
+        self.brs
+    }
+
+    /// Length of the data field in bytes
+    fn len(&self) -> usize {
+        if self.remote {
+            0
+        } else {
+            DLC_TO_LEN[self.dlc as usize] as usize
+        }
+    }
+}
+
+impl embedded_can::Frame for Frame {
+    fn new(id: impl Into<Id>, data: &[u8]) -> Option<Self> {
+        if data.len() > 8 {
+            return None;
+        }
+        let mut frame = Frame {
+            id: id.into(),
+            remote: false,
+            fd: false,
+            brs: false,
+            dlc: data.len() as u8,
+            data: [0; 64],
+        };
+        frame.data[..data.len()].copy_from_slice(data);
+        Some(frame)
+    }
+
+    fn new_remote(id: impl Into<Id>, dlc: usize) -> Option<Self> {
+        if dlc > 8 {
+            return None;
+        }
+        Some(Frame {
+            id: id.into(),
+            remote: true,
+            fd: false,
+            brs: false,
+            dlc: dlc as u8,
+            data: [0; 64],
+        })
+    }
+
+    fn is_extended(&self) -> bool {
+        matches!(self.id, Id::Extended(_))
+    }
+
+    fn is_remote_frame(&self) -> bool {
+        self.remote
+    }
+
+    fn id(&self) -> Id {
+        self.id
+    }
+
+    fn dlc(&self) -> usize {
+        self.dlc as usize
+    }
+
+    fn data(&self) -> &[u8] {
+        &self.data[..self.len()]
+    }
+}
+
+pub trait Pins<FDCAN> {}
+pub trait PinTx<FDCAN> {}
+pub trait PinRx<FDCAN> {}
+
+impl<FDCAN, TX, RX> Pins<FDCAN> for (TX, RX)
+where
+    TX: PinTx<FDCAN>,
+    RX: PinRx<FDCAN>,
+{
+}
+
+macro_rules! pins {
+    ($($FDCANX:ty: TX: [$($TX:ty),*] RX: [$($RX:ty),*])+) => {
+        $(
+            $(
+                impl PinTx<$FDCANX> for $TX {}
+            )*
+            $(
+                impl PinRx<$FDCANX> for $RX {}
+            )*
+        )+
+    }
+}
+
+pins! {
+    FDCAN1:
+        TX: [
+            PA12<Alternate<AF9>>,
+            PB9<Alternate<AF9>>,
+            PD1<Alternate<AF9>>,
+            PH13<Alternate<AF9>>
+        ]
+        RX: [
+            PA11<Alternate<AF9>>,
+            PB8<Alternate<AF9>>,
+            PD0<Alternate<AF9>>,
+            PH14<Alternate<AF9>>
+        ]
+    FDCAN2:
+        TX: [
+            PB6<Alternate<AF9>>,
+            PB13<Alternate<AF9>>
+        ]
+        RX: [
+            PB5<Alternate<AF9>>,
+            PB12<Alternate<AF9>>
+        ]
+}
+
+/// FDCAN peripheral
+pub struct Fdcan<FDCAN> {
+    rb: FDCAN,
+    layout: RamLayout,
+    ram: RamConfig,
+    frame_format: FrameFormat,
+}
+
+pub trait FdcanExt<FDCAN>: Sized {
+    type Rec: ResetEnable;
+
+    fn fdcan(
+        self,
+        _pins: impl Pins<FDCAN>,
+        config: impl Into<Config>,
+        prec: Self::Rec,
+        clocks: &CoreClocks,
+    ) -> Fdcan<FDCAN>;
+
+    fn fdcan_unchecked(
+        self,
+        config: impl Into<Config>,
+        prec: Self::Rec,
+        clocks: &CoreClocks,
+    ) -> Fdcan<FDCAN>;
+}
+
+/// Returns the frequency of the current FDCAN kernel clock
+fn kernel_clk(prec: &rec::Fdcan, clocks: &CoreClocks) -> Option<Hertz> {
+    match prec.get_kernel_clk_mux() {
+        Val(rec::FdcanClkSel::HSE) => clocks.hse_ck(),
+        Val(rec::FdcanClkSel::PLL1_Q) => clocks.pll1_q_ck(),
+        Val(rec::FdcanClkSel::PLL2_Q) => clocks.pll2_q_ck(),
+        _ => unreachable!(),
+    }
+}
+
+// FDCAN1 and FDCAN2 are a unique case where a single reset line is used to
+// control two peripherals that have separate peripheral definitions in the
+// SVD.
+
+/// Initialise FDCAN1 and FDCAN2 together
+///
+/// # Panics
+///
+/// Panics if the FDCAN kernel clock is not running, or if a bit rate cannot
+/// be generated exactly from the kernel clock.
+pub fn fdcan12(
+    fdcan1: FDCAN1,
+    fdcan2: FDCAN2,
+    config1: impl Into<Config>,
+    config2: impl Into<Config>,
+    prec: rec::Fdcan,
+    clocks: &CoreClocks,
+) -> (Fdcan<FDCAN1>, Fdcan<FDCAN2>) {
+    let ker_ck = kernel_clk(&prec, clocks)
+        .expect("FDCAN kernel clock not running!")
+        .0;
+
+    // Enable clock and reset
+    prec.enable().reset();
+
+    (
+        Fdcan::<FDCAN1>::initialise(fdcan1, config1.into(), ker_ck),
+        Fdcan::<FDCAN2>::initialise(fdcan2, config2.into(), ker_ck),
+    )
+}
+
+/// Free both FDCAN1 and FDCAN2 along with PREC.
+///
+/// Since FDCAN1 and FDCAN2 are controlled together, they are freed together.
+pub fn free_fdcan12(
+    fdcan1: Fdcan<FDCAN1>,
+    fdcan2: Fdcan<FDCAN2>,
+) -> (FDCAN1, FDCAN2, rec::Fdcan) {
+    (
+        fdcan1.rb,
+        fdcan2.rb,
+        rec::Fdcan {
+            _marker: PhantomData,
+        },
+    )
+}
+
+macro_rules! fdcan {
+    ($($FDCANX:ident: ($fdcanX:ident, $ram_start:expr),)+) => {
+        $(
+            impl Fdcan<$FDCANX> {
+                /// Create and initialise a new FDCAN peripheral
+                ///
+                /// # Panics
+                ///
+                /// Panics if the FDCAN kernel clock is not running, or if a
+                /// bit rate cannot be generated exactly from the kernel
+                /// clock.
+                pub fn $fdcanX(
+                    fdcan: $FDCANX,
+                    config: impl Into<Config>,
+                    prec: rec::Fdcan,
+                    clocks: &CoreClocks,
+                ) -> Self {
+                    let ker_ck = kernel_clk(&prec, clocks)
+                        .expect("FDCAN kernel clock not running!")
+                        .0;
+
+                    // Enable clock and reset
+                    prec.enable().reset();
+
+                    Self::initialise(fdcan, config.into(), ker_ck)
+                }
+
+                fn initialise(fdcan: $FDCANX, config: Config, ker_ck: u32) -> Self {
+                    let fd = config.frame_format != FrameFormat::Classic;
+
+                    // Calculate bit timing
+                    let nominal = calculate_bit_timing(
+                        ker_ck, config.nominal_bitrate.0, &NOMINAL_LIMITS
+                    ).expect("FDCAN nominal bit rate cannot be generated from the kernel clock!");
+                    let data = config.data_phase_bitrate().map(|bitrate| {
+                        calculate_bit_timing(ker_ck, bitrate.0, &DATA_LIMITS)
+                            .expect("FDCAN data bit rate cannot be generated from the kernel clock!")
+                    });
+
+                    // Enter initialisation mode, then allow configuration
+                    // changes. See RM0433 Rev 7 Section 56.4.1
+                    fdcan.cccr.modify(|_, w| w.init().set_bit());
+                    while fdcan.cccr.read().init().bit_is_clear() {}
+                    fdcan.cccr.modify(|_, w| w.cce().set_bit());
+
+                    fdcan.cccr.modify(|_, w| {
+                        w.fdoe()
+                            .bit(fd)
+                            .brse()
+                            .bit(config.frame_format == FrameFormat::FdBrs)
+                            .dar()
+                            .bit(!config.automatic_retransmission)
+                            .test()
+                            .clear_bit()
+                            .mon()
+                            .clear_bit()
+                            .asm()
+                            .clear_bit()
+                    });
+
+                    // Bit timing. Register values are one less than the
+                    // number of time quanta
+                    fdcan.nbtp.write(|w| unsafe {
+                        w.nbrp()
+                            .bits(nominal.prescaler - 1)
+                            .ntseg1()
+                            .bits((nominal.seg1 - 1) as u8)
+                            .ntseg2()
+                            .bits((nominal.seg2 - 1) as u8)
+                            .nsjw()
+                            .bits((nominal.sjw - 1) as u8)
+                    });
+                    if let Some(data) = data {
+                        fdcan.dbtp.write(|w| unsafe {
+                            w.dbrp()
+                                .bits((data.prescaler - 1) as u8)
+                                .dtseg1()
+                                .bits((data.seg1 - 1) as u8)
+                                .dtseg2()
+                                .bits((data.seg2 - 1) as u8)
+                                .dsjw()
+                                .bits((data.sjw - 1) as u8)
+                        });
+                    }
+
+                    // Message RAM
+                    let data_bytes = if fd { 64 } else { 8 };
+                    let layout = config.ram.layout($ram_start, data_bytes);
+
+                    // The message RAM is not initialised at reset
+                    for word in layout.standard_filters..layout.end {
+                        unsafe { ptr::write_volatile(ram_word(word), 0) };
+                    }
+
+                    fdcan.sidfc.write(|w| unsafe {
+                        w.flssa()
+                            .bits(layout.standard_filters)
+                            .lss()
+                            .bits(config.ram.standard_filters)
+                    });
+                    fdcan.xidfc.write(|w| unsafe {
+                        w.flesa()
+                            .bits(layout.extended_filters)
+                            .lse()
+                            .bits(config.ram.extended_filters)
+                    });
+                    fdcan.rxf0c.write(|w| unsafe {
+                        w.f0sa()
+                            .bits(layout.rx_fifo0)
+                            .f0s()
+                            .bits(config.ram.rx_fifo0)
+                            .f0wm()
+                            .bits(0)
+                            .f0om()
+                            .clear_bit() // blocking mode
+                    });
+                    fdcan.rxf1c.write(|w| unsafe {
+                        w.f1sa()
+                            .bits(layout.rx_fifo1)
+                            .f1s()
+                            .bits(config.ram.rx_fifo1)
+                            .f1wm()
+                            .bits(0)
+                            .f1om()
+                            .clear_bit() // blocking mode
+                    });
+                    fdcan.rxbc.reset();
+                    fdcan.txefc.reset();
+                    fdcan.txbc.write(|w| unsafe {
+                        w.tbsa()
+                            .bits(layout.tx_buffers)
+                            .ndtb()
+                            .bits(0)
+                            .tfqs()
+                            .bits(config.ram.tx_buffers)
+                            .tfqm()
+                            .set_bit() // Tx queue, prioritised by ID
+                    });
+
+                    // Element data field size: 8 bytes (0b000) or 64 bytes (0b111)
+                    let ds = if fd { 0b111 } else { 0b000 };
+                    fdcan.rxesc.write(|w| unsafe {
+                        w.f0ds().bits(ds).f1ds().bits(ds).rbds().bits(ds)
+                    });
+                    fdcan.txesc.write(|w| unsafe { w.tbds().bits(ds) });
+
+                    // Non-matching frames are stored in Rx FIFO 0 or
+                    // rejected. Remote frames are filtered like data frames
+                    let anf = if config.reject_non_matching { 0b10 } else { 0b00 };
+                    fdcan.gfc.write(|w| unsafe {
+                        w.anfs().bits(anf).anfe().bits(anf)
+                    });
+
+                    // All interrupts on line 0
+                    fdcan.ils.reset();
+                    fdcan.ile.write(|w| w.eint0().set_bit());
+
+                    // Leave initialisation mode
+                    fdcan.cccr.modify(|_, w| w.cce().clear_bit());
+                    fdcan.cccr.modify(|_, w| w.init().clear_bit());
+                    while fdcan.cccr.read().init().bit_is_set() {}
+
+                    Fdcan {
+                        rb: fdcan,
+                        layout,
+                        ram: config.ram,
+                        frame_format: config.frame_format,
+                    }
+                }
+
+                /// Write standard ID filter element `index`
+                ///
+                /// # Panics
+                ///
+                /// Panics if `index` is outside of the standard filter list
+                /// configured in [`RamConfig`](RamConfig)
+                pub fn set_standard_filter(&mut self, index: u8, filter: StandardFilter) {
+                    assert!(index < self.ram.standard_filters);
+
+                    let word = self.layout.standard_filters + index as u16;
+                    unsafe { ptr::write_volatile(ram_word(word), filter.element()) };
+                }
+
+                /// Write extended ID filter element `index`
+                ///
+                /// # Panics
+                ///
+                /// Panics if `index` is outside of the extended filter list
+                /// configured in [`RamConfig`](RamConfig)
+                pub fn set_extended_filter(&mut self, index: u8, filter: ExtendedFilter) {
+                    assert!(index < self.ram.extended_filters);
+
+                    let word = self.layout.extended_filters + 2 * index as u16;
+                    let element = filter.element();
+                    unsafe {
+                        ptr::write_volatile(ram_word(word), element[0]);
+                        ptr::write_volatile(ram_word(word + 1), element[1]);
+                    }
+                }
+
+                /// Disable standard ID filter element `index`
+                pub fn disable_standard_filter(&mut self, index: u8) {
+                    assert!(index < self.ram.standard_filters);
+
+                    let word = self.layout.standard_filters + index as u16;
+                    unsafe { ptr::write_volatile(ram_word(word), 0) };
+                }
+
+                /// Disable extended ID filter element `index`
+                pub fn disable_extended_filter(&mut self, index: u8) {
+                    assert!(index < self.ram.extended_filters);
+
+                    let word = self.layout.extended_filters + 2 * index as u16;
+                    unsafe { ptr::write_volatile(ram_word(word), 0) };
+                }
+
+                /// Set the extended ID AND mask, applied to extended IDs
+                /// before filtering
+                pub fn set_extended_id_mask(&mut self, mask: ExtendedId) {
+                    self.enter_configuration();
+                    self.rb.xidam.write(|w| unsafe { w.eidm().bits(mask.as_raw()) });
+                    self.leave_configuration();
+                }
+
+                fn enter_configuration(&mut self) {
+                    self.rb.cccr.modify(|_, w| w.init().set_bit());
+                    while self.rb.cccr.read().init().bit_is_clear() {}
+                    self.rb.cccr.modify(|_, w| w.cce().set_bit());
+                }
+
+                fn leave_configuration(&mut self) {
+                    self.rb.cccr.modify(|_, w| w.cce().clear_bit());
+                    self.rb.cccr.modify(|_, w| w.init().clear_bit());
+                    while self.rb.cccr.read().init().bit_is_set() {}
+                }
+
+                /// Returns true if the node is in the Bus_Off state
+                pub fn is_bus_off(&self) -> bool {
+                    self.rb.psr.read().bo().bit_is_set()
+                }
+
+                /// Start the bus off recovery sequence
+                ///
+                /// The node rejoins the bus after 129 occurrences of 11
+                /// consecutive recessive bits
+                pub fn recover(&mut self) {
+                    self.rb.cccr.modify(|_, w| w.init().clear_bit());
+                }
+
+                /// Returns the transmit and receive error counters
+                pub fn error_counters(&self) -> (u8, u8) {
+                    let ecr = self.rb.ecr.read();
+                    (ecr.tec().bits(), ecr.rec().bits())
+                }
+
+                /// Start listening for `event`
+                pub fn listen(&mut self, event: Event) {
+                    self.rb.ie.modify(|_, w| match event {
+                        Event::RxFifo0NewMessage => w.rf0ne().set_bit(),
+                        Event::RxFifo0MessageLost => w.rf0le().set_bit(),
+                        Event::RxFifo1NewMessage => w.rf1ne().set_bit(),
+                        Event::RxFifo1MessageLost => w.rf1le().set_bit(),
+                        Event::TransmissionComplete => w.tce().set_bit(),
+                        Event::TxQueueEmpty => w.tfee().set_bit(),
+                        Event::ErrorWarning => w.ewe().set_bit(),
+                        Event::ErrorPassive => w.epe().set_bit(),
+                        Event::BusOff => w.boe().set_bit(),
+                    });
+                    if event == Event::TransmissionComplete {
+                        // Transmission interrupt for all Tx buffers
+                        self.rb.txbtie.write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+                    }
+                }
+
+                /// Stop listening for `event`
+                pub fn unlisten(&mut self, event: Event) {
+                    self.rb.ie.modify(|_, w| match event {
+                        Event::RxFifo0NewMessage => w.rf0ne().clear_bit(),
+                        Event::RxFifo0MessageLost => w.rf0le().clear_bit(),
+                        Event::RxFifo1NewMessage => w.rf1ne().clear_bit(),
+                        Event::RxFifo1MessageLost => w.rf1le().clear_bit(),
+                        Event::TransmissionComplete => w.tce().clear_bit(),
+                        Event::TxQueueEmpty => w.tfee().clear_bit(),
+                        Event::ErrorWarning => w.ewe().clear_bit(),
+                        Event::ErrorPassive => w.epe().clear_bit(),
+                        Event::BusOff => w.boe().clear_bit(),
+                    });
+                    let _ = self.rb.ie.read();
+                    let _ = self.rb.ie.read(); // Delay 2 peripheral clocks
+                }
+
+                /// Returns `true` if `event` is pending
+                pub fn is_pending(&self, event: Event) -> bool {
+                    let ir = self.rb.ir.read();
+                    match event {
+                        Event::RxFifo0NewMessage => ir.rf0n().bit_is_set(),
+                        Event::RxFifo0MessageLost => ir.rf0l().bit_is_set(),
+                        Event::RxFifo1NewMessage => ir.rf1n().bit_is_set(),
+                        Event::RxFifo1MessageLost => ir.rf1l().bit_is_set(),
+                        Event::TransmissionComplete => ir.tc().bit_is_set(),
+                        Event::TxQueueEmpty => ir.tfe().bit_is_set(),
+                        Event::ErrorWarning => ir.ew().bit_is_set(),
+                        Event::ErrorPassive => ir.ep().bit_is_set(),
+                        Event::BusOff => ir.bo().bit_is_set(),
+                    }
+                }
+
+                /// Clears interrupt flag for `event`
+                pub fn clear_irq(&mut self, event: Event) {
+                    // Flags are cleared by writing 1
+                    self.rb.ir.write(|w| match event {
+                        Event::RxFifo0NewMessage => w.rf0n().set_bit(),
+                        Event::RxFifo0MessageLost => w.rf0l().set_bit(),
+                        Event::RxFifo1NewMessage => w.rf1n().set_bit(),
+                        Event::RxFifo1MessageLost => w.rf1l().set_bit(),
+                        Event::TransmissionComplete => w.tc().set_bit(),
+                        Event::TxQueueEmpty => w.tfe().set_bit(),
+                        Event::ErrorWarning => w.ew().set_bit(),
+                        Event::ErrorPassive => w.ep().set_bit(),
+                        Event::BusOff => w.bo().set_bit(),
+                    });
+                    let _ = self.rb.ir.read();
+                    let _ = self.rb.ir.read(); // Delay 2 peripheral clocks
+                }
+
+                /// Returns the last error code recorded on the bus, if any
+                fn last_error(&self) -> Option<Error> {
+                    match self.rb.psr.read().lec().bits() {
+                        1 => Some(Error::Stuff),
+                        2 => Some(Error::Form),
+                        3 => Some(Error::Acknowledge),
+                        4 | 5 => Some(Error::Bit),
+                        6 => Some(Error::Crc),
+                        _ => None,
+                    }
+                }
+
+                /// Read a frame from Rx FIFO 0 (`fifo1` false) or FIFO 1
+                fn read_fifo(&mut self, fifo1: bool) -> nb::Result<Frame, Error> {
+                    let (fill, get_index, lost, base) = if fifo1 {
+                        let s = self.rb.rxf1s.read();
+                        (s.f1fl().bits(), s.f1gi().bits(), s.rf1l().bit_is_set(), self.layout.rx_fifo1)
+                    } else {
+                        let s = self.rb.rxf0s.read();
+                        (s.f0fl().bits(), s.f0gi().bits(), s.rf0l().bit_is_set(), self.layout.rx_fifo0)
+                    };
+
+                    if lost {
+                        self.clear_irq(if fifo1 {
+                            Event::RxFifo1MessageLost
+                        } else {
+                            Event::RxFifo0MessageLost
+                        });
+                        return Err(nb::Error::Other(Error::Overrun));
+                    }
+                    if fill == 0 {
+                        return Err(nb::Error::WouldBlock);
+                    }
+
+                    let element = base + self.layout.element_words * get_index as u16;
+                    let frame = unsafe { read_rx_element(element) };
+
+                    // Acknowledge
+                    if fifo1 {
+                        self.rb.rxf1a.write(|w| unsafe { w.f1ai().bits(get_index) });
+                    } else {
+                        self.rb.rxf0a.write(|w| unsafe { w.f0ai().bits(get_index) });
+                    }
+
+                    Ok(frame)
+                }
+
+                /// Receive a frame from Rx FIFO 0
+                pub fn receive_fifo0(&mut self) -> nb::Result<Frame, Error> {
+                    self.read_fifo(false)
+                }
+
+                /// Receive a frame from Rx FIFO 1
+                pub fn receive_fifo1(&mut self) -> nb::Result<Frame, Error> {
+                    self.read_fifo(true)
+                }
+
+                /// Returns the number of frames pending in the Tx queue
+                pub fn tx_pending(&self) -> u32 {
+                    self.rb.txbrp.read().bits().count_ones()
+                }
+
+                /// Releases the FDCAN peripheral
+                pub fn free(mut self) -> ($FDCANX, rec::Fdcan) {
+                    // Stop participating on the bus
+                    self.enter_configuration();
+
+                    (self.rb, rec::Fdcan { _marker: PhantomData })
+                }
+            }
+
+            impl embedded_can::nb::Can for Fdcan<$FDCANX> {
+                type Frame = Frame;
+                type Error = Error;
+
+                fn transmit(&mut self, frame: &Frame) -> nb::Result<Option<Frame>, Error> {
+                    if self.is_bus_off() {
+                        return Err(nb::Error::Other(Error::BusOff));
+                    }
+                    assert!(
+                        !frame.fd || self.frame_format != FrameFormat::Classic,
+                        "CAN-FD frames require CAN-FD operation to be enabled"
+                    );
+
+                    let txfqs = self.rb.txfqs.read();
+                    if txfqs.tfqf().bit_is_set() {
+                        return Err(nb::Error::WouldBlock);
+                    }
+                    let put_index = txfqs.tfqpi().bits();
+
+                    let element = self.layout.tx_buffers
+                        + self.layout.element_words * put_index as u16;
+                    unsafe { write_tx_element(element, frame) };
+
+                    // Request transmission
+                    self.rb.txbar.write(|w| unsafe { w.bits(1 << put_index) });
+
+                    Ok(None)
+                }
+
+                fn receive(&mut self) -> nb::Result<Frame, Error> {
+                    match self.read_fifo(false) {
+                        Err(nb::Error::WouldBlock) => {}
+                        result => return result,
+                    }
+                    match self.read_fifo(true) {
+                        Err(nb::Error::WouldBlock) => {}
+                        result => return result,
+                    }
+
+                    if self.is_bus_off() {
+                        return Err(nb::Error::Other(Error::BusOff));
+                    }
+                    match self.last_error() {
+                        Some(e) => Err(nb::Error::Other(e)),
+                        None => Err(nb::Error::WouldBlock),
+                    }
+                }
+            }
+
+            impl FdcanExt<$FDCANX> for $FDCANX {
+                type Rec = rec::Fdcan;
+
+                fn fdcan(
+                    self,
+                    _pins: impl Pins<$FDCANX>,
+                    config: impl Into<Config>,
+                    prec: rec::Fdcan,
+                    clocks: &CoreClocks,
+                ) -> Fdcan<$FDCANX> {
+                    Fdcan::$fdcanX(self, config, prec, clocks)
+                }
+
+                fn fdcan_unchecked(
+                    self,
+                    config: impl Into<Config>,
+                    prec: rec::Fdcan,
+                    clocks: &CoreClocks,
+                ) -> Fdcan<$FDCANX> {
+                    Fdcan::$fdcanX(self, config, prec, clocks)
+                }
+            }
+        )+
+    };
+}
+
+/// Pointer to `word` of the message RAM
+fn ram_word(word: u16) -> *mut u32 {
+    (MESSAGE_RAM_BASE + 4 * word as usize) as *mut u32
+}
+
+/// Read a Rx element starting at `word`. See RM0433 Rev 7 Section 56.5.1
+///
+/// # Safety
+///
+/// `word` must be the start of a valid Rx element owned by the caller
+unsafe fn read_rx_element(word: u16) -> Frame {
+    let r0 = ptr::read_volatile(ram_word(word));
+    let r1 = ptr::read_volatile(ram_word(word + 1));
+
+    let id = if r0 & (1 << 30) != 0 {
+        Id::Extended(ExtendedId::new(r0 & 0x1FFF_FFFF).unwrap())
+    } else {
+        Id::Standard(StandardId::new(((r0 >> 18) & 0x7FF) as u16).unwrap())
+    };
+
+    let mut frame = Frame {
+        id,
+        remote: r0 & (1 << 29) != 0,
+        fd: r1 & (1 << 21) != 0,
+        brs: r1 & (1 << 20) != 0,
+        dlc: ((r1 >> 16) & 0xF) as u8,
+        data: [0; 64],
+    };
+
+    let len = frame.len();
+    for (i, chunk) in frame.data[..len].chunks_mut(4).enumerate() {
+        let bytes =
+            ptr::read_volatile(ram_word(word + 2 + i as u16)).to_le_bytes();
+        chunk.copy_from_slice(&bytes[..chunk.len()]);
+    }
+
+    frame
+}
+
+/// Write `frame` to a Tx element starting at `word`. See RM0433 Rev 7 Section
+/// 56.5.1
+///
+/// # Safety
+///
+/// `word` must be the start of a valid Tx element owned by the caller
+unsafe fn write_tx_element(word: u16, frame: &Frame) {
+    let t0 = match frame.id {
+        Id::Standard(id) => (id.as_raw() as u32) << 18,
+        Id::Extended(id) => (1 << 30) | id.as_raw(),
+    } | ((frame.remote as u32) << 29);
+    let t1 = ((frame.fd as u32) << 21)
+        | ((frame.brs as u32) << 20)
+        | ((frame.dlc as u32) << 16);
+
+    ptr::write_volatile(ram_word(word), t0);
+    ptr::write_volatile(ram_word(word + 1), t1);
+
+    let len = frame.len();
+    for (i, chunk) in frame.data[..len].chunks(4).enumerate() {
+        let mut bytes = [0; 4];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+        ptr::write_volatile(
+            ram_word(word + 2 + i as u16),
+            u32::from_le_bytes(bytes),
+        );
+    }
+}
+
+fdcan! {
+    FDCAN1: (fdcan1, 0),
+    FDCAN2: (fdcan2, MESSAGE_RAM_WORDS),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check that a bit timing generates `bitrate` exactly and lies within
+    /// `limits`
+    fn check_timing(
+        ker_ck: u32,
+        bitrate: u32,
+        limits: &BitTimingLimits,
+    ) -> BitTiming {
+        let t = calculate_bit_timing(ker_ck, bitrate, limits).unwrap();
+        let tq = 1 + t.seg1 as u32 + t.seg2 as u32;
+
+        assert_eq!(ker_ck, t.prescaler as u32 * tq * bitrate);
+        assert!(t.prescaler >= 1 && t.prescaler <= limits.prescaler);
+        assert!(t.seg1 >= limits.seg1.0 && t.seg1 <= limits.seg1.1);
+        assert!(t.seg2 >= limits.seg2.0 && t.seg2 <= limits.seg2.1);
+        assert!(t.sjw >= 1 && t.sjw <= t.seg2);
+
+        // Sample point between 75% and 90%
+        let sample = 1000 * (1 + t.seg1 as u32) / tq;
+        assert!(sample >= 750 && sample <= 900, "sample point {}", sample);
+
+        t
+    }
+
+    #[test]
+    fn nominal_bit_timing() {
+        for &ker_ck in [20_000_000, 24_000_000, 40_000_000, 80_000_000].iter() {
+            for &bitrate in
+                [10_000, 50_000, 125_000, 250_000, 500_000, 1_000_000].iter()
+            {
+                check_timing(ker_ck, bitrate, &NOMINAL_LIMITS);
+            }
+        }
+    }
+
+    #[test]
+    fn data_bit_timing() {
+        for &ker_ck in [40_000_000, 80_000_000].iter() {
+            for &bitrate in [1_000_000, 2_000_000, 4_000_000, 5_000_000].iter()
+            {
+                check_timing(ker_ck, bitrate, &DATA_LIMITS);
+            }
+        }
+        // Slowest data rate is limited by the prescaler
+        assert!(
+            calculate_bit_timing(80_000_000, 10_000, &DATA_LIMITS).is_none()
+        );
+    }
+
+    #[test]
+    fn bit_timing_not_exact() {
+        assert!(
+            calculate_bit_timing(25_000_000, 3_000_000, &DATA_LIMITS).is_none()
+        );
+        assert!(calculate_bit_timing(1_000_000, 2_000_000, &NOMINAL_LIMITS)
+            .is_none());
+    }
+
+    #[test]
+    fn classic_low_bitrate() {
+        // 1600 kernel clocks per bit is too long for the data bit timing,
+        // which is not used by classical CAN
+        let config = Config::new(Hertz(50_000));
+        assert!(config.data_phase_bitrate().is_none());
+        check_timing(80_000_000, 50_000, &NOMINAL_LIMITS);
+
+        let config = config.frame_format(FrameFormat::FdBrs);
+        assert_eq!(config.data_phase_bitrate(), Some(Hertz(50_000)));
+        let config = Config::new(Hertz(500_000)).data_bitrate(Hertz(2_000_000));
+        assert_eq!(config.data_phase_bitrate(), Some(Hertz(2_000_000)));
+    }
+
+    #[test]
+    fn ram_layout() {
+        let layout = RamConfig::default().layout(MESSAGE_RAM_WORDS, 64);
+        assert_eq!(layout.standard_filters, 1280);
+        assert_eq!(layout.extended_filters, 1280 + 28);
+        assert_eq!(layout.rx_fifo0, 1280 + 28 + 16);
+        assert_eq!(layout.element_words, 18);
+        assert!(layout.end <= 2 * MESSAGE_RAM_WORDS);
+    }
+
+    #[test]
+    fn dlc() {
+        assert_eq!(len_to_dlc(0), Some(0));
+        assert_eq!(len_to_dlc(8), Some(8));
+        assert_eq!(len_to_dlc(9), Some(9));
+        assert_eq!(len_to_dlc(12), Some(9));
+        assert_eq!(len_to_dlc(33), Some(14));
+        assert_eq!(len_to_dlc(64), Some(15));
+        assert_eq!(len_to_dlc(65), None);
+    }
+}
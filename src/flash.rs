@@ -1,7 +1,291 @@
 //! Flash memory
+//!
+//! Each bank of the embedded flash must be unlocked before it can be
+//! programmed or erased. [`Flash::unlock`](Flash::unlock) returns an
+//! [`UnlockedBank`](UnlockedBank) that locks the bank again when it is
+//! dropped.
+//!
+//! Programming is performed one flash word at a time. A flash word is 256
+//! bits on RM0433 and RM0399 parts, and 128 bits on RM0455 parts. A flash
+//! word can only be programmed once after it has been erased.
+//!
+//! | Parts | Sector size | Flash word |
+//! | --- | --- | --- |
+//! | RM0433 (H742/743/750/753) | 128 KiB | 256 bits |
+//! | RM0399 (H745/747/755/757) | 128 KiB | 256 bits |
+//! | RM0455 (H7A3/7B3/7B0) | 8 KiB | 128 bits |
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use stm32h7xx_hal::flash::{Bank, SECTOR_SIZE};
+//! use stm32h7xx_hal::{pac, prelude::*};
+//!
+//! let dp = pac::Peripherals::take().unwrap();
+//! let mut flash = dp.FLASH.constrain();
+//!
+//! let config = [0x55u8; 64];
+//! let mut bank2 = flash.unlock(Bank::Bank2);
+//! bank2.erase_sector(7).unwrap();
+//! bank2.program(7 * SECTOR_SIZE, &config).unwrap();
+//! ```
+//!
+//! If the data cache is enabled, the affected addresses must be invalidated
+//! after programming or erasing before they are read back.
+//!
+//! # Bank swapping
+//!
+//! On parts with two banks, the banks can be swapped so that bank 2 is
+//! mapped at `0x0800_0000` and bank 1 at `0x0810_0000`. This is used for A/B
+//! firmware updates: the new image is programmed into the bank that is not
+//! currently executing, and then [`swap_banks`](Flash::swap_banks) is
+//! called. The swap takes effect after the next system reset.
+//!
+//! The [`Bank`](Bank) enumeration always refers to the address ranges, so
+//! `Bank::Bank1` is the bank currently mapped at `0x0800_0000`.
 
+use core::convert::TryInto;
+use core::ptr;
+
+use crate::signature::FlashSize;
 use crate::stm32::{flash, FLASH};
 
+/// Size of a flash sector in bytes
+#[cfg(not(feature = "rm0455"))]
+pub const SECTOR_SIZE: usize = 128 * 1024;
+/// Size of a flash sector in bytes
+#[cfg(feature = "rm0455")]
+pub const SECTOR_SIZE: usize = 8 * 1024;
+
+/// Size of a flash word in bytes. This is the smallest unit that can be
+/// programmed
+#[cfg(not(feature = "rm0455"))]
+pub const FLASH_WORD_SIZE: usize = 32;
+/// Size of a flash word in bytes. This is the smallest unit that can be
+/// programmed
+#[cfg(feature = "rm0455")]
+pub const FLASH_WORD_SIZE: usize = 16;
+
+/// Maximum size of a single bank in bytes
+const MAX_BANK_SIZE: usize = 1024 * 1024;
+
+/// Unlock keys. See RM0433 Rev 7 Section 4.5.1
+const KEY1: u32 = 0x4567_0123;
+const KEY2: u32 = 0xCDEF_89AB;
+const OPTKEY1: u32 = 0x0819_2A3B;
+const OPTKEY2: u32 = 0x4C5D_6E7F;
+
+// FLASH_CRx bits
+const CR_LOCK: u32 = 1 << 0;
+const CR_PG: u32 = 1 << 1;
+const CR_SER: u32 = 1 << 2;
+const CR_BER: u32 = 1 << 3;
+#[cfg(not(feature = "rm0455"))]
+const CR_PSIZE_X64: u32 = 0b11 << 4;
+#[cfg(feature = "rm0455")]
+const CR_PSIZE_X64: u32 = 0; // Fixed parallelism
+#[cfg(not(feature = "rm0455"))]
+const CR_START: u32 = 1 << 7;
+#[cfg(feature = "rm0455")]
+const CR_START: u32 = 1 << 5;
+#[cfg(not(feature = "rm0455"))]
+const CR_SNB_SHIFT: u32 = 8;
+#[cfg(feature = "rm0455")]
+const CR_SNB_SHIFT: u32 = 6;
+#[cfg(not(feature = "rm0455"))]
+const CR_SNB_MASK: u32 = 0x7 << CR_SNB_SHIFT;
+#[cfg(feature = "rm0455")]
+const CR_SNB_MASK: u32 = 0x7F << CR_SNB_SHIFT;
+
+// FLASH_SRx bits. The FLASH_CCRx clear bits are at the same positions
+const SR_BSY: u32 = 1 << 0;
+const SR_QW: u32 = 1 << 2;
+const SR_WRPERR: u32 = 1 << 17;
+const SR_PGSERR: u32 = 1 << 18;
+const SR_STRBERR: u32 = 1 << 19;
+const SR_INCERR: u32 = 1 << 21;
+const SR_OPERR: u32 = 1 << 22;
+const SR_SNECCERR: u32 = 1 << 25;
+const SR_DBECCERR: u32 = 1 << 26;
+const CCR_CLEAR_ALL: u32 = 0x0FEF_0000;
+
+// FLASH_FAILx_ECC_ADDR
+#[cfg(not(feature = "rm0455"))]
+const ECC_FA_MASK: u32 = 0x7FFF;
+#[cfg(feature = "rm0455")]
+const ECC_FA_MASK: u32 = 0xFFFF;
+
+// FLASH_OPTCR, FLASH_OPTSR_x and FLASH_OPTCCR bits
+const OPTCR_OPTLOCK: u32 = 1 << 0;
+const OPTCR_OPTSTART: u32 = 1 << 1;
+const OPTSR_OPT_BUSY: u32 = 1 << 0;
+const OPTSR_OPTCHANGEERR: u32 = 1 << 30;
+const OPTSR_SWAP_BANK_OPT: u32 = 1 << 31;
+
+/// Flash errors
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The offset is not aligned to a flash word
+    Alignment,
+    /// The operation extends past the end of the bank
+    OutOfBounds,
+    /// The bank or sector is write protected
+    WriteProtection,
+    /// Programming sequence error
+    ProgrammingSequence,
+    /// Flash word was written more than once before programming completed
+    Strobe,
+    /// Inconsistency error. Writes to the flash word were not sequential
+    Inconsistency,
+    /// A write or erase operation could not complete. Usually this is an
+    /// ECC error on a flash word that was already programmed
+    Operation,
+    /// Modification of the option bytes failed
+    OptionChange,
+}
+
+/// Error-correcting code (ECC) events
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum EccError {
+    /// A single bit error was detected and corrected. Contains the offset
+    /// of the flash word within the bank
+    Corrected(usize),
+    /// A double bit error was detected but could not be corrected. Contains
+    /// the offset of the flash word within the bank
+    Uncorrectable(usize),
+}
+
+/// Flash bank
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Bank {
+    /// Bank mapped at `0x0800_0000`
+    Bank1,
+    /// Bank mapped at `0x0810_0000`
+    Bank2,
+}
+
+/// Register access for each bank. The bank 1 and bank 2 register sets are
+/// identical, except for their names in the PAC
+macro_rules! bank_registers {
+    ($($BANK:ident: ($keyr:ident, $cr:ident, $sr:ident, $ccr:ident,
+                     $ecc_fa:ident),)+) => {
+        impl Bank {
+            fn keyr_write(self, key: u32) {
+                let flash = unsafe { &*FLASH::ptr() };
+                match self {
+                    $(
+                        Bank::$BANK => flash.$keyr.write(|w| unsafe { w.bits(key) }),
+                    )+
+                }
+            }
+            fn cr_modify(self, f: impl FnOnce(u32) -> u32) {
+                let flash = unsafe { &*FLASH::ptr() };
+                match self {
+                    $(
+                        Bank::$BANK => flash.$cr.modify(|r, w| unsafe {
+                            w.bits(f(r.bits()))
+                        }),
+                    )+
+                }
+            }
+            fn cr(self) -> u32 {
+                let flash = unsafe { &*FLASH::ptr() };
+                match self {
+                    $(
+                        Bank::$BANK => flash.$cr.read().bits(),
+                    )+
+                }
+            }
+            fn sr(self) -> u32 {
+                let flash = unsafe { &*FLASH::ptr() };
+                match self {
+                    $(
+                        Bank::$BANK => flash.$sr.read().bits(),
+                    )+
+                }
+            }
+            fn ccr_write(self, bits: u32) {
+                let flash = unsafe { &*FLASH::ptr() };
+                match self {
+                    $(
+                        Bank::$BANK => flash.$ccr.write(|w| unsafe { w.bits(bits) }),
+                    )+
+                }
+            }
+            fn ecc_fa(self) -> u32 {
+                let flash = unsafe { &*FLASH::ptr() };
+                match self {
+                    $(
+                        Bank::$BANK => flash.$ecc_fa.read().bits(),
+                    )+
+                }
+            }
+        }
+    };
+}
+
+bank_registers! {
+    Bank1: (keyr1, cr1, sr1, ccr1, ecc_fa1r),
+    Bank2: (keyr2, cr2, sr2, ccr2, ecc_fa2r),
+}
+
+impl Bank {
+    /// Returns the address that this bank is mapped at
+    pub fn address(self) -> usize {
+        match self {
+            Bank::Bank1 => 0x0800_0000,
+            Bank::Bank2 => 0x0810_0000,
+        }
+    }
+
+    /// Returns the size of this bank in bytes. Returns zero if this bank
+    /// does not exist on this part
+    pub fn size(self) -> usize {
+        let total = FlashSize::get().bytes();
+
+        // Parts with 128 KiB of flash or less only have a single bank
+        if total <= 128 * 1024 {
+            match self {
+                Bank::Bank1 => total,
+                Bank::Bank2 => 0,
+            }
+        } else {
+            (total / 2).min(MAX_BANK_SIZE)
+        }
+    }
+
+    /// Returns the number of sectors in this bank
+    pub fn sectors(self) -> usize {
+        self.size() / SECTOR_SIZE
+    }
+
+    /// Wait for any ongoing operation on this bank to complete
+    fn wait_idle(self) {
+        while self.sr() & (SR_BSY | SR_QW) != 0 {}
+    }
+
+    /// Check and clear the error flags for this bank
+    fn errors(self) -> Result<(), Error> {
+        let sr = self.sr();
+        self.ccr_write(CCR_CLEAR_ALL & !(SR_SNECCERR | SR_DBECCERR));
+
+        if sr & SR_WRPERR != 0 {
+            Err(Error::WriteProtection)
+        } else if sr & SR_PGSERR != 0 {
+            Err(Error::ProgrammingSequence)
+        } else if sr & SR_STRBERR != 0 {
+            Err(Error::Strobe)
+        } else if sr & SR_INCERR != 0 {
+            Err(Error::Inconsistency)
+        } else if sr & SR_OPERR != 0 {
+            Err(Error::Operation)
+        } else {
+            Ok(())
+        }
+    }
+}
+
 /// Extension trait to constrain the FLASH peripheral
 pub trait FlashExt {
     /// Constrains the FLASH peripheral to play nicely with the other abstractions
@@ -34,3 +318,231 @@ impl ACR {
         unsafe { &(*FLASH::ptr()).acr }
     }
 }
+
+impl Flash {
+    /// Unlocks `bank` for programming and erasing. The bank is locked
+    /// again when the returned `UnlockedBank` is dropped
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bank` does not exist on this part
+    pub fn unlock(&mut self, bank: Bank) -> UnlockedBank<'_> {
+        assert!(bank.size() > 0, "Flash bank does not exist on this part");
+
+        bank.wait_idle();
+        if bank.cr() & CR_LOCK != 0 {
+            bank.keyr_write(KEY1);
+            bank.keyr_write(KEY2);
+        }
+        assert!(bank.cr() & CR_LOCK == 0);
+
+        UnlockedBank { _flash: self, bank }
+    }
+
+    /// Returns the error-correcting code (ECC) event for `bank` if there is
+    /// one, and clears it
+    ///
+    /// Uncorrectable errors also cause a bus error when the flash word is
+    /// read by the CPU
+    pub fn ecc_error(&mut self, bank: Bank) -> Option<EccError> {
+        let sr = bank.sr();
+        let offset = (bank.ecc_fa() & ECC_FA_MASK) as usize * FLASH_WORD_SIZE;
+
+        if sr & SR_DBECCERR != 0 {
+            bank.ccr_write(SR_DBECCERR | SR_SNECCERR);
+            Some(EccError::Uncorrectable(offset))
+        } else if sr & SR_SNECCERR != 0 {
+            bank.ccr_write(SR_SNECCERR);
+            Some(EccError::Corrected(offset))
+        } else {
+            None
+        }
+    }
+
+    /// Returns `true` if the banks are currently swapped, that is bank 2 is
+    /// mapped at `0x0800_0000`
+    pub fn banks_swapped(&self) -> bool {
+        let flash = unsafe { &*FLASH::ptr() };
+        flash.optsr_cur.read().bits() & OPTSR_SWAP_BANK_OPT != 0
+    }
+
+    /// Swaps the two flash banks
+    ///
+    /// This programs the SWAP_BANK option bit to the opposite of its
+    /// current value. The new mapping takes effect after the next system
+    /// reset, for example `cortex_m::peripheral::SCB::sys_reset()`. Calling
+    /// this method again before a reset cancels the swap.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this part only has a single bank
+    pub fn swap_banks(&mut self) -> Result<(), Error> {
+        assert!(Bank::Bank2.size() > 0, "Part only has a single bank");
+
+        let flash = unsafe { &*FLASH::ptr() };
+
+        Bank::Bank1.wait_idle();
+        Bank::Bank2.wait_idle();
+
+        // Unlock option bytes. See RM0433 Rev 7 Section 4.4.3
+        if flash.optcr.read().bits() & OPTCR_OPTLOCK != 0 {
+            flash.optkeyr.write(|w| unsafe { w.bits(OPTKEY1) });
+            flash.optkeyr.write(|w| unsafe { w.bits(OPTKEY2) });
+        }
+        flash
+            .optccr
+            .write(|w| unsafe { w.bits(OPTSR_OPTCHANGEERR) });
+
+        // Program the opposite of the current swap state
+        let swapped = self.banks_swapped();
+        flash.optsr_prg.modify(|r, w| unsafe {
+            w.bits(if swapped {
+                r.bits() & !OPTSR_SWAP_BANK_OPT
+            } else {
+                r.bits() | OPTSR_SWAP_BANK_OPT
+            })
+        });
+
+        // Start option byte modification
+        flash
+            .optcr
+            .modify(|r, w| unsafe { w.bits(r.bits() | OPTCR_OPTSTART) });
+        while flash.optsr_cur.read().bits() & OPTSR_OPT_BUSY != 0 {}
+
+        let failed = flash.optsr_cur.read().bits() & OPTSR_OPTCHANGEERR != 0;
+
+        // Lock option bytes
+        flash
+            .optcr
+            .modify(|r, w| unsafe { w.bits(r.bits() | OPTCR_OPTLOCK) });
+
+        if failed {
+            Err(Error::OptionChange)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A flash bank that is unlocked for programming and erasing
+///
+/// The bank is locked again when this is dropped
+pub struct UnlockedBank<'a> {
+    _flash: &'a mut Flash,
+    bank: Bank,
+}
+
+impl<'a> UnlockedBank<'a> {
+    /// Returns the bank
+    pub fn bank(&self) -> Bank {
+        self.bank
+    }
+
+    /// Returns the contents of the bank
+    pub fn read(&self) -> &[u8] {
+        // NOTE(unsafe) The bank is mapped for its full size, and cannot
+        // be modified whilst this reference exists
+        unsafe {
+            core::slice::from_raw_parts(
+                self.bank.address() as *const u8,
+                self.bank.size(),
+            )
+        }
+    }
+
+    /// Erases sector number `sector` within the bank
+    pub fn erase_sector(&mut self, sector: u8) -> Result<(), Error> {
+        if usize::from(sector) >= self.bank.sectors() {
+            return Err(Error::OutOfBounds);
+        }
+
+        self.bank.wait_idle();
+        self.bank.errors().ok(); // Clear stale errors
+
+        let snb = (u32::from(sector) << CR_SNB_SHIFT) & CR_SNB_MASK;
+        self.bank
+            .cr_modify(|r| (r & !CR_SNB_MASK) | CR_SER | CR_PSIZE_X64 | snb);
+        self.bank.cr_modify(|r| r | CR_START);
+        self.bank.wait_idle();
+        self.bank.cr_modify(|r| r & !(CR_SER | CR_SNB_MASK));
+
+        self.bank.errors()
+    }
+
+    /// Erases all sectors in the bank
+    ///
+    /// Erasing the bank that the program is currently executing from will
+    /// stall the CPU and leave no program to return to.
+    pub fn erase(&mut self) -> Result<(), Error> {
+        self.bank.wait_idle();
+        self.bank.errors().ok(); // Clear stale errors
+
+        self.bank.cr_modify(|r| r | CR_BER | CR_PSIZE_X64);
+        self.bank.cr_modify(|r| r | CR_START);
+        self.bank.wait_idle();
+        self.bank.cr_modify(|r| r & !CR_BER);
+
+        self.bank.errors()
+    }
+
+    /// Programs `data` at byte `offset` within the bank
+    ///
+    /// `offset` must be aligned to [`FLASH_WORD_SIZE`](FLASH_WORD_SIZE).
+    /// If the length of `data` is not a multiple of `FLASH_WORD_SIZE`, the
+    /// last flash word is padded with `0xFF`. The flash words must have
+    /// been erased beforehand.
+    pub fn program(&mut self, offset: usize, data: &[u8]) -> Result<(), Error> {
+        if offset % FLASH_WORD_SIZE != 0 {
+            return Err(Error::Alignment);
+        }
+        if offset + data.len() > self.bank.size() {
+            return Err(Error::OutOfBounds);
+        }
+
+        self.bank.wait_idle();
+        self.bank.errors().ok(); // Clear stale errors
+
+        self.bank.cr_modify(|r| r | CR_PG | CR_PSIZE_X64);
+        let result = self.program_words(offset, data);
+        self.bank.cr_modify(|r| r & !CR_PG);
+
+        result
+    }
+
+    fn program_words(
+        &mut self,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        let mut address = self.bank.address() + offset;
+
+        for chunk in data.chunks(FLASH_WORD_SIZE) {
+            let mut word = [0xFF; FLASH_WORD_SIZE];
+            word[..chunk.len()].copy_from_slice(chunk);
+
+            // The flash word is written to the write buffer 32 bits at a
+            // time. Programming starts once the buffer is full
+            for (i, bytes) in word.chunks_exact(4).enumerate() {
+                let value = u32::from_le_bytes(bytes.try_into().unwrap());
+                unsafe {
+                    ptr::write_volatile((address + 4 * i) as *mut u32, value)
+                };
+            }
+            cortex_m::asm::dsb();
+
+            self.bank.wait_idle();
+            self.bank.errors()?;
+
+            address += FLASH_WORD_SIZE;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Drop for UnlockedBank<'a> {
+    fn drop(&mut self) {
+        self.bank.wait_idle();
+        self.bank.cr_modify(|r| r | CR_LOCK);
+    }
+}
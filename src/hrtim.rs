@@ -0,0 +1,1341 @@
+//! High Resolution Timer (HRTIM)
+//!
+//! The HRTIM consists of a master timer and five timing units, Timer A to
+//! Timer E. Each timing unit drives two outputs through a set/reset
+//! crossbar, so that the output edges can be generated from any
+//! combination of the timer's own period and compare events, the master
+//! timer events and external events.
+//!
+//! On STM32H7 parts the HRTIM has no delay-locked loop (DLL), so the
+//! resolution of the counters is one period of the HRTIM clock. The HRTIM
+//! is clocked by `timy_ker_ck` by default, or by `c_ck` if the HRTIMSEL bit
+//! is set in the RCC.
+//!
+//! # Usage
+//!
+//! Two phase interleaved PWM at 100kHz, where Timer B is reset from the
+//! master timer at half of the period:
+//!
+//! ```
+//! use stm32h7xx_hal::hrtim::{Compare, Events, CounterReset, Timers};
+//!
+//! let hrtim = dp.HRTIM_COMMON.hrtim(
+//!     dp.HRTIM_MASTER,
+//!     (dp.HRTIM_TIMA, dp.HRTIM_TIMB, dp.HRTIM_TIMC, dp.HRTIM_TIMD, dp.HRTIM_TIME),
+//!     ccdr.peripheral.HRTIM,
+//!     &ccdr.clocks,
+//! );
+//! let mut control = hrtim.control;
+//!
+//! // 400MHz HRTIM clock, period of 4000 ticks
+//! let _master = hrtim.master
+//!     .frequency(100.khz())
+//!     .compare(Compare::Compare1, 2000)
+//!     .finalize();
+//!
+//! let mut tima = hrtim.tima
+//!     .frequency(100.khz())
+//!     .compare(Compare::Compare1, 300)
+//!     .reset_on(CounterReset::MASTER_PERIOD)
+//!     .output1(gpioc.pc6.into_alternate_af1(), Events::PERIOD, Events::COMPARE1)
+//!     .finalize();
+//! let mut timb = hrtim.timb
+//!     .frequency(100.khz())
+//!     .compare(Compare::Compare1, 300)
+//!     .reset_on(CounterReset::MASTER_COMPARE1)
+//!     .output1(gpioc.pc8.into_alternate_af1(), Events::PERIOD, Events::COMPARE1)
+//!     .finalize();
+//!
+//! tima.enable_outputs();
+//! timb.enable_outputs();
+//! control.start(Timers::MASTER | Timers::A | Timers::B);
+//! ```
+//!
+//! ## Dead time
+//!
+//! When dead time is enabled on a timing unit, output 2 is the complement
+//! of output 1 and its set/reset crossbar is not used.
+//!
+//! ## Fault inputs
+//!
+//! The five fault inputs are configured once for the whole HRTIM using
+//! [HrtimControl::fault_input](HrtimControl::fault_input), and then
+//! enabled for each timing unit using
+//! [HrTimerBuilder::fault](HrTimerBuilder::fault). When a fault occurs,
+//! the outputs go to their fault state and are disabled until they are
+//! enabled again.
+//!
+//! ## Burst mode
+//!
+//! Burst mode periodically forces outputs to their idle state, for example
+//! to improve the efficiency of a converter at light load. Outputs take
+//! part in burst mode if they were configured with
+//! [output1_idle_in_burst](HrTimerBuilder::output1_idle_in_burst) or
+//! [output2_idle_in_burst](HrTimerBuilder::output2_idle_in_burst).
+//!
+//! ## ADC triggers
+//!
+//! The HRTIM has four ADC trigger outputs, each of which is the OR of a
+//! set of HRTIM events. See [HrtimControl::adc_trigger](HrtimControl::adc_trigger).
+
+use core::ops::BitOr;
+
+use crate::gpio::gpioa::{PA10, PA11, PA12, PA15, PA8, PA9};
+use crate::gpio::gpiob::PB3;
+use crate::gpio::gpioc::{PC11, PC6, PC7, PC8};
+use crate::gpio::gpiod::PD4;
+use crate::gpio::gpiog::{PG10, PG6, PG7};
+use crate::gpio::{Alternate, AF1, AF2};
+
+use crate::pwm::{FaultMonitor, Polarity};
+use crate::rcc::{rec, CoreClocks, ResetEnable};
+use crate::stm32::{
+    HRTIM_COMMON, HRTIM_MASTER, HRTIM_TIMA, HRTIM_TIMB, HRTIM_TIMC, HRTIM_TIMD,
+    HRTIM_TIME,
+};
+use crate::time::{Hertz, NanoSeconds};
+
+/// Minimum value of the period register
+const PERIOD_MIN: u32 = 0x0003;
+/// Maximum value of the period register
+const PERIOD_MAX: u32 = 0xFFFD;
+/// Maximum value of the dead time registers
+const DEADTIME_MAX: u32 = 0x1FF;
+
+// TIMxCR and MCR bits, common to the master timer and timing units
+const CR_CONT: u32 = 1 << 3;
+const CR_PREEN: u32 = 1 << 27;
+// TIMxCR bits
+const TIMCR_TREPU: u32 = 1 << 17;
+// MCR bits
+const MCR_MREPU: u32 = 1 << 29;
+const MCR_MCEN: u32 = 1 << 16;
+
+// OUTxR bits, for output 1. Output 2 bits are shifted by 16
+const OUTR_POL: u32 = 1 << 1;
+const OUTR_IDLEM: u32 = 1 << 2;
+const OUTR_IDLES: u32 = 1 << 3;
+const OUTR_FAULT_SHIFT: u32 = 4;
+const OUTR_DTEN: u32 = 1 << 8;
+
+// DTxR bits
+const DTR_DTPRSC_SHIFT: u32 = 10;
+const DTR_DTF_SHIFT: u32 = 16;
+
+// BMCR bits
+const BMCR_BME: u32 = 1 << 0;
+const BMCR_BMOM: u32 = 1 << 1;
+const BMCR_BMCLK_SHIFT: u32 = 2;
+const BMCR_BMPRSC_SHIFT: u32 = 6;
+const BMCR_BMPREN: u32 = 1 << 10;
+const BMCR_BMSTAT: u32 = 1 << 31;
+
+/// Compare registers of a timer
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Compare {
+    Compare1,
+    Compare2,
+    Compare3,
+    Compare4,
+}
+
+/// Interrupt events of a timer
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Event {
+    /// Compare 1 match
+    Compare1,
+    /// Compare 2 match
+    Compare2,
+    /// Compare 3 match
+    Compare3,
+    /// Compare 4 match
+    Compare4,
+    /// Repetition counter underflow
+    Repetition,
+    /// Register update
+    Update,
+}
+
+impl Event {
+    /// Bit position in the xDIER, xISR and xICR registers. These are the
+    /// same for the master timer and the timing units
+    fn bit(self) -> u32 {
+        match self {
+            Event::Compare1 => 1 << 0,
+            Event::Compare2 => 1 << 1,
+            Event::Compare3 => 1 << 2,
+            Event::Compare4 => 1 << 3,
+            Event::Repetition => 1 << 4,
+            Event::Update => 1 << 6,
+        }
+    }
+}
+
+/// Define a bitmask type with some named bits
+macro_rules! bitmask {
+    ($(#[$meta:meta])* $Name:ident: $($(#[$cmeta:meta])* $CONST:ident = $bit:expr,)+) => {
+        $(#[$meta])*
+        #[derive(Copy, Clone, Debug, PartialEq)]
+        pub struct $Name(u32);
+
+        impl $Name {
+            $(
+                $(#[$cmeta])*
+                pub const $CONST: $Name = $Name(1 << $bit);
+            )+
+
+            /// No events
+            pub const NONE: $Name = $Name(0);
+
+            /// Construct from the raw register bits. See the reference
+            /// manual for the bit positions
+            pub const fn from_bits(bits: u32) -> Self {
+                $Name(bits)
+            }
+
+            /// Returns the raw register bits
+            pub const fn bits(self) -> u32 {
+                self.0
+            }
+        }
+
+        impl BitOr for $Name {
+            type Output = Self;
+
+            fn bitor(self, rhs: Self) -> Self {
+                $Name(self.0 | rhs.0)
+            }
+        }
+    };
+}
+
+bitmask! {
+    /// Events that can set or reset an output through the crossbar. See
+    /// RM0433 Rev 7 Section 37.5.40 HRTIM Timerx Output1 Set Register
+    Events:
+    /// Software set/reset
+    SOFTWARE = 0,
+    /// Timer x resynchronisation
+    RESYNC = 1,
+    /// Timer x period
+    PERIOD = 2,
+    /// Timer x compare 1
+    COMPARE1 = 3,
+    /// Timer x compare 2
+    COMPARE2 = 4,
+    /// Timer x compare 3
+    COMPARE3 = 5,
+    /// Timer x compare 4
+    COMPARE4 = 6,
+    /// Master timer period
+    MASTER_PERIOD = 7,
+    /// Master timer compare 1
+    MASTER_COMPARE1 = 8,
+    /// Master timer compare 2
+    MASTER_COMPARE2 = 9,
+    /// Master timer compare 3
+    MASTER_COMPARE3 = 10,
+    /// Master timer compare 4
+    MASTER_COMPARE4 = 11,
+    /// External event 1
+    EXTERNAL_EVENT1 = 21,
+    /// External event 2
+    EXTERNAL_EVENT2 = 22,
+    /// External event 3
+    EXTERNAL_EVENT3 = 23,
+    /// External event 4
+    EXTERNAL_EVENT4 = 24,
+    /// External event 5
+    EXTERNAL_EVENT5 = 25,
+    /// Registers update
+    UPDATE = 31,
+}
+
+bitmask! {
+    /// Events that reset the counter of a timing unit. See RM0433 Rev 7
+    /// Section 37.5.44 TIMx Reset Register
+    CounterReset:
+    /// Timer x update
+    UPDATE = 1,
+    /// Timer x compare 2
+    COMPARE2 = 2,
+    /// Timer x compare 4
+    COMPARE4 = 3,
+    /// Master timer period
+    MASTER_PERIOD = 4,
+    /// Master timer compare 1
+    MASTER_COMPARE1 = 5,
+    /// Master timer compare 2
+    MASTER_COMPARE2 = 6,
+    /// Master timer compare 3
+    MASTER_COMPARE3 = 7,
+    /// Master timer compare 4
+    MASTER_COMPARE4 = 8,
+    /// External event 1
+    EXTERNAL_EVENT1 = 9,
+    /// External event 2
+    EXTERNAL_EVENT2 = 10,
+}
+
+bitmask! {
+    /// Timers of the HRTIM. Used to start, stop, update and reset several
+    /// timers simultaneously
+    Timers:
+    /// Master timer
+    MASTER = 0,
+    /// Timer A
+    A = 1,
+    /// Timer B
+    B = 2,
+    /// Timer C
+    C = 3,
+    /// Timer D
+    D = 4,
+    /// Timer E
+    E = 5,
+}
+
+bitmask! {
+    /// Events for ADC trigger 1 and 3. See RM0433 Rev 7 Section 37.5.63
+    /// HRTIM ADC Trigger 1 Register
+    AdcTrigger13Events:
+    /// Master timer compare 1
+    MASTER_COMPARE1 = 0,
+    /// Master timer compare 2
+    MASTER_COMPARE2 = 1,
+    /// Master timer compare 3
+    MASTER_COMPARE3 = 2,
+    /// Master timer compare 4
+    MASTER_COMPARE4 = 3,
+    /// Master timer period
+    MASTER_PERIOD = 4,
+    /// Timer A compare 3
+    TIMA_COMPARE3 = 11,
+    /// Timer A period
+    TIMA_PERIOD = 13,
+    /// Timer B compare 3
+    TIMB_COMPARE3 = 16,
+    /// Timer B period
+    TIMB_PERIOD = 18,
+    /// Timer C compare 3
+    TIMC_COMPARE3 = 21,
+    /// Timer C period
+    TIMC_PERIOD = 23,
+    /// Timer D compare 3
+    TIMD_COMPARE3 = 25,
+    /// Timer D period
+    TIMD_PERIOD = 27,
+    /// Timer E compare 3
+    TIME_COMPARE3 = 29,
+    /// Timer E period
+    TIME_PERIOD = 31,
+}
+
+bitmask! {
+    /// Events for ADC trigger 2 and 4. See RM0433 Rev 7 Section 37.5.64
+    /// HRTIM ADC Trigger 2 Register
+    AdcTrigger24Events:
+    /// Master timer compare 1
+    MASTER_COMPARE1 = 0,
+    /// Master timer compare 2
+    MASTER_COMPARE2 = 1,
+    /// Master timer compare 3
+    MASTER_COMPARE3 = 2,
+    /// Master timer compare 4
+    MASTER_COMPARE4 = 3,
+    /// Master timer period
+    MASTER_PERIOD = 4,
+    /// Timer A compare 2
+    TIMA_COMPARE2 = 10,
+    /// Timer A period
+    TIMA_PERIOD = 13,
+    /// Timer B compare 2
+    TIMB_COMPARE2 = 14,
+    /// Timer B period
+    TIMB_PERIOD = 17,
+    /// Timer C compare 2
+    TIMC_COMPARE2 = 18,
+    /// Timer C period
+    TIMC_PERIOD = 21,
+    /// Timer D compare 2
+    TIMD_COMPARE2 = 23,
+    /// Timer D period
+    TIMD_PERIOD = 26,
+    /// Timer E compare 2
+    TIME_COMPARE2 = 28,
+}
+
+bitmask! {
+    /// Events that start a burst. See RM0433 Rev 7 Section 37.5.54 HRTIM
+    /// Burst Mode Trigger Register
+    BurstTrigger:
+    /// Software start
+    SOFTWARE = 0,
+    /// Master timer reset or roll-over
+    MASTER_RESET = 1,
+    /// Master timer repetition
+    MASTER_REPETITION = 2,
+    /// Master timer compare 1
+    MASTER_COMPARE1 = 3,
+    /// Master timer compare 2
+    MASTER_COMPARE2 = 4,
+    /// Master timer compare 3
+    MASTER_COMPARE3 = 5,
+    /// Master timer compare 4
+    MASTER_COMPARE4 = 6,
+    /// Timer A reset or roll-over
+    TIMA_RESET = 7,
+    /// Timer A repetition
+    TIMA_REPETITION = 8,
+}
+
+/// ADC trigger outputs
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AdcTrigger {
+    /// ADC trigger 1, with [AdcTrigger13Events](AdcTrigger13Events)
+    Trigger1(AdcTrigger13Events),
+    /// ADC trigger 2, with [AdcTrigger24Events](AdcTrigger24Events)
+    Trigger2(AdcTrigger24Events),
+    /// ADC trigger 3, with [AdcTrigger13Events](AdcTrigger13Events)
+    Trigger3(AdcTrigger13Events),
+    /// ADC trigger 4, with [AdcTrigger24Events](AdcTrigger24Events)
+    Trigger4(AdcTrigger24Events),
+}
+
+/// State of an output when a fault is active
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FaultState {
+    /// No action, the output is not affected by the fault
+    NoAction = 0b00,
+    /// The output goes to its active state
+    Active = 0b01,
+    /// The output goes to its inactive state
+    Inactive = 0b10,
+    /// The output goes to a high impedance state
+    HighZ = 0b11,
+}
+
+/// Fault inputs
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FaultInput {
+    Fault1 = 0,
+    Fault2 = 1,
+    Fault3 = 2,
+    Fault4 = 3,
+    Fault5 = 4,
+}
+
+/// Clock source for the burst mode counter
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BurstClock {
+    /// Master timer reset or roll-over
+    Master = 0b0000,
+    /// Timer A reset or roll-over
+    TimerA = 0b0001,
+    /// Timer B reset or roll-over
+    TimerB = 0b0010,
+    /// Timer C reset or roll-over
+    TimerC = 0b0011,
+    /// Timer D reset or roll-over
+    TimerD = 0b0100,
+    /// Timer E reset or roll-over
+    TimerE = 0b0101,
+    /// The HRTIM clock, divided by the burst mode prescaler
+    Hrtim = 0b1010,
+}
+
+/// Burst mode configuration
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BurstMode {
+    /// Clock source for the burst mode counter
+    pub clock: BurstClock,
+    /// Burst mode prescaler when the clock is
+    /// [BurstClock::Hrtim](BurstClock::Hrtim). The clock is divided by
+    /// 2^prescaler. Maximum 15
+    pub prescaler: u8,
+    /// Burst mode period in burst clock cycles
+    pub period: u16,
+    /// Number of burst clock cycles that the outputs are idle for within
+    /// each period
+    pub idle: u16,
+    /// If `true`, burst mode continues until stopped. Otherwise a single
+    /// burst is performed for each trigger
+    pub continuous: bool,
+    /// Events that trigger a burst
+    pub trigger: BurstTrigger,
+}
+
+/// Marker type for output 1 of a timing unit
+pub struct Output1;
+/// Marker type for output 2 of a timing unit
+pub struct Output2;
+
+/// Marks which GPIO pins may be used as HRTIM outputs; it should not be
+/// directly used. See the device datasheet 'Pin descriptions' chapter for
+/// which pins can be used (or look at Implementors)
+pub trait Pins<TIM, OUTPUT> {}
+
+/// Marks which GPIO pins may be used as HRTIM fault inputs; it should not
+/// be directly used
+pub trait FaultPins {
+    const INPUT: FaultInput;
+}
+
+macro_rules! pins {
+    ($($TIMX:ty: OUT1: [$($OUT1:ty),*] OUT2: [$($OUT2:ty),*])+) => {
+        $(
+            $(
+                impl Pins<$TIMX, Output1> for $OUT1 {}
+            )*
+            $(
+                impl Pins<$TIMX, Output2> for $OUT2 {}
+            )*
+        )+
+    };
+}
+macro_rules! fault_pins {
+    ($($FLT:ident: [$($PIN:ty),*])+) => {
+        $(
+            $(
+                impl FaultPins for $PIN {
+                    const INPUT: FaultInput = FaultInput::$FLT;
+                }
+            )*
+        )+
+    };
+}
+
+pins! {
+    HRTIM_TIMA:
+        OUT1: [ PC6<Alternate<AF1>> ]
+        OUT2: [ PC7<Alternate<AF1>> ]
+    HRTIM_TIMB:
+        OUT1: [ PC8<Alternate<AF1>> ]
+        OUT2: [ PA8<Alternate<AF2>> ]
+    HRTIM_TIMC:
+        OUT1: [ PA9<Alternate<AF2>> ]
+        OUT2: [ PA10<Alternate<AF2>> ]
+    HRTIM_TIMD:
+        OUT1: [ PA11<Alternate<AF2>> ]
+        OUT2: [ PA12<Alternate<AF2>> ]
+    HRTIM_TIME:
+        OUT1: [ PG6<Alternate<AF2>> ]
+        OUT2: [ PG7<Alternate<AF2>> ]
+}
+fault_pins! {
+    Fault1: [ PA15<Alternate<AF2>> ]
+    Fault2: [ PC11<Alternate<AF2>> ]
+    Fault3: [ PD4<Alternate<AF2>> ]
+    Fault4: [ PB3<Alternate<AF2>> ]
+    Fault5: [ PG10<Alternate<AF2>> ]
+}
+
+/// Calculates the prescaler and period for a timer. Returns (CKPSC, PER)
+///
+/// fCOUNTER = fHRTIM / 2^CKPSC
+///
+/// The smallest prescaler is chosen to give the finest resolution. Returns
+/// `None` if the frequency cannot be generated
+fn calculate_period(clk: u32, freq: u32) -> Option<(u8, u16)> {
+    if freq == 0 {
+        return None;
+    }
+
+    // Round to the nearest period
+    let ticks = (clk + freq / 2) / freq;
+
+    (0..=7).find_map(|ckpsc| {
+        let period = (ticks + (1 << ckpsc >> 1)) >> ckpsc;
+
+        match period {
+            PERIOD_MIN..=PERIOD_MAX => Some((ckpsc, period as u16)),
+            _ => None,
+        }
+    })
+}
+
+/// Calculates the dead time register values. Returns (DTPRSC, DTR, DTF)
+///
+/// tDTG = tHRTIM * 2^DTPRSC
+///
+/// The smallest prescaler is chosen that can represent both the rising and
+/// falling dead times. The dead times are rounded up. Returns `None` if the
+/// dead times are too long
+fn calculate_deadtime(
+    clk: u32,
+    rising: NanoSeconds,
+    falling: NanoSeconds,
+) -> Option<(u8, u16, u16)> {
+    // ticks = ns * Hz / 1e9, rounded up
+    let ticks = |ns: NanoSeconds| {
+        (u64::from(ns.0) * u64::from(clk) + 999_999_999) / 1_000_000_000
+    };
+    let (rising, falling) = (ticks(rising), ticks(falling));
+
+    (0..=7).find_map(|dtprsc| {
+        let div = 1 << dtprsc;
+        let dtr = (rising + div - 1) / div;
+        let dtf = (falling + div - 1) / div;
+
+        if dtr <= DEADTIME_MAX as u64 && dtf <= DEADTIME_MAX as u64 {
+            Some((dtprsc, dtr as u16, dtf as u16))
+        } else {
+            None
+        }
+    })
+}
+
+/// Configuration of a timing unit output
+#[derive(Copy, Clone)]
+struct OutputConfig {
+    enabled: bool,
+    set: Events,
+    reset: Events,
+    polarity: Polarity,
+    idle_in_burst: Option<Polarity>,
+    fault: FaultState,
+}
+
+impl OutputConfig {
+    const DISABLED: OutputConfig = OutputConfig {
+        enabled: false,
+        set: Events::NONE,
+        reset: Events::NONE,
+        polarity: Polarity::ActiveHigh,
+        idle_in_burst: None,
+        fault: FaultState::NoAction,
+    };
+
+    /// Returns the bits for the OUTxR register, for output 1
+    fn outr(&self) -> u32 {
+        let pol = match self.polarity {
+            Polarity::ActiveHigh => 0,
+            Polarity::ActiveLow => OUTR_POL,
+        };
+        let idle = match self.idle_in_burst {
+            None => 0,
+            Some(Polarity::ActiveLow) => OUTR_IDLEM,
+            Some(Polarity::ActiveHigh) => OUTR_IDLEM | OUTR_IDLES,
+        };
+
+        pol | idle | ((self.fault as u32) << OUTR_FAULT_SHIFT)
+    }
+}
+
+/// Builder for the master timer or a timing unit
+///
+/// The counter runs from zero to the period, and is reset either when it
+/// reaches the period (continuous mode), or by a counter reset event.
+pub struct HrTimerBuilder<TIM> {
+    tim: TIM,
+    clk: Hertz,
+    prescaler: u8,
+    period: u16,
+    compare: [u16; 4],
+    repetition: u8,
+    continuous: bool,
+    preload: bool,
+    reset: CounterReset,
+    outputs: [OutputConfig; 2],
+    deadtime: Option<(NanoSeconds, NanoSeconds)>,
+    faults: u32,
+}
+
+/// The master timer or a timing unit, configured and ready to be started
+/// with [HrtimControl::start](HrtimControl::start)
+pub struct HrTimer<TIM> {
+    tim: TIM,
+    outputs: u32,
+}
+
+/// Controls functionality that is common to the whole HRTIM
+pub struct HrtimControl {
+    common: HRTIM_COMMON,
+    clk: Hertz,
+    outputs: u32,
+}
+
+/// The HRTIM, split into the master timer, the five timing units and the
+/// common control
+pub struct Parts {
+    pub master: HrTimerBuilder<HRTIM_MASTER>,
+    pub tima: HrTimerBuilder<HRTIM_TIMA>,
+    pub timb: HrTimerBuilder<HRTIM_TIMB>,
+    pub timc: HrTimerBuilder<HRTIM_TIMC>,
+    pub timd: HrTimerBuilder<HRTIM_TIMD>,
+    pub time: HrTimerBuilder<HRTIM_TIME>,
+    pub control: HrtimControl,
+}
+
+/// Extension trait for the HRTIM
+pub trait HrtimExt {
+    /// Enable the HRTIM and split it into its timers
+    fn hrtim(
+        self,
+        master: HRTIM_MASTER,
+        timers: (HRTIM_TIMA, HRTIM_TIMB, HRTIM_TIMC, HRTIM_TIMD, HRTIM_TIME),
+        prec: rec::Hrtim,
+        clocks: &CoreClocks,
+    ) -> Parts;
+}
+
+/// Returns the current HRTIM clock
+fn kernel_clk(clocks: &CoreClocks) -> Hertz {
+    // unsafe: read only
+    let cfgr = unsafe { (*crate::stm32::RCC::ptr()).cfgr.read() };
+
+    if cfgr.hrtimsel().bit_is_set() {
+        clocks.c_ck()
+    } else {
+        clocks.timy_ker_ck()
+    }
+}
+
+impl HrtimExt for HRTIM_COMMON {
+    fn hrtim(
+        self,
+        master: HRTIM_MASTER,
+        timers: (HRTIM_TIMA, HRTIM_TIMB, HRTIM_TIMC, HRTIM_TIMD, HRTIM_TIME),
+        prec: rec::Hrtim,
+        clocks: &CoreClocks,
+    ) -> Parts {
+        prec.enable().reset();
+
+        let clk = kernel_clk(clocks);
+        let (tima, timb, timc, timd, time) = timers;
+
+        Parts {
+            master: HrTimerBuilder::new(master, clk),
+            tima: HrTimerBuilder::new(tima, clk),
+            timb: HrTimerBuilder::new(timb, clk),
+            timc: HrTimerBuilder::new(timc, clk),
+            timd: HrTimerBuilder::new(timd, clk),
+            time: HrTimerBuilder::new(time, clk),
+            control: HrtimControl {
+                common: self,
+                clk,
+                outputs: 0,
+            },
+        }
+    }
+}
+
+impl<TIM> HrTimerBuilder<TIM> {
+    fn new(tim: TIM, clk: Hertz) -> Self {
+        HrTimerBuilder {
+            tim,
+            clk,
+            prescaler: 0,
+            period: PERIOD_MAX as u16,
+            compare: [0; 4],
+            repetition: 0,
+            continuous: true,
+            preload: false,
+            reset: CounterReset::NONE,
+            outputs: [OutputConfig::DISABLED; 2],
+            deadtime: None,
+            faults: 0,
+        }
+    }
+
+    /// Set the frequency of the counter; will overwrite the previous
+    /// prescaler and period. The requested frequency will be rounded to
+    /// the nearest achievable frequency
+    ///
+    /// Panics if the frequency cannot be generated from the HRTIM clock
+    pub fn frequency<T: Into<Hertz>>(mut self, freq: T) -> Self {
+        let (prescaler, period) = calculate_period(self.clk.0, freq.into().0)
+            .expect("HRTIM frequency out of range");
+
+        self.prescaler = prescaler;
+        self.period = period;
+        self
+    }
+
+    /// Set the prescaler; the counter runs at fHRTIM / 2^prescaler.
+    /// Maximum 7
+    pub fn prescaler(mut self, prescaler: u8) -> Self {
+        assert!(prescaler <= 7);
+        self.prescaler = prescaler;
+        self
+    }
+
+    /// Set the period; the counter runs from 0 to period. Minimum 3,
+    /// maximum 0xFFFD
+    pub fn period(mut self, period: u16) -> Self {
+        assert!((PERIOD_MIN..=PERIOD_MAX).contains(&u32::from(period)));
+        self.period = period;
+        self
+    }
+
+    /// Set the initial value of a compare register
+    pub fn compare(mut self, compare: Compare, value: u16) -> Self {
+        self.compare[compare as usize] = value;
+        self
+    }
+
+    /// Set the repetition counter. The repetition event occurs every
+    /// `repetition + 1` periods
+    pub fn repetition(mut self, repetition: u8) -> Self {
+        self.repetition = repetition;
+        self
+    }
+
+    /// The counter stops when it reaches the period, until it is reset
+    /// again
+    pub fn single_shot(mut self) -> Self {
+        self.continuous = false;
+        self
+    }
+
+    /// Enable preload for the period, compare and repetition
+    /// registers. New values are transferred on the repetition event
+    pub fn preload(mut self) -> Self {
+        self.preload = true;
+        self
+    }
+}
+
+/// Register access for the master timer and timing units
+macro_rules! hrtim_timer {
+    ($($TIMX:ident: ($cr:ident, $isr:ident, $icr:ident, $dier:ident,
+                     $cnt:ident, $per:ident, $rep:ident,
+                     $cmp1:ident, $cmp2:ident, $cmp3:ident, $cmp4:ident,
+                     $repu:expr),)+) => {
+        $(
+            impl HrTimerBuilder<$TIMX> {
+                /// Write the common configuration registers
+                fn write_common(&self) {
+                    let tim = &self.tim;
+
+                    tim.$per.write(|w| unsafe { w.bits(u32::from(self.period)) });
+                    tim.$rep.write(|w| unsafe { w.bits(u32::from(self.repetition)) });
+                    tim.$cmp1.write(|w| unsafe { w.bits(u32::from(self.compare[0])) });
+                    tim.$cmp2.write(|w| unsafe { w.bits(u32::from(self.compare[1])) });
+                    tim.$cmp3.write(|w| unsafe { w.bits(u32::from(self.compare[2])) });
+                    tim.$cmp4.write(|w| unsafe { w.bits(u32::from(self.compare[3])) });
+
+                    let cont = if self.continuous { CR_CONT } else { 0 };
+                    let preen = if self.preload { CR_PREEN | $repu } else { 0 };
+                    tim.$cr.modify(|r, w| unsafe {
+                        w.bits(
+                            (r.bits() & !(0b111 | CR_CONT | CR_PREEN | $repu))
+                                | u32::from(self.prescaler)
+                                | cont
+                                | preen,
+                        )
+                    });
+                }
+            }
+
+            impl HrTimer<$TIMX> {
+                /// Returns the current value of the counter
+                pub fn counter(&self) -> u16 {
+                    self.tim.$cnt.read().bits() as u16
+                }
+
+                /// Returns the period
+                pub fn get_period(&self) -> u16 {
+                    self.tim.$per.read().bits() as u16
+                }
+
+                /// Set the period. Minimum 3, maximum 0xFFFD
+                pub fn set_period(&mut self, period: u16) {
+                    assert!((PERIOD_MIN..=PERIOD_MAX).contains(&u32::from(period)));
+                    self.tim.$per.write(|w| unsafe { w.bits(u32::from(period)) });
+                }
+
+                /// Returns the value of a compare register
+                pub fn get_compare(&self, compare: Compare) -> u16 {
+                    let bits = match compare {
+                        Compare::Compare1 => self.tim.$cmp1.read().bits(),
+                        Compare::Compare2 => self.tim.$cmp2.read().bits(),
+                        Compare::Compare3 => self.tim.$cmp3.read().bits(),
+                        Compare::Compare4 => self.tim.$cmp4.read().bits(),
+                    };
+                    bits as u16
+                }
+
+                /// Set the value of a compare register
+                pub fn set_compare(&mut self, compare: Compare, value: u16) {
+                    let bits = u32::from(value);
+                    match compare {
+                        Compare::Compare1 => self.tim.$cmp1.write(|w| unsafe { w.bits(bits) }),
+                        Compare::Compare2 => self.tim.$cmp2.write(|w| unsafe { w.bits(bits) }),
+                        Compare::Compare3 => self.tim.$cmp3.write(|w| unsafe { w.bits(bits) }),
+                        Compare::Compare4 => self.tim.$cmp4.write(|w| unsafe { w.bits(bits) }),
+                    }
+                }
+
+                /// Start listening for `event`
+                pub fn listen(&mut self, event: Event) {
+                    self.tim.$dier.modify(|r, w| unsafe { w.bits(r.bits() | event.bit()) });
+                }
+
+                /// Stop listening for `event`
+                pub fn unlisten(&mut self, event: Event) {
+                    self.tim.$dier.modify(|r, w| unsafe { w.bits(r.bits() & !event.bit()) });
+                }
+
+                /// Returns `true` if `event` is pending
+                pub fn is_pending(&self, event: Event) -> bool {
+                    self.tim.$isr.read().bits() & event.bit() != 0
+                }
+
+                /// Clears the interrupt flag for `event`
+                pub fn clear_irq(&mut self, event: Event) {
+                    self.tim.$icr.write(|w| unsafe { w.bits(event.bit()) });
+                }
+
+                /// Releases the timer
+                pub fn free(self) -> $TIMX {
+                    self.tim
+                }
+            }
+        )+
+    };
+}
+
+hrtim_timer! {
+    HRTIM_MASTER: (mcr, misr, micr, mdier, mcntr, mper, mrep,
+                   mcmp1r, mcmp2r, mcmp3r, mcmp4r, MCR_MREPU),
+    HRTIM_TIMA: (timacr, timaisr, timaicr, timadier, cntar, perar, repar,
+                 cmp1ar, cmp2ar, cmp3ar, cmp4ar, TIMCR_TREPU),
+    HRTIM_TIMB: (timbcr, timbisr, timbicr, timbdier, cntbr, perbr, repbr,
+                 cmp1br, cmp2br, cmp3br, cmp4br, TIMCR_TREPU),
+    HRTIM_TIMC: (timccr, timcisr, timcicr, timcdier, cntcr, percr, repcr,
+                 cmp1cr, cmp2cr, cmp3cr, cmp4cr, TIMCR_TREPU),
+    HRTIM_TIMD: (timdcr, timdisr, timdicr, timddier, cntdr, perdr, repdr,
+                 cmp1dr, cmp2dr, cmp3dr, cmp4dr, TIMCR_TREPU),
+    HRTIM_TIME: (timecr, timeisr, timeicr, timedier, cnter, perer, reper,
+                 cmp1er, cmp2er, cmp3er, cmp4er, TIMCR_TREPU),
+}
+
+impl HrTimerBuilder<HRTIM_MASTER> {
+    /// Configure the master timer
+    pub fn finalize(self) -> HrTimer<HRTIM_MASTER> {
+        self.write_common();
+
+        HrTimer {
+            tim: self.tim,
+            outputs: 0,
+        }
+    }
+}
+
+/// Configuration specific to timing units A-E
+macro_rules! hrtim_timing_unit {
+    ($($TIMX:ident: ($index:expr, $rst:ident, $set1:ident, $rst1:ident,
+                     $set2:ident, $rst2:ident, $dt:ident, $out:ident,
+                     $flt:ident),)+) => {
+        $(
+            impl HrTimerBuilder<$TIMX> {
+                /// Configure output 1. The output goes to its active state on
+                /// any of the `set` events, and to its inactive state on any
+                /// of the `reset` events
+                pub fn output1<P: Pins<$TIMX, Output1>>(
+                    mut self,
+                    _pin: P,
+                    set: Events,
+                    reset: Events,
+                ) -> Self {
+                    self.outputs[0].enabled = true;
+                    self.outputs[0].set = set;
+                    self.outputs[0].reset = reset;
+                    self
+                }
+
+                /// Configure output 2. The output goes to its active state on
+                /// any of the `set` events, and to its inactive state on any
+                /// of the `reset` events. If dead time is enabled, `set` and
+                /// `reset` are ignored
+                pub fn output2<P: Pins<$TIMX, Output2>>(
+                    mut self,
+                    _pin: P,
+                    set: Events,
+                    reset: Events,
+                ) -> Self {
+                    self.outputs[1].enabled = true;
+                    self.outputs[1].set = set;
+                    self.outputs[1].reset = reset;
+                    self
+                }
+
+                /// Set the polarity of output 1
+                pub fn output1_polarity(mut self, polarity: Polarity) -> Self {
+                    self.outputs[0].polarity = polarity;
+                    self
+                }
+
+                /// Set the polarity of output 2
+                pub fn output2_polarity(mut self, polarity: Polarity) -> Self {
+                    self.outputs[1].polarity = polarity;
+                    self
+                }
+
+                /// Output 1 takes part in burst mode, and goes to `level`
+                /// during the idle period
+                pub fn output1_idle_in_burst(mut self, level: Polarity) -> Self {
+                    self.outputs[0].idle_in_burst = Some(level);
+                    self
+                }
+
+                /// Output 2 takes part in burst mode, and goes to `level`
+                /// during the idle period
+                pub fn output2_idle_in_burst(mut self, level: Polarity) -> Self {
+                    self.outputs[1].idle_in_burst = Some(level);
+                    self
+                }
+
+                /// Set the dead time inserted before the rising edges of output
+                /// 1 and output 2. Output 2 becomes the complement of output 1
+                ///
+                /// Panics at `finalize` if the dead times are too long for the
+                /// HRTIM clock
+                pub fn deadtime<T: Into<NanoSeconds>>(mut self, rising: T, falling: T) -> Self {
+                    self.deadtime = Some((rising.into(), falling.into()));
+                    self
+                }
+
+                /// Enable a fault input for this timing unit. The outputs go
+                /// to `state` when the fault is active
+                ///
+                /// The fault input itself must be configured using
+                /// [HrtimControl::fault_input](HrtimControl::fault_input)
+                pub fn fault(mut self, input: FaultInput, state: FaultState) -> Self {
+                    self.faults |= 1 << (input as u32);
+                    self.outputs[0].fault = state;
+                    self.outputs[1].fault = state;
+                    self
+                }
+
+                /// Set the events that reset the counter
+                pub fn reset_on(mut self, reset: CounterReset) -> Self {
+                    self.reset = reset;
+                    self
+                }
+
+                /// Configure the timing unit
+                pub fn finalize(self) -> HrTimer<$TIMX> {
+                    self.write_common();
+
+                    let tim = &self.tim;
+
+                    tim.$rst.write(|w| unsafe { w.bits(self.reset.bits()) });
+                    tim.$set1.write(|w| unsafe { w.bits(self.outputs[0].set.bits()) });
+                    tim.$rst1.write(|w| unsafe { w.bits(self.outputs[0].reset.bits()) });
+                    tim.$set2.write(|w| unsafe { w.bits(self.outputs[1].set.bits()) });
+                    tim.$rst2.write(|w| unsafe { w.bits(self.outputs[1].reset.bits()) });
+
+                    let mut outr = self.outputs[0].outr() | (self.outputs[1].outr() << 16);
+
+                    if let Some((rising, falling)) = self.deadtime {
+                        let (dtprsc, dtr, dtf) = calculate_deadtime(self.clk.0, rising, falling)
+                            .expect("HRTIM dead time out of range");
+
+                        tim.$dt.write(|w| unsafe {
+                            w.bits(u32::from(dtr)
+                                   | (u32::from(dtprsc) << DTR_DTPRSC_SHIFT)
+                                   | (u32::from(dtf) << DTR_DTF_SHIFT))
+                        });
+                        outr |= OUTR_DTEN;
+                    }
+                    tim.$out.write(|w| unsafe { w.bits(outr) });
+
+                    // Fault inputs
+                    tim.$flt.write(|w| unsafe { w.bits(self.faults) });
+
+                    // Bits in the OENR, ODISR and ODSR registers
+                    let outputs = self.outputs
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, output)| output.enabled)
+                        .fold(0, |acc, (i, _)| acc | (1 << (2 * $index + i)));
+
+                    HrTimer {
+                        tim: self.tim,
+                        outputs,
+                    }
+                }
+            }
+
+            impl HrTimer<$TIMX> {
+                /// Enable the configured outputs
+                pub fn enable_outputs(&mut self) {
+                    // unsafe: write only register, bits for this timer only
+                    let common = unsafe { &*HRTIM_COMMON::ptr() };
+                    common.oenr.write(|w| unsafe { w.bits(self.outputs) });
+                }
+
+                /// Disable the configured outputs. The outputs go to their
+                /// idle state
+                pub fn disable_outputs(&mut self) {
+                    // unsafe: write only register, bits for this timer only
+                    let common = unsafe { &*HRTIM_COMMON::ptr() };
+                    common.odisr.write(|w| unsafe { w.bits(self.outputs) });
+                }
+
+                /// Returns `true` if all the configured outputs are enabled.
+                /// Outputs are disabled when a fault occurs
+                pub fn outputs_enabled(&self) -> bool {
+                    // unsafe: read only
+                    let common = unsafe { &*HRTIM_COMMON::ptr() };
+                    common.oenr.read().bits() & self.outputs == self.outputs
+                }
+            }
+        )+
+    };
+}
+
+hrtim_timing_unit! {
+    HRTIM_TIMA: (0, rstar, seta1r, rsta1r, seta2r, rsta2r, dtar, outar, fltar),
+    HRTIM_TIMB: (1, rstbr, setb1r, rstb1r, setb2r, rstb2r, dtbr, outbr, fltbr),
+    HRTIM_TIMC: (2, rstcr, setc1r, rstc1r, setc2r, rstc2r, dtcr, outcr, fltcr),
+    HRTIM_TIMD: (3, rstdr, setd1r, rstd1r, setd2r, rstd2r, dtdr, outdr, fltdr),
+    HRTIM_TIME: (4, rster, sete1r, rste1r, sete2r, rste2r, dter, outer, flter),
+}
+
+impl HrtimControl {
+    /// Returns the HRTIM clock frequency
+    pub fn clk(&self) -> Hertz {
+        self.clk
+    }
+
+    /// Start the counters of `timers` simultaneously
+    pub fn start(&mut self, timers: Timers) {
+        // unsafe: the counter enable bits are only modified here
+        let master = unsafe { &*HRTIM_MASTER::ptr() };
+        master.mcr.modify(|r, w| unsafe {
+            w.bits(r.bits() | (timers.bits() * MCR_MCEN))
+        });
+    }
+
+    /// Stop the counters of `timers` simultaneously
+    pub fn stop(&mut self, timers: Timers) {
+        // unsafe: the counter enable bits are only modified here
+        let master = unsafe { &*HRTIM_MASTER::ptr() };
+        master.mcr.modify(|r, w| unsafe {
+            w.bits(r.bits() & !(timers.bits() * MCR_MCEN))
+        });
+    }
+
+    /// Transfer the preload registers of `timers` to their active
+    /// registers simultaneously
+    pub fn software_update(&mut self, timers: Timers) {
+        self.common.cr2.write(|w| unsafe { w.bits(timers.bits()) });
+    }
+
+    /// Reset the counters of `timers` simultaneously
+    pub fn software_reset(&mut self, timers: Timers) {
+        self.common
+            .cr2
+            .write(|w| unsafe { w.bits(timers.bits() << 8) });
+    }
+
+    /// Configure a fault input on a GPIO pin
+    ///
+    /// `filter` is the number of consecutive samples required to validate
+    /// the fault, as defined by the FLTxF field. Maximum 15
+    pub fn fault_input<P: FaultPins>(
+        &mut self,
+        _pin: P,
+        polarity: Polarity,
+        filter: u8,
+    ) {
+        assert!(filter <= 15);
+
+        let flt = P::INPUT as u32;
+        let pol = match polarity {
+            Polarity::ActiveLow => 0,
+            Polarity::ActiveHigh => 1 << 1,
+        };
+        // FLTxE | FLTxP | FLTxSRC = 0 (pin) | FLTxF
+        let bits = 1 | pol | (u32::from(filter) << 3);
+
+        match flt {
+            0..=3 => {
+                let shift = 8 * flt;
+                self.common.fltinr1.modify(|r, w| unsafe {
+                    w.bits((r.bits() & !(0xFF << shift)) | (bits << shift))
+                });
+            }
+            _ => {
+                self.common.fltinr2.modify(|r, w| unsafe {
+                    w.bits((r.bits() & !0xFF) | bits)
+                });
+            }
+        }
+    }
+
+    /// Returns `true` if `input` has caused a fault since it was last
+    /// cleared
+    pub fn is_fault_pending(&self, input: FaultInput) -> bool {
+        self.common.isr.read().bits() & (1 << input as u32) != 0
+    }
+
+    /// Clear the fault flag for `input`. Outputs must be enabled again
+    /// after a fault
+    pub fn clear_fault_pending(&mut self, input: FaultInput) {
+        self.common
+            .icr
+            .write(|w| unsafe { w.bits(1 << input as u32) });
+    }
+
+    /// Configure burst mode. Burst mode is enabled, and bursts start on
+    /// the trigger events
+    pub fn burst_mode(&mut self, burst: BurstMode) {
+        assert!(burst.prescaler <= 15);
+        assert!(burst.idle <= burst.period);
+
+        // Disable burst mode before changing its configuration
+        self.common
+            .bmcr
+            .modify(|r, w| unsafe { w.bits(r.bits() & !BMCR_BME) });
+
+        self.common
+            .bmper
+            .write(|w| unsafe { w.bits(u32::from(burst.period)) });
+        self.common
+            .bmcmpr
+            .write(|w| unsafe { w.bits(u32::from(burst.idle)) });
+        self.common
+            .bmtrgr
+            .write(|w| unsafe { w.bits(burst.trigger.bits()) });
+
+        let bmom = if burst.continuous { BMCR_BMOM } else { 0 };
+        self.common.bmcr.write(|w| unsafe {
+            w.bits(
+                BMCR_BME
+                    | bmom
+                    | BMCR_BMPREN
+                    | ((burst.clock as u32) << BMCR_BMCLK_SHIFT)
+                    | (u32::from(burst.prescaler) << BMCR_BMPRSC_SHIFT),
+            )
+        });
+    }
+
+    /// Trigger a burst by software
+    pub fn burst_start(&mut self) {
+        self.common.bmtrgr.modify(|r, w| unsafe {
+            w.bits(r.bits() | BurstTrigger::SOFTWARE.bits())
+        });
+    }
+
+    /// Stop a burst that is in progress. The outputs resume after the
+    /// current idle period
+    pub fn burst_stop(&mut self) {
+        self.common
+            .bmcr
+            .modify(|r, w| unsafe { w.bits(r.bits() & !BMCR_BMSTAT) });
+    }
+
+    /// Returns `true` if a burst is in progress
+    pub fn is_burst_active(&self) -> bool {
+        self.common.bmcr.read().bits() & BMCR_BMSTAT != 0
+    }
+
+    /// Disable burst mode
+    pub fn disable_burst_mode(&mut self) {
+        self.common
+            .bmcr
+            .modify(|r, w| unsafe { w.bits(r.bits() & !BMCR_BME) });
+    }
+
+    /// Set the events that generate an ADC trigger. The ADC must select
+    /// the corresponding HRTIM ADC trigger as its external trigger
+    pub fn adc_trigger(&mut self, trigger: AdcTrigger) {
+        match trigger {
+            AdcTrigger::Trigger1(events) => self
+                .common
+                .adc1r
+                .write(|w| unsafe { w.bits(events.bits()) }),
+            AdcTrigger::Trigger2(events) => self
+                .common
+                .adc2r
+                .write(|w| unsafe { w.bits(events.bits()) }),
+            AdcTrigger::Trigger3(events) => self
+                .common
+                .adc3r
+                .write(|w| unsafe { w.bits(events.bits()) }),
+            AdcTrigger::Trigger4(events) => self
+                .common
+                .adc4r
+                .write(|w| unsafe { w.bits(events.bits()) }),
+        }
+    }
+
+    /// Register the outputs of `timer` with this control, so that
+    /// [FaultMonitor](FaultMonitor) can enable and disable them
+    pub fn monitor<TIM>(&mut self, timer: &HrTimer<TIM>) {
+        self.outputs |= timer.outputs;
+    }
+
+    /// Releases the HRTIM_COMMON peripheral
+    pub fn free(self) -> HRTIM_COMMON {
+        self.common
+    }
+}
+
+impl FaultMonitor for HrtimControl {
+    /// Returns true if any monitored output has been disabled by a fault
+    fn is_fault_active(&self) -> bool {
+        self.common.oenr.read().bits() & self.outputs != self.outputs
+    }
+
+    /// Clears all fault flags and enables the monitored outputs again
+    fn clear_fault(&mut self) {
+        self.common.icr.write(|w| unsafe { w.bits(0x3F) });
+        self.common.oenr.write(|w| unsafe { w.bits(self.outputs) });
+    }
+
+    /// Disables the monitored outputs
+    fn set_fault(&mut self) {
+        self.common.odisr.write(|w| unsafe { w.bits(self.outputs) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn period() {
+        // 400MHz HRTIM clock
+        assert_eq!(calculate_period(400_000_000, 100_000), Some((0, 4000)));
+        assert_eq!(calculate_period(400_000_000, 10_000), Some((0, 40000)));
+        assert_eq!(calculate_period(400_000_000, 1_000), Some((3, 50000)));
+        assert_eq!(calculate_period(400_000_000, 100_000_000), Some((0, 4)));
+        assert_eq!(calculate_period(400_000_000, 200_000_000), None);
+        assert_eq!(calculate_period(400_000_000, 10), None);
+        assert_eq!(calculate_period(400_000_000, 0), None);
+    }
+
+    #[test]
+    fn deadtime() {
+        // 400MHz HRTIM clock, 2.5ns per tick
+        assert_eq!(
+            calculate_deadtime(400_000_000, NanoSeconds(100), NanoSeconds(50)),
+            Some((0, 40, 20))
+        );
+        assert_eq!(
+            calculate_deadtime(400_000_000, NanoSeconds(101), NanoSeconds(0)),
+            Some((0, 41, 0))
+        );
+        assert_eq!(
+            calculate_deadtime(
+                400_000_000,
+                NanoSeconds(2_000),
+                NanoSeconds(50)
+            ),
+            Some((1, 400, 10))
+        );
+        assert_eq!(
+            calculate_deadtime(
+                400_000_000,
+                NanoSeconds(200_000),
+                NanoSeconds(0)
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,509 @@
+//! Interrupt driven I2C transactions
+//!
+//! An [I2cEngine](I2cEngine) performs transactions without blocking. It is
+//! driven by calling [on_interrupt](I2cEngine::on_interrupt) from both the
+//! I2Cx_EV and I2Cx_ER interrupt handlers. Buffers must be `'static`, as
+//! they are held by the engine until the transaction completes.
+//!
+//! ```
+//! let i2c = dp.I2C1.i2c((scl, sda), 100.khz(), ccdr.peripheral.I2C1, &ccdr.clocks);
+//! let mut engine = i2c.engine();
+//!
+//! engine.write_read(0x76, COMMAND, BUFFER).unwrap();
+//!
+//! // Later, after some interrupts
+//! if let Some(completion) = engine.completed() {
+//!     ...
+//! }
+//! ```
+//!
+//! Completion is reported either by polling with
+//! [completed](I2cEngine::completed), or by a callback set with
+//! [set_callback](I2cEngine::set_callback), which is called from the
+//! interrupt handler.
+//!
+//! # Segments
+//!
+//! A transaction is made of one or more [Operations](Operation). Adjacent
+//! operations in the same direction are sent as one message, without a
+//! repeated START between them. Each change of direction is preceded by a
+//! repeated START, and the transaction ends with a STOP.
+
+use super::{Error, I2c};
+use crate::stm32::{I2C1, I2C2, I2C3, I2C4};
+
+/// One part of a transaction
+#[derive(Debug)]
+pub enum Operation {
+    /// Read into the buffer
+    Read(&'static mut [u8]),
+    /// Write the buffer
+    Write(&'static [u8]),
+}
+
+/// An I2C transaction, with the buffers it uses
+#[derive(Debug)]
+pub enum Transaction {
+    /// Write the buffer
+    Write(&'static [u8]),
+    /// Read into the buffer
+    Read(&'static mut [u8]),
+    /// Write the first buffer, then read into the second buffer after a
+    /// repeated START
+    WriteRead(&'static [u8], &'static mut [u8]),
+    /// Perform each operation in turn
+    Operations(&'static mut [Operation]),
+}
+
+/// A view of one operation of a transaction
+enum Segment<'a> {
+    Read(&'a mut [u8]),
+    Write(&'a [u8]),
+}
+
+impl Transaction {
+    /// Number of operations
+    fn len(&self) -> usize {
+        match self {
+            Transaction::Write(_) | Transaction::Read(_) => 1,
+            Transaction::WriteRead(..) => 2,
+            Transaction::Operations(operations) => operations.len(),
+        }
+    }
+
+    /// Returns operation `index`, or `None` if out of range
+    fn segment(&mut self, index: usize) -> Option<Segment<'_>> {
+        match (self, index) {
+            (Transaction::Write(bytes), 0) => Some(Segment::Write(bytes)),
+            (Transaction::Read(buffer), 0) => Some(Segment::Read(buffer)),
+            (Transaction::WriteRead(bytes, _), 0) => {
+                Some(Segment::Write(bytes))
+            }
+            (Transaction::WriteRead(_, buffer), 1) => {
+                Some(Segment::Read(buffer))
+            }
+            (Transaction::Operations(operations), i) => {
+                operations.get_mut(i).map(|operation| match operation {
+                    Operation::Read(buffer) => Segment::Read(buffer),
+                    Operation::Write(bytes) => Segment::Write(bytes),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the direction and length of operation `index`
+    fn info(&mut self, index: usize) -> Option<(bool, usize)> {
+        self.segment(index).map(|segment| match segment {
+            Segment::Read(buffer) => (true, buffer.len()),
+            Segment::Write(bytes) => (false, bytes.len()),
+        })
+    }
+}
+
+/// A completed transaction
+#[derive(Debug)]
+pub struct Completion {
+    /// Address of the target
+    pub address: u8,
+    /// The transaction, with its buffers
+    pub transaction: Transaction,
+    /// Result of the transaction. On error, read buffers may be partly
+    /// filled
+    pub result: Result<(), Error>,
+}
+
+/// Transaction in progress
+#[derive(Debug)]
+struct State {
+    address: u8,
+    transaction: Transaction,
+    /// Operation and byte position of the next byte
+    operation: usize,
+    position: usize,
+    /// First operation after the current message
+    message_end: usize,
+    /// Error to report once the STOP condition has been sent
+    error: Option<Error>,
+}
+
+/// Interrupt driven I2C controller
+#[derive(Debug)]
+pub struct I2cEngine<I2C> {
+    i2c: I2c<I2C>,
+    state: Option<State>,
+    completion: Option<Completion>,
+    callback: Option<fn(Completion)>,
+}
+
+macro_rules! i2c_engine {
+    ($($I2CX:ident,)+) => {
+        $(
+            impl I2c<$I2CX> {
+                /// Converts the I2C controller into an interrupt driven
+                /// transaction engine
+                pub fn engine(self) -> I2cEngine<$I2CX> {
+                    I2cEngine {
+                        i2c: self,
+                        state: None,
+                        completion: None,
+                        callback: None,
+                    }
+                }
+            }
+
+            impl I2cEngine<$I2CX> {
+                /// Starts `transaction` with the target at `address`
+                ///
+                /// Returns the transaction if it has no operations, if
+                /// another transaction is in progress, or if the completion
+                /// of the previous transaction has not been collected.
+                pub fn start(&mut self, address: u8, transaction: Transaction) -> Result<(), Transaction> {
+                    if transaction.len() == 0
+                        || self.state.is_some()
+                        || self.completion.is_some()
+                    {
+                        return Err(transaction);
+                    }
+
+                    self.state = Some(State {
+                        address,
+                        transaction,
+                        operation: 0,
+                        position: 0,
+                        message_end: 0,
+                        error: None,
+                    });
+
+                    // Clear flags left by a previous transaction
+                    self.i2c.i2c.icr.write(|w| {
+                        w.stopcf()
+                            .set_bit()
+                            .nackcf()
+                            .set_bit()
+                            .berrcf()
+                            .set_bit()
+                            .arlocf()
+                            .set_bit()
+                            .ovrcf()
+                            .set_bit()
+                            .timoutcf()
+                            .set_bit()
+                            .peccf()
+                            .set_bit()
+                    });
+
+                    self.i2c.i2c.cr1.modify(|_, w| {
+                        w.txie()
+                            .set_bit()
+                            .rxie()
+                            .set_bit()
+                            .tcie()
+                            .set_bit()
+                            .stopie()
+                            .set_bit()
+                            .nackie()
+                            .set_bit()
+                            .errie()
+                            .set_bit()
+                    });
+
+                    self.start_message(0);
+                    Ok(())
+                }
+
+                /// Starts writing `bytes` to the target at `address`
+                pub fn write(&mut self, address: u8, bytes: &'static [u8]) -> Result<(), Transaction> {
+                    self.start(address, Transaction::Write(bytes))
+                }
+
+                /// Starts reading into `buffer` from the target at
+                /// `address`
+                pub fn read(&mut self, address: u8, buffer: &'static mut [u8]) -> Result<(), Transaction> {
+                    self.start(address, Transaction::Read(buffer))
+                }
+
+                /// Starts writing `bytes` to the target at `address`, then
+                /// reading into `buffer` after a repeated START
+                pub fn write_read(
+                    &mut self,
+                    address: u8,
+                    bytes: &'static [u8],
+                    buffer: &'static mut [u8],
+                ) -> Result<(), Transaction> {
+                    self.start(address, Transaction::WriteRead(bytes, buffer))
+                }
+
+                /// Starts a transaction of `operations` with the target at
+                /// `address`
+                pub fn transaction(
+                    &mut self,
+                    address: u8,
+                    operations: &'static mut [Operation],
+                ) -> Result<(), Transaction> {
+                    self.start(address, Transaction::Operations(operations))
+                }
+
+                /// Returns `true` if a transaction is in progress
+                pub fn is_busy(&self) -> bool {
+                    self.state.is_some()
+                }
+
+                /// Returns the completed transaction, if any. Completions
+                /// are only stored if no callback is set
+                pub fn completed(&mut self) -> Option<Completion> {
+                    self.completion.take()
+                }
+
+                /// Sets a callback that receives each completed transaction.
+                /// The callback is called from
+                /// [on_interrupt](I2cEngine::on_interrupt)
+                pub fn set_callback(&mut self, callback: Option<fn(Completion)>) {
+                    self.callback = callback;
+                }
+
+                /// Handles the I2C interrupts. Call this from both the
+                /// I2Cx_EV and I2Cx_ER interrupt handlers
+                ///
+                /// Bus errors end the transaction with an error. If the SMBus
+                /// timeouts or PEC are still configured, these are reported
+                /// as [Error::Timeout](Error::Timeout) and
+                /// [Error::Pec](Error::Pec)
+                pub fn on_interrupt(&mut self) {
+                    let isr = self.i2c.i2c.isr.read();
+
+                    // SMBALERT# is not part of a transaction. It can only be
+                    // enabled by SmBus, which reports it with is_alert
+                    if isr.alert().bit_is_set() {
+                        self.i2c.i2c.icr.write(|w| w.alertcf().set_bit());
+                    }
+
+                    if self.state.is_none() {
+                        // Nothing in progress, for example an error on the
+                        // bus
+                        self.i2c.i2c.icr.write(|w| {
+                            w.stopcf()
+                                .set_bit()
+                                .berrcf()
+                                .set_bit()
+                                .arlocf()
+                                .set_bit()
+                                .ovrcf()
+                                .set_bit()
+                                .timoutcf()
+                                .set_bit()
+                                .peccf()
+                                .set_bit()
+                        });
+                        return;
+                    }
+
+                    // Errors
+                    if isr.berr().is_error() {
+                        self.i2c.i2c.icr.write(|w| w.berrcf().set_bit());
+                        return self.finish(Err(Error::Bus));
+                    }
+                    if isr.arlo().is_lost() {
+                        self.i2c.i2c.icr.write(|w| w.arlocf().set_bit());
+                        return self.finish(Err(Error::Arbitration));
+                    }
+                    if isr.ovr().bit_is_set() {
+                        self.i2c.i2c.icr.write(|w| w.ovrcf().set_bit());
+                        return self.finish(Err(Error::Overrun));
+                    }
+                    if isr.timeout().bit_is_set() {
+                        self.i2c.i2c.icr.write(|w| w.timoutcf().set_bit());
+                        return self.finish(Err(Error::Timeout));
+                    }
+                    if isr.pecerr().bit_is_set() {
+                        self.i2c.i2c.icr.write(|w| w.peccf().set_bit());
+                        return self.finish(Err(Error::Pec));
+                    }
+                    if isr.nackf().bit_is_set() {
+                        // A STOP is sent automatically after a NACK. The
+                        // transaction ends with that STOP, so that the next
+                        // transaction cannot start before it
+                        self.i2c.i2c.icr.write(|w| w.nackcf().set_bit());
+                        flush_txdr!(self.i2c.i2c);
+                        self.i2c.i2c.cr1.modify(|_, w| {
+                            w.txie().clear_bit().rxie().clear_bit().tcie().clear_bit()
+                        });
+                        if let Some(state) = self.state.as_mut() {
+                            state.error = Some(Error::NotAcknowledge);
+                        }
+                    }
+
+                    let failed = self.state.as_ref().map_or(false, |s| s.error.is_some());
+                    if !failed {
+                        // Data
+                        if isr.rxne().is_not_empty() {
+                            let byte = self.i2c.i2c.rxdr.read().rxdata().bits();
+                            self.store_byte(byte);
+                        }
+                        if isr.txis().is_empty() {
+                            let byte = self.next_byte();
+                            self.i2c.i2c.txdr.write(|w| w.txdata().bits(byte));
+                        }
+
+                        // Next part of a long message
+                        if isr.tcr().bit_is_set() {
+                            self.i2c.master_reload();
+                        }
+
+                        // Next message, after a repeated START
+                        if isr.tc().is_complete() {
+                            let next = self.state.as_ref().map_or(0, |s| s.message_end);
+                            self.start_message(next);
+                        }
+                    }
+
+                    // End of the transaction
+                    if isr.stopf().bit_is_set() {
+                        self.i2c.i2c.icr.write(|w| w.stopcf().set_bit());
+                        let error = self.state.as_mut().and_then(|s| s.error.take());
+                        self.finish(error.map_or(Ok(()), Err));
+                    }
+                }
+
+                /// Disables the interrupts and returns the I2C controller.
+                /// Any transaction in progress is abandoned
+                pub fn free(mut self) -> I2c<$I2CX> {
+                    self.disable_interrupts();
+                    self.i2c
+                }
+
+                /// Starts the message beginning at `operation`. A message is
+                /// a run of operations in the same direction. It ends with
+                /// a STOP if it is the last message
+                fn start_message(&mut self, operation: usize) {
+                    let state = match self.state.as_mut() {
+                        Some(state) => state,
+                        None => return,
+                    };
+                    let (read, _) = match state.transaction.info(operation) {
+                        Some(info) => info,
+                        None => return,
+                    };
+
+                    // Find the end of the message
+                    let mut end = operation;
+                    let mut length = 0;
+                    while let Some((r, len)) = state.transaction.info(end) {
+                        if r != read {
+                            break;
+                        }
+                        length += len;
+                        end += 1;
+                    }
+                    let last = end == state.transaction.len();
+
+                    state.operation = operation;
+                    state.position = 0;
+                    state.message_end = end;
+                    let address = state.address;
+
+                    let nbytes = self.i2c.first_part(length);
+                    self.i2c.i2c.cr2.write(|w| {
+                        w.sadd()
+                            .bits(u16::from(address) << 1)
+                            .add10()
+                            .clear_bit()
+                            .rd_wrn()
+                            .bit(read)
+                            .nbytes()
+                            .bits(nbytes)
+                            .reload()
+                            .bit(self.i2c.remaining > 0)
+                            .autoend()
+                            .bit(last)
+                            .start()
+                            .set_bit()
+                    });
+                }
+
+                /// Returns the next byte to write
+                fn next_byte(&mut self) -> u8 {
+                    let state = match self.state.as_mut() {
+                        Some(state) => state,
+                        None => return 0,
+                    };
+
+                    while let Some(Segment::Write(bytes)) =
+                        state.transaction.segment(state.operation)
+                    {
+                        if let Some(byte) = bytes.get(state.position) {
+                            state.position += 1;
+                            return *byte;
+                        }
+
+                        // Continue with the next operation of the message
+                        state.operation += 1;
+                        state.position = 0;
+                    }
+
+                    0
+                }
+
+                /// Stores a received byte
+                fn store_byte(&mut self, byte: u8) {
+                    let state = match self.state.as_mut() {
+                        Some(state) => state,
+                        None => return,
+                    };
+
+                    while let Some(Segment::Read(buffer)) =
+                        state.transaction.segment(state.operation)
+                    {
+                        if let Some(b) = buffer.get_mut(state.position) {
+                            *b = byte;
+                            state.position += 1;
+                            return;
+                        }
+
+                        // Continue with the next operation of the message
+                        state.operation += 1;
+                        state.position = 0;
+                    }
+                }
+
+                /// Ends the transaction in progress with `result`
+                fn finish(&mut self, result: Result<(), Error>) {
+                    self.disable_interrupts();
+                    self.i2c.remaining = 0;
+
+                    if let Some(state) = self.state.take() {
+                        let completion = Completion {
+                            address: state.address,
+                            transaction: state.transaction,
+                            result,
+                        };
+
+                        match self.callback {
+                            Some(callback) => callback(completion),
+                            None => self.completion = Some(completion),
+                        }
+                    }
+                }
+
+                fn disable_interrupts(&mut self) {
+                    self.i2c.i2c.cr1.modify(|_, w| {
+                        w.txie()
+                            .clear_bit()
+                            .rxie()
+                            .clear_bit()
+                            .tcie()
+                            .clear_bit()
+                            .stopie()
+                            .clear_bit()
+                            .nackie()
+                            .clear_bit()
+                            .errie()
+                            .clear_bit()
+                    });
+                }
+            }
+        )+
+    };
+}
+
+i2c_engine!(I2C1, I2C2, I2C3, I2C4,);
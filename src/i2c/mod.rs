@@ -1,4 +1,33 @@
 //! Inter Integrated Circuit (I2C)
+//!
+//! I2C controller (master) mode is provided by [I2c](I2c), and target
+//! (slave) mode by [I2cTarget](I2cTarget). The SMBus and PMBus host
+//! protocols are provided by [SmBus](SmBus), and interrupt driven
+//! transactions by [I2cEngine](I2cEngine).
+//!
+//! # Noise filters
+//!
+//! The analog and digital noise filters are set by a [Config](Config), and
+//! taken into account when calculating the bus timing.
+//!
+//! ```
+//! let config = i2c::Config::new(100.khz()).digital_filter(4);
+//! let i2c = dp.I2C1.i2c((scl, sda), config, ccdr.peripheral.I2C1, &ccdr.clocks);
+//! ```
+//!
+//! # Bus recovery
+//!
+//! A target can hold SDA low indefinitely if the controller is reset in
+//! the middle of a transfer. To recover the bus with
+//! [recover_bus](I2c::recover_bus), keep the pins and create the
+//! peripheral with `i2c_unchecked`.
+//!
+//! ```
+//! let pins = (scl.set_open_drain(), sda.set_open_drain());
+//! let mut i2c = dp.I2C1.i2c_unchecked(100.khz(), ccdr.peripheral.I2C1, &ccdr.clocks);
+//!
+//! let (pins, result) = i2c.recover_bus(pins, &mut delay);
+//! ```
 
 use core::cmp;
 use core::marker::PhantomData;
@@ -9,8 +38,10 @@ use crate::gpio::gpioc::PC9;
 use crate::gpio::gpiod::{PD12, PD13};
 use crate::gpio::gpiof::{PF0, PF1, PF14, PF15};
 use crate::gpio::gpioh::{PH11, PH12, PH4, PH5, PH7, PH8};
-use crate::gpio::{Alternate, AF4, AF6};
+use crate::gpio::{Alternate, OpenDrain, Output, AF4, AF6};
+use crate::hal::blocking::delay::DelayUs;
 use crate::hal::blocking::i2c::{Read, Write, WriteRead};
+use crate::hal::digital::v2::{InputPin, OutputPin};
 use crate::rcc::{rec, CoreClocks, ResetEnable};
 use crate::stm32::{I2C1, I2C2, I2C3, I2C4};
 use crate::time::Hertz;
@@ -25,7 +56,7 @@ pub enum Event {
     Transmit,
     /// (RXIE)
     Receive,
-    /// (TCIE)
+    /// Transfer complete, or transfer complete reload (TCIE)
     TransferComplete,
     /// Stop detection (STOPIE)
     Stop,
@@ -47,6 +78,58 @@ pub enum Stop {
     Automatic,
 }
 
+/// I2C controller configuration
+///
+/// The bus frequency, and the noise filters on the SCL and SDA inputs. Any
+/// frequency converts into a configuration with the analog filter enabled,
+/// and the digital filter disabled.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Config {
+    frequency: Hertz,
+    analog_filter: bool,
+    digital_filter: u8,
+}
+
+impl Config {
+    /// Create a configuration for the bus frequency `frequency`
+    pub fn new<F>(frequency: F) -> Self
+    where
+        F: Into<Hertz>,
+    {
+        Config {
+            frequency: frequency.into(),
+            analog_filter: true,
+            digital_filter: 0,
+        }
+    }
+
+    /// Enable or disable the analog noise filter, which suppresses spikes
+    /// shorter than 50ns
+    pub fn analog_filter(mut self, enable: bool) -> Self {
+        self.analog_filter = enable;
+        self
+    }
+
+    /// Set the digital noise filter to suppress spikes shorter than `length`
+    /// periods of the i2c_ker_ck. A `length` of 0 disables the digital
+    /// filter
+    ///
+    /// # Panics
+    ///
+    /// Panics if `length` is greater than 15
+    pub fn digital_filter(mut self, length: u8) -> Self {
+        assert!(length <= 15, "The digital filter is at most 15 cycles");
+        self.digital_filter = length;
+        self
+    }
+}
+
+impl<T: Into<Hertz>> From<T> for Config {
+    fn from(frequency: T) -> Config {
+        Config::new(frequency)
+    }
+}
+
 /// I2C error
 #[derive(Debug)]
 #[non_exhaustive]
@@ -57,10 +140,12 @@ pub enum Error {
     Arbitration,
     /// No ack received
     NotAcknowledge,
-    // Overrun, // slave mode only
-    // Pec, // SMBUS mode only
-    // Timeout, // SMBUS mode only
-    // Alert, // SMBUS mode only
+    /// Overrun or underrun, in target mode without clock stretching
+    Overrun,
+    /// PEC mismatch, in SMBus mode
+    Pec,
+    /// Bus timeout, in SMBus mode
+    Timeout,
 }
 
 /// A trait to represent the SCL Pin of an I2C Port
@@ -73,6 +158,19 @@ pub trait PinSda<I2C> {
     fn set_open_drain(self) -> Self;
 }
 
+/// A trait for I2C pins that can be used as open drain outputs, for bus
+/// recovery
+pub trait RecoveryPin: Sized {
+    /// The pin as an open drain output
+    type Gpio: OutputPin + InputPin;
+
+    /// Configures the pin as an open drain output
+    fn into_gpio(self) -> Self::Gpio;
+
+    /// Configures the pin back to its alternate function
+    fn from_gpio(gpio: Self::Gpio) -> Self;
+}
+
 /// A trait to represent the collection of pins required for an I2C port
 pub trait Pins<I2C> {
     fn set_open_drain(self) -> Self;
@@ -93,30 +191,80 @@ where
 #[derive(Debug)]
 pub struct I2c<I2C> {
     i2c: I2C,
+    /// Bytes of the current transfer that are not yet loaded into NBYTES
+    remaining: usize,
 }
 
+/// Maximum number of bytes that can be loaded into NBYTES at once
+const NBYTES_MAX: usize = 255;
+
 pub trait I2cExt<I2C>: Sized {
     type Rec: ResetEnable;
 
-    fn i2c<PINS, F>(
+    fn i2c<PINS, C>(
         self,
         _pins: PINS,
-        frequency: F,
+        config: C,
         prec: Self::Rec,
         clocks: &CoreClocks,
     ) -> I2c<I2C>
     where
         PINS: Pins<I2C>,
-        F: Into<Hertz>;
+        C: Into<Config>;
 
-    fn i2c_unchecked<F>(
+    fn i2c_unchecked<C>(
         self,
-        frequency: F,
+        config: C,
         prec: Self::Rec,
         clocks: &CoreClocks,
     ) -> I2c<I2C>
     where
-        F: Into<Hertz>;
+        C: Into<Config>;
+}
+
+/// Clocks SCL until SDA is released, up to nine times, then generates a
+/// STOP condition. Returns `true` if SDA is released
+///
+/// See UM10204 Rev 6 Section 3.1.16
+fn clock_out_bus<SCL, SDA, D>(
+    scl: &mut SCL,
+    sda: &mut SDA,
+    delay: &mut D,
+) -> bool
+where
+    SCL: OutputPin,
+    SDA: OutputPin + InputPin,
+    D: DelayUs<u8>,
+{
+    // Half period of a 100kHz SCL
+    const HALF_PERIOD_US: u8 = 5;
+
+    let _ = scl.set_high();
+    let _ = sda.set_high();
+    delay.delay_us(HALF_PERIOD_US);
+
+    for _ in 0..9 {
+        if sda.is_high().unwrap_or(false) {
+            break;
+        }
+
+        let _ = scl.set_low();
+        delay.delay_us(HALF_PERIOD_US);
+        let _ = scl.set_high();
+        delay.delay_us(HALF_PERIOD_US);
+    }
+
+    // STOP condition, SDA rising while SCL is high
+    let _ = scl.set_low();
+    delay.delay_us(HALF_PERIOD_US);
+    let _ = sda.set_low();
+    delay.delay_us(HALF_PERIOD_US);
+    let _ = scl.set_high();
+    delay.delay_us(HALF_PERIOD_US);
+    let _ = sda.set_high();
+    delay.delay_us(HALF_PERIOD_US);
+
+    sda.is_high().unwrap_or(false)
 }
 
 // Sequence to flush the TXDR register. This resets the TXIS and TXE
@@ -152,6 +300,12 @@ macro_rules! busy_wait {
                 $i2c.icr.write(|w| w.stopcf().set_bit().nackcf().set_bit());
                 flush_txdr!($i2c);
                 return Err(Error::NotAcknowledge);
+            } else if isr.timeout().bit_is_set() {
+                $i2c.icr.write(|w| w.timoutcf().set_bit());
+                return Err(Error::Timeout);
+            } else if isr.pecerr().bit_is_set() {
+                $i2c.icr.write(|w| w.peccf().set_bit());
+                return Err(Error::Pec);
             } else {
                 // try again
             }
@@ -159,9 +313,10 @@ macro_rules! busy_wait {
     };
 }
 
-// Calculate I2C timing for Analog Filter ON, Digital Filter OFF
+// Calculate I2C timing for the analog filter enabled `$af`, and a digital
+// filter of `$dnf` cycles
 macro_rules! i2c_timing {
-    ($i2cclk:ident, $freq:ident) => {{
+    ($i2cclk:ident, $freq:ident, $af:expr, $dnf:expr) => {{
         // Refer to RM0433 Rev 7 Figure 539 for setup and hold timing:
         //
         // t_I2CCLK = 1 / PCLK1
@@ -169,8 +324,9 @@ macro_rules! i2c_timing {
         // t_SCLL   = (SCLL + 1) * t_PRESC
         // t_SCLH   = (SCLH + 1) * t_PRESC
         //
-        // t_SYNC1 + t_SYNC2 > 4 * t_I2CCLK
+        // t_SYNCx >= t_AF + (DNF + 2) * t_I2CCLK
         // t_SCL ~= t_SYNC1 + t_SYNC2 + t_SCLL + t_SCLH
+        let dnf = $dnf as u32;
         let ratio = $i2cclk / $freq;
 
         // For the standard-mode configuration method, we must have a ratio of 4
@@ -180,6 +336,36 @@ macro_rules! i2c_timing {
             "The I2C PCLK must be at least 4 times the bus frequency!"
         );
 
+        // Both edges of SCL are delayed by the digital filter
+        assert!(
+            ratio >= 4 + 2 * dnf,
+            "The I2C digital filter is too long for this bus frequency!"
+        );
+
+        // The filter delays and 4 cycles must fit within the maximum data
+        // hold time tHD;DAT(max). See RM0433 Rev 7 Section 47.4.5
+        let t_hddat_max_ns: u64 = match $freq {
+            x if x <= 100_000 => 3450, // Standard mode (Sm)
+            x if x <= 400_000 => 900,  // Fast mode (Fm)
+            _ => 450,                  // Fast mode Plus (Fm+)
+        };
+        let t_af_max_ns: u64 = if $af { 80 } else { 0 };
+        assert!(
+            (dnf as u64 + 4) * 1_000_000_000
+                <= (t_hddat_max_ns - t_af_max_ns) * $i2cclk as u64,
+            "The I2C digital filter is too long for this I2C kernel clock!"
+        );
+
+        // With the default filters, the SCL high and low periods below leave
+        // enough margin for t_SYNC1 + t_SYNC2. Otherwise subtract the minimum
+        // cycles of t_SYNC1 + t_SYNC2, with t_AF(min) = 50ns. The SCL period
+        // excluding these is rounded up, so that SCL is not faster than
+        // specified
+        let default_filters = $af && dnf == 0;
+        let t_af = if $af { $i2cclk / 20_000_000 } else { 0 };
+        let sync = 2 * (t_af + dnf + 2);
+        let period = (($i2cclk + $freq - 1) / $freq).saturating_sub(sync);
+
         let (presc_reg, scll, sclh, sdadel, scldel) = if $freq > 100_000 {
             // Fast-mode (Fm) or Fast-mode Plus (Fm+)
             // here we pick SCLL + 1 = 2 * (SCLH + 1)
@@ -191,7 +377,11 @@ macro_rules! i2c_timing {
             // Actual precale value selected
             let presc = (presc_reg + 1) as u32;
 
-            let sclh = ((ratio / presc) - 3) / 3;
+            let sclh = if default_filters {
+                ((ratio / presc) - 3) / 3
+            } else {
+                cmp::max((period + 3 * presc - 1) / (3 * presc), 1) - 1
+            };
             let scll = (2 * (sclh + 1)) - 1;
 
             let (sdadel, scldel) = if $freq > 400_000 {
@@ -232,7 +422,11 @@ macro_rules! i2c_timing {
             // Actual prescale value selected
             let presc = (presc_reg + 1) as u32;
 
-            let sclh = ((ratio / presc) - 2) / 2;
+            let sclh = if default_filters {
+                ((ratio / presc) - 2) / 2
+            } else {
+                cmp::max((period + 2 * presc - 1) / (2 * presc), 1) - 1
+            };
             let scll = sclh;
 
             // Speed check
@@ -256,21 +450,48 @@ macro_rules! i2c_timing {
         // Sanity check
         assert!(presc_reg < 16);
 
+        // SDA is also delayed by the filters. Remove the digital filter
+        // delay, and without the analog filter add t_AF(min) = 50ns rounded
+        // up. See RM0433 Rev 7 Section 47.4.5
+        let presc = presc_reg as u32 + 1;
+        let af_delay = if $af {
+            0
+        } else {
+            ($i2cclk / 20_000_000 + presc) / presc
+        };
+        let sdadel = (sdadel as u32 + af_delay).saturating_sub(dnf / presc);
+
         // Keep values within reasonable limits for fast per_ck
-        let sdadel = cmp::max(sdadel, 2);
+        let sdadel = cmp::max(sdadel, 2_u32.saturating_sub(dnf / presc)) as u8;
         let scldel = cmp::max(scldel, 4);
 
         (presc_reg, scll, sclh, sdadel, scldel)
     }};
 }
 
+// Target mode. Declared here so that the macros above are in scope
+mod target;
+pub use target::{
+    I2cTarget, I2cTargetExt, Oa2Mask, OwnAddress, TargetConfig, TargetEvent,
+};
+
+// SMBus host mode
+mod smbus;
+pub use smbus::{PinSmba, SmBus, SmBusConfig, ALERT_RESPONSE_ADDRESS};
+
+// Interrupt driven transactions
+mod engine;
+pub use engine::{Completion, I2cEngine, Operation, Transaction};
+
 macro_rules! i2c {
     ($($I2CX:ident: ($i2cX:ident, $Rec:ident, $pclkX:ident),)+) => {
         $(
             impl I2c<$I2CX> {
                 /// Create and initialise a new I2C peripheral.
                 ///
-                /// The frequency of the I2C bus clock is specified by `frequency`.
+                /// The frequency of the I2C bus clock and the noise filters
+                /// are specified by `config`, which can also be just the
+                /// frequency.
                 ///
                 /// # Panics
                 ///
@@ -278,17 +499,21 @@ macro_rules! i2c {
                 /// is out of bounds. The acceptable range is [4, 8192].
                 ///
                 /// Panics if the `frequency` is too fast. The maximum is 1MHz.
-                pub fn $i2cX<F> (
+                ///
+                /// Panics if the digital filter is too long for `frequency`
+                /// or the i2c_ker_ck.
+                pub fn $i2cX<C> (
                     i2c: $I2CX,
-                    frequency: F,
+                    config: C,
                     prec: rec::$Rec,
                     clocks: &CoreClocks
                 ) -> Self where
-                    F: Into<Hertz>,
+                    C: Into<Config>,
                 {
                     prec.enable().reset();
 
-                    let freq: u32 = frequency.into().0;
+                    let config: Config = config.into();
+                    let freq: u32 = config.frequency.0;
 
                     // Maximum f_SCL for Fast-mode Plus (Fm+)
                     assert!(freq <= 1_000_000);
@@ -298,13 +523,22 @@ macro_rules! i2c {
                     // Clear PE bit in I2C_CR1
                     i2c.cr1.modify(|_, w| w.pe().clear_bit());
 
-                    // Enable the Analog Noise Filter by setting
-                    // ANFOFF (Analog Noise Filter OFF) to 0.  This is
-                    // usually enabled by default
-                    i2c.cr1.modify(|_, w| w.anfoff().clear_bit());
+                    // Configure the noise filters. ANFOFF (Analog Noise
+                    // Filter OFF) and DNF can only be written while PE is 0
+                    i2c.cr1.modify(|_, w| {
+                        w.anfoff()
+                            .bit(!config.analog_filter)
+                            .dnf()
+                            .bits(config.digital_filter)
+                    });
 
                     // Configure timing
-                    let (presc_reg, scll, sclh, sdadel, scldel) = i2c_timing!(i2c_clk, freq);
+                    let (presc_reg, scll, sclh, sdadel, scldel) = i2c_timing!(
+                        i2c_clk,
+                        freq,
+                        config.analog_filter,
+                        config.digital_filter
+                    );
                     i2c.timingr.write(|w|
                         w.presc()
                             .bits(presc_reg)
@@ -319,9 +553,9 @@ macro_rules! i2c {
                     );
 
                     // Enable the peripheral
-                    i2c.cr1.write(|w| w.pe().set_bit());
+                    i2c.cr1.modify(|_, w| w.pe().set_bit());
 
-                    I2c { i2c }
+                    I2c { i2c, remaining: 0 }
                 }
 
                 /// Returns a reference to the inner peripheral
@@ -329,6 +563,49 @@ macro_rules! i2c {
                     &self.i2c
                 }
 
+                /// Bus recovery
+                ///
+                /// Recovers the bus when a target holds SDA low, for example
+                /// after the controller was reset in the middle of a
+                /// transfer. The `pins` are temporarily configured as open
+                /// drain outputs, and SCL is clocked until the target
+                /// releases SDA, up to nine times. Then a STOP condition is
+                /// generated and the peripheral is re-initialised.
+                ///
+                /// SCL is clocked at about 100kHz using `delay`. The
+                /// internal pull-ups of the pins are disabled.
+                ///
+                /// Returns the pins, with
+                /// [Error::Bus](Error::Bus) if SDA is still held low.
+                pub fn recover_bus<SCL, SDA, D>(
+                    &mut self,
+                    pins: (SCL, SDA),
+                    delay: &mut D,
+                ) -> ((SCL, SDA), Result<(), Error>)
+                where
+                    SCL: PinScl<$I2CX> + RecoveryPin,
+                    SDA: PinSda<$I2CX> + RecoveryPin,
+                    D: DelayUs<u8>,
+                {
+                    let (scl, sda) = pins;
+
+                    // Disabling the peripheral releases SCL and SDA
+                    self.i2c.cr1.modify(|_, w| w.pe().clear_bit());
+
+                    let mut scl = scl.into_gpio();
+                    let mut sda = sda.into_gpio();
+                    let released = clock_out_bus(&mut scl, &mut sda, delay);
+                    let pins = (SCL::from_gpio(scl), SDA::from_gpio(sda));
+
+                    // Clearing PE reset the state machine and the flags,
+                    // the configuration is kept
+                    self.remaining = 0;
+                    self.i2c.cr1.modify(|_, w| w.pe().set_bit());
+
+                    let result = if released { Ok(()) } else { Err(Error::Bus) };
+                    (pins, result)
+                }
+
                 /// Enable or disable the DMA mode for reception
                 pub fn rx_dma(&mut self, enable: bool) {
                     self.i2c.cr1.modify(|_,w| w.rxdmaen().bit(enable));
@@ -405,6 +682,10 @@ macro_rules! i2c {
             /// previous transaction can still be "in progress" up to 50% of a
             /// bus cycle after a ACK/NACK event. Otherwise these methods return
             /// immediately.
+            ///
+            /// Transfers of more than 255 bytes are split into parts of up to
+            /// 255 bytes. The bus clock is stretched after each part until
+            /// [master_reload](I2c::master_reload) is called.
             impl I2c<$I2CX> {
                 /// Master read
                 ///
@@ -415,7 +696,8 @@ macro_rules! i2c {
                 /// Slave:            ...
                 /// ```
                 pub fn master_read(&mut self, addr: u8, length: usize, stop: Stop) {
-                    assert!(length < 256 && length > 0);
+                    assert!(length > 0);
+                    let nbytes = self.first_part(length);
 
                     // Wait for any previous address sequence to end
                     // automatically. This could be up to 50% of a bus
@@ -431,7 +713,9 @@ macro_rules! i2c {
                             .rd_wrn()
                             .read()
                             .nbytes()
-                            .bits(length as u8)
+                            .bits(nbytes)
+                            .reload()
+                            .bit(self.remaining > 0)
                             .start()
                             .set_bit()
                             .autoend()
@@ -447,7 +731,8 @@ macro_rules! i2c {
                 /// Slave:            ...
                 /// ```
                 pub fn master_write(&mut self, addr: u8, length: usize, stop: Stop) {
-                    assert!(length < 256 && length > 0);
+                    assert!(length > 0);
+                    let nbytes = self.first_part(length);
 
                     // Wait for any previous address sequence to end
                     // automatically. This could be up to 50% of a bus
@@ -466,7 +751,9 @@ macro_rules! i2c {
                             .rd_wrn()
                             .write()
                             .nbytes()
-                            .bits(length as u8)
+                            .bits(nbytes)
+                            .reload()
+                            .bit(self.remaining > 0)
                             .autoend()
                             .bit(stop == Stop::Automatic)
                     });
@@ -483,7 +770,8 @@ macro_rules! i2c {
                 /// Slave:  ...             ...
                 /// ```
                 pub fn master_re_start(&mut self, addr: u8, length: usize, stop: Stop) {
-                    assert!(length < 256 && length > 0);
+                    assert!(length > 0);
+                    let nbytes = self.first_part(length);
 
                     self.i2c.cr2.write(|w| {
                         w.sadd()
@@ -492,7 +780,9 @@ macro_rules! i2c {
                             .rd_wrn()
                             .read()
                             .nbytes()
-                            .bits(length as u8)
+                            .bits(nbytes)
+                            .reload()
+                            .bit(self.remaining > 0)
                             .start()
                             .set_bit()
                             .autoend()
@@ -500,6 +790,46 @@ macro_rules! i2c {
                     });
                 }
 
+                /// Master reload
+                ///
+                /// Continues a transfer of more than 255 bytes with the next
+                /// part, once the previous part is complete (the TCR flag is
+                /// set). Returns `false`, without doing anything, if the TCR
+                /// flag is not set.
+                ///
+                /// For DMA transfers, listen for
+                /// [TransferComplete](Event::TransferComplete), which also
+                /// occurs when TCR is set, and call this method from the
+                /// interrupt handler.
+                pub fn master_reload(&mut self) -> bool {
+                    if self.i2c.isr.read().tcr().bit_is_clear() {
+                        return false;
+                    }
+
+                    let nbytes = cmp::min(self.remaining, NBYTES_MAX);
+                    self.remaining -= nbytes;
+
+                    // Writing NBYTES clears TCR. AUTOEND is kept from the
+                    // start of the transfer
+                    self.i2c.cr2.modify(|_, w| {
+                        w.nbytes()
+                            .bits(nbytes as u8)
+                            .reload()
+                            .bit(self.remaining > 0)
+                    });
+
+                    true
+                }
+
+                /// Returns NBYTES for the first part of a transfer of
+                /// `length` bytes, and records the remaining bytes
+                fn first_part(&mut self, length: usize) -> u8 {
+                    let nbytes = cmp::min(length, NBYTES_MAX);
+                    self.remaining = length - nbytes;
+
+                    nbytes as u8
+                }
+
                 /// Master stop
                 ///
                 /// Generate a stop condition.
@@ -522,7 +852,9 @@ macro_rules! i2c {
                 /// be passed as `pins`. This function sets each pin to
                 /// open-drain mode.
                 ///
-                /// The frequency of the I2C bus clock is specified by `frequency`.
+                /// The frequency of the I2C bus clock and the noise filters
+                /// are specified by `config`, which can also be just the
+                /// frequency.
                 ///
                 /// # Panics
                 ///
@@ -530,22 +862,27 @@ macro_rules! i2c {
                 /// is out of bounds. The acceptable range is [4, 8192].
                 ///
                 /// Panics if the `frequency` is too fast. The maximum is 1MHz.
-                fn i2c<PINS, F>(self, pins: PINS, frequency: F,
+                ///
+                /// Panics if the digital filter is too long for `frequency`
+                /// or the i2c_ker_ck.
+                fn i2c<PINS, C>(self, pins: PINS, config: C,
                                 prec: rec::$Rec,
                                 clocks: &CoreClocks) -> I2c<$I2CX>
                 where
                     PINS: Pins<$I2CX>,
-                    F: Into<Hertz>
+                    C: Into<Config>
                 {
                     let _ = pins.set_open_drain();
 
-                    I2c::$i2cX(self, frequency, prec, clocks)
+                    I2c::$i2cX(self, config, prec, clocks)
                 }
 
                 /// Create and initialise a new I2C peripheral. No pin types are
                 /// required.
                 ///
-                /// The frequency of the I2C bus clock is specified by `frequency`.
+                /// The frequency of the I2C bus clock and the noise filters
+                /// are specified by `config`, which can also be just the
+                /// frequency.
                 ///
                 /// # Panics
                 ///
@@ -553,13 +890,16 @@ macro_rules! i2c {
                 /// is out of bounds. The acceptable range is [4, 8192].
                 ///
                 /// Panics if the `frequency` is too fast. The maximum is 1MHz.
-                fn i2c_unchecked<F>(self, frequency: F,
+                ///
+                /// Panics if the digital filter is too long for `frequency`
+                /// or the i2c_ker_ck.
+                fn i2c_unchecked<C>(self, config: C,
                                     prec: rec::$Rec,
                                     clocks: &CoreClocks) -> I2c<$I2CX>
                 where
-                    F: Into<Hertz>
+                    C: Into<Config>
                 {
-                    I2c::$i2cX(self, frequency, prec, clocks)
+                    I2c::$i2cX(self, config, prec, clocks)
                 }
             }
 
@@ -567,15 +907,20 @@ macro_rules! i2c {
                 type Error = Error;
 
                 fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Error> {
-                    // TODO support transfers of more than 255 bytes
-                    assert!(bytes.len() < 256 && bytes.len() > 0);
+                    assert!(!bytes.is_empty());
 
                     // I2C start
                     //
                     // ST SAD+W
                     self.master_write(addr, bytes.len(), Stop::Software);
 
-                    for byte in bytes {
+                    for (i, byte) in bytes.iter().enumerate() {
+                        // Load the next part of a long transfer
+                        if i > 0 && i % NBYTES_MAX == 0 {
+                            busy_wait!(self.i2c, tcr, bit_is_set);
+                            self.master_reload();
+                        }
+
                         // Wait until we are allowed to send data
                         // (START has been ACKed or last byte when
                         // through)
@@ -604,16 +949,21 @@ macro_rules! i2c {
                     bytes: &[u8],
                     buffer: &mut [u8],
                 ) -> Result<(), Error> {
-                    // TODO support transfers of more than 255 bytes
-                    assert!(bytes.len() < 256 && bytes.len() > 0);
-                    assert!(buffer.len() < 256 && buffer.len() > 0);
+                    assert!(!bytes.is_empty());
+                    assert!(!buffer.is_empty());
 
                     // I2C start
                     //
                     // ST SAD+W
                     self.master_write(addr, bytes.len(), Stop::Software);
 
-                    for byte in bytes {
+                    for (i, byte) in bytes.iter().enumerate() {
+                        // Load the next part of a long transfer
+                        if i > 0 && i % NBYTES_MAX == 0 {
+                            busy_wait!(self.i2c, tcr, bit_is_set);
+                            self.master_reload();
+                        }
+
                         // Wait until we are allowed to send data
                         // (START has been ACKed or last byte went through)
                         busy_wait!(self.i2c, txis, is_empty);
@@ -630,7 +980,13 @@ macro_rules! i2c {
                     // SR  SAD+R
                     self.master_re_start(addr, buffer.len(), Stop::Automatic);
 
-                    for byte in buffer {
+                    for (i, byte) in buffer.iter_mut().enumerate() {
+                        // Load the next part of a long transfer
+                        if i > 0 && i % NBYTES_MAX == 0 {
+                            busy_wait!(self.i2c, tcr, bit_is_set);
+                            self.master_reload();
+                        }
+
                         // Wait until we have received something
                         busy_wait!(self.i2c, rxne, is_not_empty);
 
@@ -651,12 +1007,17 @@ macro_rules! i2c {
                     addr: u8,
                     buffer: &mut [u8],
                 ) -> Result<(), Error> {
-                    // TODO support transfers of more than 255 bytes
-                    assert!(buffer.len() < 256 && buffer.len() > 0);
+                    assert!(!buffer.is_empty());
 
                     self.master_read(addr, buffer.len(), Stop::Automatic);
 
-                    for byte in buffer {
+                    for (i, byte) in buffer.iter_mut().enumerate() {
+                        // Load the next part of a long transfer
+                        if i > 0 && i % NBYTES_MAX == 0 {
+                            busy_wait!(self.i2c, tcr, bit_is_set);
+                            self.master_reload();
+                        }
+
                         // Wait until we have received something
                         busy_wait!(self.i2c, rxne, is_not_empty);
 
@@ -672,22 +1033,57 @@ macro_rules! i2c {
     };
 }
 
+macro_rules! into_alternate {
+    ($pin:expr, AF4) => {
+        $pin.into_alternate_af4()
+    };
+    ($pin:expr, AF6) => {
+        $pin.into_alternate_af6()
+    };
+}
+
 macro_rules! pins {
-    ($($I2CX:ty: SCL: [$($SCL:ty),*] SDA: [$($SDA:ty),*])+) => {
+    ($($I2CX:ty:
+       SCL: [$($SCL:ident<Alternate<$SCL_AF:ident>>),*]
+       SDA: [$($SDA:ident<Alternate<$SDA_AF:ident>>),*])+) => {
         $(
             $(
-                impl PinScl<$I2CX> for $SCL {
+                impl PinScl<$I2CX> for $SCL<Alternate<$SCL_AF>> {
                     fn set_open_drain(self) -> Self {
                         self.set_open_drain()
                     }
                 }
+
+                impl RecoveryPin for $SCL<Alternate<$SCL_AF>> {
+                    type Gpio = $SCL<Output<OpenDrain>>;
+
+                    fn into_gpio(self) -> Self::Gpio {
+                        self.into_open_drain_output()
+                    }
+
+                    fn from_gpio(gpio: Self::Gpio) -> Self {
+                        into_alternate!(gpio, $SCL_AF).set_open_drain()
+                    }
+                }
             )*
             $(
-                impl PinSda<$I2CX> for $SDA {
+                impl PinSda<$I2CX> for $SDA<Alternate<$SDA_AF>> {
                     fn set_open_drain(self) -> Self {
                         self.set_open_drain()
                     }
                 }
+
+                impl RecoveryPin for $SDA<Alternate<$SDA_AF>> {
+                    type Gpio = $SDA<Output<OpenDrain>>;
+
+                    fn into_gpio(self) -> Self::Gpio {
+                        self.into_open_drain_output()
+                    }
+
+                    fn from_gpio(gpio: Self::Gpio) -> Self {
+                        into_alternate!(gpio, $SDA_AF).set_open_drain()
+                    }
+                }
             )*
         )+
     }
@@ -758,10 +1154,11 @@ i2c!(
 mod tests {
     use core::cmp;
 
-    /// Runs a timing testcase over PCLK and I2C clock ranges
+    /// Runs a timing testcase over PCLK and I2C clock ranges, and noise
+    /// filter settings
     fn i2c_timing_testcase<F>(f: F)
     where
-        F: Fn(u32, u32),
+        F: Fn(u32, u32, bool, u8),
     {
         let i2c_timing_tests = [
             // (i2c_clk, range of bus frequencies to test)
@@ -774,9 +1171,14 @@ mod tests {
             (120_000_000, (15_000..=1_000_000)), // Max PCLK
         ];
 
+        // (analog filter, digital filter)
+        let filters = [(true, 0), (false, 0), (true, 1), (false, 2)];
+
         for (clock, freq_range) in i2c_timing_tests.iter() {
             for freq in freq_range.clone().step_by(1_000) {
-                f(*clock, freq)
+                for (af, dnf) in filters.iter() {
+                    f(*clock, freq, *af, *dnf)
+                }
             }
         }
     }
@@ -784,18 +1186,21 @@ mod tests {
     #[test]
     /// Test the SCL frequency is within the expected range
     fn i2c_frequency() {
-        i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let (presc_reg, scll, sclh, _, _) = i2c_timing!(i2c_clk, freq);
+        i2c_timing_testcase(|i2c_clk: u32, freq: u32, af: bool, dnf: u8| {
+            let (presc_reg, scll, sclh, _, _) =
+                i2c_timing!(i2c_clk, freq, af, dnf);
 
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
             let t_i2c_clk = 1. / (i2c_clk as f32);
             let freq = freq as f32;
 
-            // Estimate minimum sync times. Analog filter on, 2 i2c_clk cycles
-            let t_af_min = 50e-9_f32; // Analog filter 50ns. From H7 Datasheet
-            let t_sync1 = t_af_min + 2. * t_i2c_clk;
-            let t_sync2 = t_af_min + 2. * t_i2c_clk;
+            // Estimate minimum sync times. Analog filter, digital filter and
+            // 2 i2c_clk cycles
+            let t_af_min = if af { 50e-9_f32 } else { 0. }; // From H7 Datasheet
+            let t_dnf = dnf as f32 * t_i2c_clk;
+            let t_sync1 = t_af_min + t_dnf + 2. * t_i2c_clk;
+            let t_sync2 = t_af_min + t_dnf + 2. * t_i2c_clk;
 
             // See RM0433 Rev 7 Section 47.4.9
             let t_high_low = sclh as f32 + 1. + scll as f32 + 1.;
@@ -822,8 +1227,12 @@ mod tests {
     #[test]
     /// Test that the low period of SCL is greater than the minimum specification
     fn i2c_scl_low() {
-        i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let (presc_reg, scll, _, _, _) = i2c_timing!(i2c_clk, freq);
+        i2c_timing_testcase(|i2c_clk: u32, freq: u32, af: bool, dnf: u8| {
+            if !af || dnf != 0 {
+                return; // Default filters only
+            }
+            let (presc_reg, scll, _, _, _) =
+                i2c_timing!(i2c_clk, freq, af, dnf);
 
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
@@ -849,11 +1258,52 @@ mod tests {
         });
     }
 
+    #[test]
+    /// Test that the low period of SCL is greater than the minimum
+    /// specification, with non-default noise filters
+    fn i2c_scl_low_filters() {
+        i2c_timing_testcase(|i2c_clk: u32, freq: u32, af: bool, dnf: u8| {
+            if af && dnf == 0 {
+                return; // Tested above
+            }
+            let (presc_reg, scll, _, _, _) =
+                i2c_timing!(i2c_clk, freq, af, dnf);
+
+            // Timing parameters
+            let presc = (presc_reg + 1) as f32;
+            let t_i2c_clk = 1. / (i2c_clk as f32);
+            let freq = freq as f32;
+            // The SCL low period counter starts when SCL low is detected,
+            // after t_SYNC1. So SCL is low for t_SYNC1 + t_SCLL. See RM0433
+            // Rev 7 Section 47.4.9. Estimate the minimum sync time
+            let t_af_min = if af { 50e-9_f32 } else { 0. }; // From H7 Datasheet
+            let t_sync1 = t_af_min + (dnf as f32 + 2.) * t_i2c_clk;
+            let t_scll = t_sync1 + (scll as f32 + 1.) * presc * t_i2c_clk;
+
+            // From I2C Specification Table 10
+            //
+            // UM10204 rev 6.: https://www.nxp.com/docs/en/user-guide/UM10204.pdf
+            let t_scll_minimum = match freq {
+                x if x <= 100_000. => 4.7e-6, // Standard mode (Sm)
+                x if x <= 400_000. => 1.3e-6, // Fast mode (Fm)
+                _ => 0.5e-6,                  // Fast mode Plus (Fm+)
+            };
+
+            println!("Target {} Hz; SCLL {}", freq, scll);
+            println!(
+                "T SCL LOW {:.2e}; MINIMUM {:.2e}",
+                t_scll, t_scll_minimum
+            );
+            assert!(t_scll >= t_scll_minimum);
+        });
+    }
+
     #[test]
     /// Test the SDADEL value is greater than the minimum specification
     fn i2c_sdadel_minimum() {
-        i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let (presc_reg, _, _, sdadel, _) = i2c_timing!(i2c_clk, freq);
+        i2c_timing_testcase(|i2c_clk: u32, freq: u32, af: bool, dnf: u8| {
+            let (presc_reg, _, _, sdadel, _) =
+                i2c_timing!(i2c_clk, freq, af, dnf);
 
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
@@ -870,14 +1320,16 @@ mod tests {
                 _ => 120e-9,                  // Fast mode Plus (Fm+)
             };
 
-            let t_af_min = 50e-9_f32; // Analog filter min 50ns. From H7 Datasheet
+            // Analog filter min 50ns. From H7 Datasheet
+            let t_af_min = if af { 50e-9_f32 } else { 0. };
             let hddat_min = 0.;
+            let dnf = dnf as f32;
 
             // From RM0433 Rev 7 Section 47.4.5
             //
             // tSDADEL >= {tf + tHD;DAT(min) - tAF(min) - [(DNF + 3) x tI2CCLK]}
             let t_sdadel_minimim =
-                t_fall_max + hddat_min - t_af_min - (3. * t_i2c_clk);
+                t_fall_max + hddat_min - t_af_min - ((dnf + 3.) * t_i2c_clk);
 
             println!("Target {} Hz; SDADEL {}", freq, sdadel);
             println!(
@@ -891,8 +1343,9 @@ mod tests {
     #[test]
     /// Test the SDADEL value is less than the maximum specification
     fn i2c_sdadel_maximum() {
-        i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let (presc_reg, _, _, sdadel, _) = i2c_timing!(i2c_clk, freq);
+        i2c_timing_testcase(|i2c_clk: u32, freq: u32, af: bool, dnf: u8| {
+            let (presc_reg, _, _, sdadel, _) =
+                i2c_timing!(i2c_clk, freq, af, dnf);
 
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
@@ -905,12 +1358,15 @@ mod tests {
                 x if x <= 400_000. => 0.9e-6,  // Fast mode (Fm)
                 _ => 0.45e-6,                  // Fast mode Plus (Fm+)
             };
-            let t_af_max = 80e-9_f32; // Analog filter max 80ns. From H7 Datasheet
+            // Analog filter max 80ns. From H7 Datasheet
+            let t_af_max = if af { 80e-9_f32 } else { 0. };
+            let dnf = dnf as f32;
 
             // From RM0433 Rev 7 Section 47.4.5
             //
             // tSDADEL <= {tHD;DAT(max) - tAF(max) - [(DNF + 4) x tI2CCLK]}
-            let t_sdadel_maximum = t_hddat_max - t_af_max - (4. * t_i2c_clk);
+            let t_sdadel_maximum =
+                t_hddat_max - t_af_max - ((dnf + 4.) * t_i2c_clk);
 
             println!("Target {} Hz; SDADEL {}", freq, sdadel);
             println!(
@@ -924,8 +1380,9 @@ mod tests {
     #[test]
     /// Test the SCLDEL value is greater than the minimum specification
     fn i2c_scldel_minimum() {
-        i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let (presc_reg, _, _, _, scldel) = i2c_timing!(i2c_clk, freq);
+        i2c_timing_testcase(|i2c_clk: u32, freq: u32, af: bool, dnf: u8| {
+            let (presc_reg, _, _, _, scldel) =
+                i2c_timing!(i2c_clk, freq, af, dnf);
 
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
@@ -0,0 +1,514 @@
+//! SMBus and PMBus host
+//!
+//! An [SmBus](SmBus) is created from an [I2c](super::I2c) controller, and
+//! implements the SMBus host protocols. The Packet Error Code (PEC) is
+//! calculated, sent and checked by hardware. PMBus uses the same protocols,
+//! so PMBus commands are sent with the methods here.
+//!
+//! ```
+//! let i2c = dp.I2C1.i2c((scl, sda), 100.khz(), ccdr.peripheral.I2C1, &ccdr.clocks);
+//! let mut smbus = i2c.smbus(SmBusConfig::default(), &ccdr.clocks);
+//!
+//! // PMBus READ_VOUT
+//! let vout = smbus.read_word(0x40, 0x8B)?;
+//! ```
+//!
+//! # Timeouts
+//!
+//! By default the bus is reset if SCL is held low for 25ms (tTIMEOUT), and
+//! a transfer fails if the cumulative clock stretching in one message
+//! exceeds 10ms (tLOW:MEXT). Both return [Error::Timeout](Error::Timeout).
+//!
+//! # SMBALERT#
+//!
+//! With [enable_alert](SmBus::enable_alert), devices can request attention
+//! by pulling the SMBA pin low. The host then reads the address of the
+//! device from the Alert Response Address, [ALERT_RESPONSE_ADDRESS].
+
+use core::cmp;
+
+use super::{Error, I2c, NBYTES_MAX};
+use crate::gpio::gpioa::PA9;
+use crate::gpio::gpiob::{PB12, PB5};
+use crate::gpio::gpiod::PD11;
+use crate::gpio::gpiof::{PF13, PF2};
+use crate::gpio::gpioh::{PH10, PH6, PH9};
+use crate::gpio::{Alternate, AF4, AF6};
+use crate::rcc::CoreClocks;
+use crate::stm32::{I2C1, I2C2, I2C3, I2C4};
+use crate::time::MicroSeconds;
+
+/// Address that devices asserting SMBALERT# respond to
+pub const ALERT_RESPONSE_ADDRESS: u8 = 0x0C;
+
+/// A trait to represent the SMBA Pin of an I2C Port
+pub trait PinSmba<I2C> {}
+
+/// SMBus configuration
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SmBusConfig {
+    pec: bool,
+    timeout: Option<MicroSeconds>,
+    clock_low_extend: Option<MicroSeconds>,
+}
+
+impl Default for SmBusConfig {
+    /// PEC enabled, 25ms timeout and 10ms cumulative clock low extension
+    fn default() -> Self {
+        SmBusConfig {
+            pec: true,
+            timeout: Some(MicroSeconds(25_000)),
+            clock_low_extend: Some(MicroSeconds(10_000)),
+        }
+    }
+}
+
+impl SmBusConfig {
+    /// Enable or disable the Packet Error Code
+    pub fn pec(mut self, pec: bool) -> Self {
+        self.pec = pec;
+        self
+    }
+
+    /// Reset the bus if SCL is held low for `timeout`, or disable the
+    /// timeout with `None`
+    pub fn timeout<T>(mut self, timeout: Option<T>) -> Self
+    where
+        T: Into<MicroSeconds>,
+    {
+        self.timeout = timeout.map(Into::into);
+        self
+    }
+
+    /// Fail a transfer if the cumulative clock stretching in one message
+    /// exceeds `extend`, or disable this check with `None`
+    pub fn clock_low_extend<T>(mut self, extend: Option<T>) -> Self
+    where
+        T: Into<MicroSeconds>,
+    {
+        self.clock_low_extend = extend.map(Into::into);
+        self
+    }
+}
+
+/// Calculates TIMEOUTA or TIMEOUTB for `timeout` with an I2C kernel clock
+/// of `i2c_clk`. See RM0433 Rev 7 Section 47.4.11
+///
+/// t = (TIMEOUTx + 1) * 2048 * t_I2CCLK
+///
+/// Where TIMEOUTx is a 12-bit value. Returns `None` if `timeout` is out of
+/// range
+fn calculate_timeout(i2c_clk: u32, timeout: MicroSeconds) -> Option<u16> {
+    // Round to the nearest multiple of 2048 cycles
+    let ticks = (u64::from(i2c_clk) * u64::from(timeout.0) + 1_024_000_000)
+        / 2_048_000_000;
+
+    match ticks {
+        1..=4096 => Some(ticks as u16 - 1),
+        _ => None,
+    }
+}
+
+/// SMBus host, using an I2C peripheral
+#[derive(Debug)]
+pub struct SmBus<I2C> {
+    i2c: I2c<I2C>,
+    pec: bool,
+}
+
+macro_rules! smbus {
+    ($($I2CX:ident: $pclkX:ident,)+) => {
+        $(
+            impl I2c<$I2CX> {
+                /// Configures the I2C peripheral as an SMBus host
+                ///
+                /// # Panics
+                ///
+                /// Panics if a timeout is out of range. The maximum is
+                /// 4096 * 2048 periods of the I2C kernel clock
+                pub fn smbus(self, config: SmBusConfig, clocks: &CoreClocks) -> SmBus<$I2CX> {
+                    let i2c_clk = clocks.$pclkX().0;
+
+                    let timeout = config.timeout.map(|t| {
+                        calculate_timeout(i2c_clk, t).expect("SMBus timeout out of range")
+                    });
+                    let extend = config.clock_low_extend.map(|t| {
+                        calculate_timeout(i2c_clk, t)
+                            .expect("SMBus clock low extension out of range")
+                    });
+
+                    // Clear PE bit in I2C_CR1
+                    self.i2c.cr1.modify(|_, w| w.pe().clear_bit());
+
+                    // Timeout A detects SCL low (TIDLE = 0)
+                    self.i2c.timeoutr.write(|w| unsafe {
+                        w.timeouta()
+                            .bits(timeout.unwrap_or(0))
+                            .tidle()
+                            .clear_bit()
+                            .timouten()
+                            .bit(timeout.is_some())
+                            .timeoutb()
+                            .bits(extend.unwrap_or(0))
+                            .texten()
+                            .bit(extend.is_some())
+                    });
+
+                    self.i2c.cr1.modify(|_, w| {
+                        w.smbhen()
+                            .set_bit()
+                            .pecen()
+                            .bit(config.pec)
+                            .pe()
+                            .set_bit()
+                    });
+
+                    SmBus { i2c: self, pec: config.pec }
+                }
+            }
+
+            impl SmBus<$I2CX> {
+                /// Quick command. Sends only the address, with the R/W bit
+                /// as the data
+                ///
+                /// ```
+                /// Host:   ST SAD+R/W    SP
+                /// Device:            A
+                /// ```
+                pub fn quick_command(&mut self, addr: u8, read: bool) -> Result<(), Error> {
+                    self.start(addr, read, 0, false, true, false);
+                    self.end()
+                }
+
+                /// Send byte
+                pub fn send_byte(&mut self, addr: u8, byte: u8) -> Result<(), Error> {
+                    self.write_pec(addr, &[byte])
+                }
+
+                /// Receive byte
+                pub fn receive_byte(&mut self, addr: u8) -> Result<u8, Error> {
+                    let mut buffer = [0];
+                    self.read_pec(addr, &mut buffer)?;
+
+                    Ok(buffer[0])
+                }
+
+                /// Write byte to the register `command`
+                pub fn write_byte(&mut self, addr: u8, command: u8, byte: u8) -> Result<(), Error> {
+                    self.write_pec(addr, &[command, byte])
+                }
+
+                /// Write word to the register `command`. Words are sent
+                /// least significant byte first
+                pub fn write_word(&mut self, addr: u8, command: u8, word: u16) -> Result<(), Error> {
+                    let [low, high] = word.to_le_bytes();
+                    self.write_pec(addr, &[command, low, high])
+                }
+
+                /// Read byte from the register `command`
+                pub fn read_byte(&mut self, addr: u8, command: u8) -> Result<u8, Error> {
+                    let mut buffer = [0];
+                    self.write_read_pec(addr, &[command], &mut buffer)?;
+
+                    Ok(buffer[0])
+                }
+
+                /// Read word from the register `command`
+                pub fn read_word(&mut self, addr: u8, command: u8) -> Result<u16, Error> {
+                    let mut buffer = [0; 2];
+                    self.write_read_pec(addr, &[command], &mut buffer)?;
+
+                    Ok(u16::from_le_bytes(buffer))
+                }
+
+                /// Process call. Writes `word` to the register `command`,
+                /// then reads a word in the same message
+                pub fn process_call(&mut self, addr: u8, command: u8, word: u16) -> Result<u16, Error> {
+                    let [low, high] = word.to_le_bytes();
+                    let mut buffer = [0; 2];
+                    self.write_read_pec(addr, &[command, low, high], &mut buffer)?;
+
+                    Ok(u16::from_le_bytes(buffer))
+                }
+
+                /// Block write. Sends the byte count, then `data`, which is
+                /// at most 255 bytes
+                pub fn block_write(&mut self, addr: u8, command: u8, data: &[u8]) -> Result<(), Error> {
+                    assert!(data.len() <= 255, "SMBus blocks are at most 255 bytes");
+
+                    let count = data.len() + 2;
+                    self.start(addr, false, count + self.pec as usize, self.pec, true, false);
+
+                    self.write_bytes(&[command, data.len() as u8])?;
+                    self.write_bytes_from(data, 2)?;
+                    self.end()
+                }
+
+                /// Block read. Returns the byte count sent by the device
+                ///
+                /// If the count is larger than `buffer`, the extra bytes are
+                /// discarded.
+                pub fn block_read(&mut self, addr: u8, command: u8, buffer: &mut [u8]) -> Result<usize, Error> {
+                    self.start(addr, false, 1, false, false, false);
+                    self.write_bytes(&[command])?;
+                    busy_wait!(self.i2c.i2c, tc, is_complete);
+
+                    // Read the byte count, then continue with the block
+                    // once it is known
+                    self.start(addr, true, 1, self.pec, true, true);
+                    busy_wait!(self.i2c.i2c, rxne, is_not_empty);
+                    let count = usize::from(self.i2c.i2c.rxdr.read().rxdata().bits());
+
+                    busy_wait!(self.i2c.i2c, tcr, bit_is_set);
+                    self.i2c.remaining = count + self.pec as usize;
+                    self.i2c.master_reload();
+
+                    let length = cmp::min(count, buffer.len());
+                    self.read_bytes(&mut buffer[..length], count + self.pec as usize)?;
+                    self.end()?;
+
+                    Ok(count)
+                }
+
+                /// Enables the SMBALERT# input on `pin`. The alert is
+                /// reported by [is_alert](SmBus::is_alert), and raises the
+                /// error interrupt if listening for
+                /// [Errors](super::Event::Errors)
+                pub fn enable_alert<PIN>(&mut self, _pin: PIN)
+                where
+                    PIN: PinSmba<$I2CX>,
+                {
+                    self.i2c.i2c.cr1.modify(|_, w| w.alerten().set_bit());
+                }
+
+                /// Returns true if a device asserted SMBALERT#, and clears
+                /// the flag
+                pub fn is_alert(&mut self) -> bool {
+                    if self.i2c.i2c.isr.read().alert().bit_is_set() {
+                        self.i2c.i2c.icr.write(|w| w.alertcf().set_bit());
+                        true
+                    } else {
+                        false
+                    }
+                }
+
+                /// Returns a mutable reference to the underlying I2C
+                /// controller, for example to listen for events
+                pub fn i2c(&mut self) -> &mut I2c<$I2CX> {
+                    &mut self.i2c
+                }
+
+                /// Disables SMBus mode, and returns the I2C controller
+                pub fn free(self) -> I2c<$I2CX> {
+                    let i2c = &self.i2c.i2c;
+
+                    i2c.cr1.modify(|_, w| w.pe().clear_bit());
+                    i2c.timeoutr.reset();
+                    i2c.cr1.modify(|_, w| {
+                        w.smbhen()
+                            .clear_bit()
+                            .pecen()
+                            .clear_bit()
+                            .alerten()
+                            .clear_bit()
+                            .pe()
+                            .set_bit()
+                    });
+
+                    self.i2c
+                }
+
+                /// Writes `bytes`, followed by the PEC
+                fn write_pec(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Error> {
+                    self.start(addr, false, bytes.len() + self.pec as usize, self.pec, true, false);
+                    self.write_bytes(bytes)?;
+                    self.end()
+                }
+
+                /// Reads into `buffer`, followed by the PEC
+                fn read_pec(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Error> {
+                    let length = buffer.len() + self.pec as usize;
+
+                    self.start(addr, true, length, self.pec, true, false);
+                    self.read_bytes(buffer, length)?;
+                    self.end()
+                }
+
+                /// Writes `bytes`, then reads into `buffer` after a repeated
+                /// start, followed by the PEC
+                fn write_read_pec(&mut self, addr: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+                    self.start(addr, false, bytes.len(), false, false, false);
+                    self.write_bytes(bytes)?;
+                    busy_wait!(self.i2c.i2c, tc, is_complete);
+
+                    self.read_pec(addr, buffer)
+                }
+
+                /// Starts a transfer of `length` bytes, including the PEC. If
+                /// `reload` is set, the transfer is continued after `length`
+                /// bytes even if it was not longer
+                fn start(&mut self, addr: u8, read: bool, length: usize, pec: bool, autoend: bool, reload: bool) {
+                    let nbytes = self.i2c.first_part(length);
+
+                    // Wait for any previous address sequence to end
+                    // automatically
+                    while self.i2c.i2c.cr2.read().start().bit_is_set() {}
+
+                    // Clear a STOP flag left by a failed transfer
+                    self.i2c.i2c.icr.write(|w| w.stopcf().set_bit());
+
+                    self.i2c.i2c.cr2.write(|w| {
+                        w.sadd()
+                            .bits(u16::from(addr) << 1)
+                            .add10()
+                            .clear_bit()
+                            .rd_wrn()
+                            .bit(read)
+                            .nbytes()
+                            .bits(nbytes)
+                            .reload()
+                            .bit(reload || self.i2c.remaining > 0)
+                            .pecbyte()
+                            .bit(pec)
+                            .autoend()
+                            .bit(autoend)
+                            .start()
+                            .set_bit()
+                    });
+                }
+
+                /// Writes `bytes` at the start of the transfer
+                fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+                    self.write_bytes_from(bytes, 0)
+                }
+
+                /// Writes `bytes`, starting at byte `offset` of the
+                /// transfer. If the PEC starts a new part of the transfer,
+                /// that part is loaded
+                fn write_bytes_from(&mut self, bytes: &[u8], offset: usize) -> Result<(), Error> {
+                    for (i, byte) in bytes.iter().enumerate() {
+                        let i = i + offset;
+
+                        // Load the next part of a long transfer
+                        if i > 0 && i % NBYTES_MAX == 0 {
+                            busy_wait!(self.i2c.i2c, tcr, bit_is_set);
+                            self.i2c.master_reload();
+                        }
+
+                        busy_wait!(self.i2c.i2c, txis, is_empty);
+                        self.i2c.i2c.txdr.write(|w| w.txdata().bits(*byte));
+                    }
+
+                    // The PEC is sent by hardware, and may start the next
+                    // part of the transfer
+                    let end = offset + bytes.len();
+                    if end > 0 && end % NBYTES_MAX == 0 && self.i2c.remaining > 0 {
+                        busy_wait!(self.i2c.i2c, tcr, bit_is_set);
+                        self.i2c.master_reload();
+                    }
+
+                    Ok(())
+                }
+
+                /// Reads `length` bytes, storing the first bytes in
+                /// `buffer`. The PEC is read but not stored
+                fn read_bytes(&mut self, buffer: &mut [u8], length: usize) -> Result<(), Error> {
+                    for i in 0..length {
+                        // Load the next part of a long transfer
+                        if i > 0 && i % NBYTES_MAX == 0 {
+                            busy_wait!(self.i2c.i2c, tcr, bit_is_set);
+                            self.i2c.master_reload();
+                        }
+
+                        busy_wait!(self.i2c.i2c, rxne, is_not_empty);
+                        let byte = self.i2c.i2c.rxdr.read().rxdata().bits();
+
+                        if let Some(b) = buffer.get_mut(i) {
+                            *b = byte;
+                        }
+                    }
+
+                    Ok(())
+                }
+
+                /// Waits for the automatic STOP, then checks the PEC
+                fn end(&mut self) -> Result<(), Error> {
+                    busy_wait!(self.i2c.i2c, stopf, bit_is_set);
+                    self.i2c.i2c.icr.write(|w| w.stopcf().set_bit());
+
+                    if self.i2c.i2c.isr.read().pecerr().bit_is_set() {
+                        self.i2c.i2c.icr.write(|w| w.peccf().set_bit());
+                        return Err(Error::Pec);
+                    }
+
+                    Ok(())
+                }
+            }
+        )+
+    };
+}
+
+smbus!(
+    I2C1: pclk1,
+    I2C2: pclk1,
+    I2C3: pclk1,
+    I2C4: pclk4,
+);
+
+macro_rules! smba_pins {
+    ($($I2CX:ty: [$($SMBA:ty),*])+) => {
+        $(
+            $(
+                impl PinSmba<$I2CX> for $SMBA {}
+            )*
+        )+
+    }
+}
+
+smba_pins! {
+    I2C1: [
+        PB5<Alternate<AF4>>
+    ]
+    I2C2: [
+        PB12<Alternate<AF4>>,
+        PF2<Alternate<AF4>>,
+        PH6<Alternate<AF4>>
+    ]
+    I2C3: [
+        PA9<Alternate<AF4>>,
+        PH9<Alternate<AF4>>
+    ]
+    I2C4: [
+        PB5<Alternate<AF6>>,
+        PD11<Alternate<AF4>>,
+        PF13<Alternate<AF4>>,
+        PH10<Alternate<AF4>>
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeout() {
+        // 25ms at 120MHz: (1464 + 1) * 2048 / 120MHz = 25.003ms
+        assert_eq!(
+            calculate_timeout(120_000_000, MicroSeconds(25_000)),
+            Some(1464)
+        );
+        // 10ms at 16MHz: (77 + 1) * 2048 / 16MHz = 9.984ms
+        assert_eq!(
+            calculate_timeout(16_000_000, MicroSeconds(10_000)),
+            Some(77)
+        );
+        // Maximum, 4096 * 2048 cycles
+        assert_eq!(
+            calculate_timeout(100_000_000, MicroSeconds(83_886)),
+            Some(4095)
+        );
+        // Out of range
+        assert_eq!(calculate_timeout(120_000_000, MicroSeconds(100_000)), None);
+        assert_eq!(calculate_timeout(120_000_000, MicroSeconds(5)), None);
+    }
+}
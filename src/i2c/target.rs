@@ -0,0 +1,427 @@
+//! I2C target (slave) mode
+//!
+//! An [I2cTarget](I2cTarget) responds to one or two own addresses, and
+//! optionally the general call address. Events on the bus are returned by
+//! [poll](I2cTarget::poll). Clock stretching is always enabled, so the
+//! controller waits while the target handles each event. This makes it
+//! possible to call `poll` from an interrupt handler, see
+//! [listen](I2cTarget::listen).
+//!
+//! ```
+//! let config = TargetConfig::new(OwnAddress::SevenBit(0x42));
+//! let mut target = dp.I2C1.i2c_target(
+//!     (scl, sda),
+//!     config,
+//!     100.khz(),
+//!     ccdr.peripheral.I2C1,
+//!     &ccdr.clocks,
+//! );
+//!
+//! loop {
+//!     match block!(target.poll()) {
+//!         Ok(TargetEvent::Received(byte)) => { ... },
+//!         Ok(TargetEvent::TransmitRequest) => target.write(0xAB),
+//!         _ => {}
+//!     }
+//! }
+//! ```
+
+use core::cmp;
+use core::marker::PhantomData;
+
+use super::{Error, Pins};
+use crate::rcc::{rec, CoreClocks, ResetEnable};
+use crate::stm32::{I2C1, I2C2, I2C3, I2C4};
+use crate::time::Hertz;
+
+/// Own address of an I2C target
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OwnAddress {
+    /// 7-bit address
+    SevenBit(u8),
+    /// 10-bit address
+    TenBit(u16),
+}
+
+/// Mask for the second own address. Masked bits are not compared, so the
+/// target responds to a range of addresses
+///
+/// Reserved addresses are never acknowledged
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Oa2Mask {
+    /// All bits are compared
+    NoMask = 0,
+    /// Bit 1 is masked
+    Mask1 = 1,
+    /// Bits 2:1 are masked
+    Mask2 = 2,
+    /// Bits 3:1 are masked
+    Mask3 = 3,
+    /// Bits 4:1 are masked
+    Mask4 = 4,
+    /// Bits 5:1 are masked
+    Mask5 = 5,
+    /// Bits 6:1 are masked
+    Mask6 = 6,
+    /// Bits 7:1 are masked, all addresses are acknowledged
+    Mask7 = 7,
+}
+
+/// Configuration of an I2C target
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TargetConfig {
+    own_address1: OwnAddress,
+    own_address2: Option<(u8, Oa2Mask)>,
+    general_call: bool,
+}
+
+impl TargetConfig {
+    /// Target that responds to `address`
+    pub fn new(address: OwnAddress) -> Self {
+        TargetConfig {
+            own_address1: address,
+            own_address2: None,
+            general_call: false,
+        }
+    }
+
+    /// Also respond to the 7-bit `address`, with the bits in `mask` not
+    /// compared
+    pub fn own_address2(mut self, address: u8, mask: Oa2Mask) -> Self {
+        assert!(address < 0x80, "Own address 2 is a 7-bit address");
+        self.own_address2 = Some((address, mask));
+        self
+    }
+
+    /// Also respond to the general call address (0x00)
+    pub fn general_call(mut self) -> Self {
+        self.general_call = true;
+        self
+    }
+}
+
+/// Events of an I2C target
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TargetEvent {
+    /// The controller addressed the target to write to it. The 7-bit
+    /// address that matched is given, or the full 10-bit own address 1
+    Write { address: u16 },
+    /// The controller addressed the target to read from it. The 7-bit
+    /// address that matched is given, or the full 10-bit own address 1
+    Read { address: u16 },
+    /// The controller wrote a byte
+    Received(u8),
+    /// The controller is waiting for the next byte, which must be written
+    /// with [write](I2cTarget::write). The clock is stretched until then
+    TransmitRequest,
+    /// The controller ended the transfer with a stop condition
+    Stop,
+}
+
+/// I2C peripheral operating in target (slave) mode
+#[derive(Debug)]
+pub struct I2cTarget<I2C> {
+    i2c: I2C,
+    config: TargetConfig,
+}
+
+/// Extension trait for I2C peripherals that can operate in target mode
+pub trait I2cTargetExt<I2C>: Sized {
+    type Rec: ResetEnable;
+
+    /// Create and initialise a new I2C peripheral in target mode
+    ///
+    /// A tuple of pins `(scl, sda)` for this I2C peripheral should be passed
+    /// as `pins`. This function sets each pin to open-drain mode.
+    ///
+    /// `frequency` is the bus frequency used by the controller, which sets
+    /// the data setup and hold times.
+    fn i2c_target<PINS, F>(
+        self,
+        pins: PINS,
+        config: TargetConfig,
+        frequency: F,
+        prec: Self::Rec,
+        clocks: &CoreClocks,
+    ) -> I2cTarget<I2C>
+    where
+        PINS: Pins<I2C>,
+        F: Into<Hertz>;
+}
+
+macro_rules! i2c_target {
+    ($($I2CX:ident: ($i2cX:ident, $Rec:ident, $pclkX:ident),)+) => {
+        $(
+            impl I2cTarget<$I2CX> {
+                /// Create and initialise a new I2C peripheral in target
+                /// mode
+                ///
+                /// `frequency` is the bus frequency used by the controller,
+                /// which sets the data setup and hold times.
+                ///
+                /// # Panics
+                ///
+                /// Panics if the ratio between `frequency` and the
+                /// i2c_ker_ck is out of bounds. The acceptable range is [4,
+                /// 8192].
+                ///
+                /// Panics if the `frequency` is too fast. The maximum is
+                /// 1MHz.
+                pub fn $i2cX<F>(
+                    i2c: $I2CX,
+                    config: TargetConfig,
+                    frequency: F,
+                    prec: rec::$Rec,
+                    clocks: &CoreClocks,
+                ) -> Self
+                where
+                    F: Into<Hertz>,
+                {
+                    prec.enable().reset();
+
+                    let freq: u32 = frequency.into().0;
+
+                    // Maximum f_SCL for Fast-mode Plus (Fm+)
+                    assert!(freq <= 1_000_000);
+
+                    let i2c_clk: u32 = clocks.$pclkX().0;
+
+                    // Clear PE bit in I2C_CR1
+                    i2c.cr1.modify(|_, w| w.pe().clear_bit());
+
+                    // Configure timing. Only the prescaler, SDADEL and
+                    // SCLDEL are used in target mode
+                    let (presc_reg, scll, sclh, sdadel, scldel) = i2c_timing!(i2c_clk, freq, true, 0);
+                    i2c.timingr.write(|w|
+                        w.presc()
+                            .bits(presc_reg)
+                            .scll()
+                            .bits(scll)
+                            .sclh()
+                            .bits(sclh)
+                            .sdadel()
+                            .bits(sdadel)
+                            .scldel()
+                            .bits(scldel)
+                    );
+
+                    // Own address 1
+                    match config.own_address1 {
+                        OwnAddress::SevenBit(address) => {
+                            assert!(address < 0x80, "Not a 7-bit address");
+                            i2c.oar1.write(|w| {
+                                w.oa1()
+                                    .bits(u16::from(address) << 1)
+                                    .oa1mode()
+                                    .clear_bit()
+                                    .oa1en()
+                                    .set_bit()
+                            });
+                        }
+                        OwnAddress::TenBit(address) => {
+                            assert!(address < 0x400, "Not a 10-bit address");
+                            i2c.oar1.write(|w| {
+                                w.oa1()
+                                    .bits(address)
+                                    .oa1mode()
+                                    .set_bit()
+                                    .oa1en()
+                                    .set_bit()
+                            });
+                        }
+                    }
+
+                    // Own address 2
+                    if let Some((address, mask)) = config.own_address2 {
+                        i2c.oar2.write(|w| unsafe {
+                            w.oa2()
+                                .bits(address)
+                                .oa2msk()
+                                .bits(mask as u8)
+                                .oa2en()
+                                .set_bit()
+                        });
+                    }
+
+                    // Enable the Analog Noise Filter, keep clock
+                    // stretching enabled and enable the peripheral
+                    i2c.cr1.write(|w| {
+                        w.anfoff()
+                            .clear_bit()
+                            .nostretch()
+                            .clear_bit()
+                            .gcen()
+                            .bit(config.general_call)
+                            .pe()
+                            .set_bit()
+                    });
+
+                    I2cTarget { i2c, config }
+                }
+
+                /// Returns a reference to the inner peripheral
+                pub fn inner(&self) -> &$I2CX {
+                    &self.i2c
+                }
+
+                /// Returns the next event on the bus, or `WouldBlock` if
+                /// there is none
+                ///
+                /// The clock is stretched from the address match until the
+                /// event is returned, and from a transmit request until the
+                /// next byte is written.
+                pub fn poll(&mut self) -> nb::Result<TargetEvent, Error> {
+                    let isr = self.i2c.isr.read();
+
+                    if isr.berr().bit_is_set() {
+                        self.i2c.icr.write(|w| w.berrcf().set_bit());
+                        return Err(nb::Error::Other(Error::Bus));
+                    }
+                    if isr.ovr().bit_is_set() {
+                        self.i2c.icr.write(|w| w.ovrcf().set_bit());
+                        return Err(nb::Error::Other(Error::Overrun));
+                    }
+
+                    // Data from a write, before a repeated start
+                    if isr.rxne().bit_is_set() {
+                        let byte = self.i2c.rxdr.read().rxdata().bits();
+                        return Ok(TargetEvent::Received(byte));
+                    }
+
+                    // The controller does not acknowledge the last byte of a
+                    // read. Discard the byte already in TXDR
+                    if isr.nackf().bit_is_set() {
+                        self.i2c.icr.write(|w| w.nackcf().set_bit());
+                        flush_txdr!(self.i2c);
+                    }
+
+                    if isr.stopf().bit_is_set() {
+                        self.i2c.icr.write(|w| w.stopcf().set_bit());
+                        flush_txdr!(self.i2c);
+                        return Ok(TargetEvent::Stop);
+                    }
+
+                    if isr.addr().bit_is_set() {
+                        let address = self.matched_address(isr.addcode().bits());
+                        let read = isr.dir().bit_is_set();
+
+                        // Start a read with an empty TXDR, so that the
+                        // first byte is requested
+                        if read {
+                            self.i2c.isr.write(|w| w.txe().set_bit());
+                        }
+
+                        // Releases the clock
+                        self.i2c.icr.write(|w| w.addrcf().set_bit());
+
+                        return Ok(if read {
+                            TargetEvent::Read { address }
+                        } else {
+                            TargetEvent::Write { address }
+                        });
+                    }
+
+                    if isr.txis().bit_is_set() {
+                        return Ok(TargetEvent::TransmitRequest);
+                    }
+
+                    Err(nb::Error::WouldBlock)
+                }
+
+                /// Writes the next byte of a read. Should be called after a
+                /// [TransmitRequest](TargetEvent::TransmitRequest)
+                pub fn write(&mut self, byte: u8) {
+                    self.i2c.txdr.write(|w| w.txdata().bits(byte));
+                }
+
+                /// Enables the interrupts for all target events. The
+                /// interrupt handler should call `poll` until it returns
+                /// `WouldBlock`
+                pub fn listen(&mut self) {
+                    self.i2c.cr1.modify(|_, w| {
+                        w.addrie()
+                            .set_bit()
+                            .rxie()
+                            .set_bit()
+                            .txie()
+                            .set_bit()
+                            .stopie()
+                            .set_bit()
+                            .nackie()
+                            .set_bit()
+                            .errie()
+                            .set_bit()
+                    });
+                }
+
+                /// Disables the interrupts for all target events
+                pub fn unlisten(&mut self) {
+                    self.i2c.cr1.modify(|_, w| {
+                        w.addrie()
+                            .clear_bit()
+                            .rxie()
+                            .clear_bit()
+                            .txie()
+                            .clear_bit()
+                            .stopie()
+                            .clear_bit()
+                            .nackie()
+                            .clear_bit()
+                            .errie()
+                            .clear_bit()
+                    });
+                    let _ = self.i2c.cr1.read();
+                    let _ = self.i2c.cr1.read(); // Delay 2 peripheral clocks
+                }
+
+                /// Releases the I2C peripheral
+                pub fn free(self) -> ($I2CX, rec::$Rec) {
+                    self.i2c.cr1.modify(|_, w| w.pe().clear_bit());
+
+                    (self.i2c, rec::$Rec { _marker: PhantomData })
+                }
+
+                /// The address that matched, from the ADDCODE field. For a
+                /// 10-bit address, this only contains the header
+                fn matched_address(&self, addcode: u8) -> u16 {
+                    match self.config.own_address1 {
+                        OwnAddress::TenBit(address)
+                            if addcode == 0b111_1000 | (address >> 8) as u8 =>
+                        {
+                            address
+                        }
+                        _ => u16::from(addcode),
+                    }
+                }
+            }
+
+            impl I2cTargetExt<$I2CX> for $I2CX {
+                type Rec = rec::$Rec;
+
+                fn i2c_target<PINS, F>(
+                    self,
+                    pins: PINS,
+                    config: TargetConfig,
+                    frequency: F,
+                    prec: rec::$Rec,
+                    clocks: &CoreClocks,
+                ) -> I2cTarget<$I2CX>
+                where
+                    PINS: Pins<$I2CX>,
+                    F: Into<Hertz>,
+                {
+                    let _ = pins.set_open_drain();
+
+                    I2cTarget::$i2cX(self, config, frequency, prec, clocks)
+                }
+            }
+        )+
+    };
+}
+
+i2c_target!(
+    I2C1: (i2c1, I2c1, pclk1),
+    I2C2: (i2c2, I2c2, pclk1),
+    I2C3: (i2c3, I2c3, pclk1),
+    I2C4: (i2c4, I2c4, pclk4),
+);
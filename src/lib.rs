@@ -21,8 +21,9 @@
 //!
 //! * [Inter Integrated Circuit (I2C)](crate::i2c)
 //! * [Serial Peripheral Interface (SPI)](crate::spi)
-//! * [Serial Data (USART/UART)](crate::serial)
+//! * [Serial Data (USART/UART/LPUART)](crate::serial)
 //! * [Serial Audio Interface](crate::sai)
+//! * [Controller Area Network (FDCAN)](crate::fdcan) Feature gate `can`
 //! * [Quad SPI](crate::qspi) Feature gate `quadspi`
 //! * [Ethernet](crate::ethernet) Feature gate `ethernet`
 //! * [USB HS](crate::usb_hs) Feature gate `usb_hs`
@@ -36,16 +37,20 @@
 //! Timing functions
 //!
 //! * [Pulse Width Modulation (PWM)](crate::pwm)
+//! * [Input Capture and PWM Input](crate::capture)
+//! * [One-Pulse Mode](crate::one_pulse)
+//! * [High Resolution Timer (HRTIM)](crate::hrtim)
 //! * [Quadrature Encoder Interface](crate::qei)
 //! * [Real-Time Clock](crate::rtc) Feature gate `rtc`
 //! * [Timers](crate::timer)
+//! * [Monotonic Timers for RTIC](crate::monotonic) Feature gate `rtic`
 //! * [Delays](crate::delay)
 //!
 //! Others
 //!
 //! * [Cyclic Redundancy Check (CRC)](crate::crc) Feature gate `crc`
 //! * [Random Number Generator](crate::rng)
-//! * [System Window Watchdog](crate::watchdog)
+//! * [System Window Watchdog and Independent Watchdog](crate::watchdog)
 
 #![cfg_attr(not(test), no_std)]
 #![allow(non_camel_case_types)]
@@ -137,6 +142,8 @@ pub mod adc;
 #[cfg(all(feature = "device-selected", feature = "crc"))]
 pub mod crc;
 #[cfg(feature = "device-selected")]
+pub mod capture;
+#[cfg(feature = "device-selected")]
 pub mod dac;
 #[cfg(feature = "device-selected")]
 pub mod delay;
@@ -150,16 +157,24 @@ pub mod dma;
 pub mod ethernet;
 #[cfg(feature = "device-selected")]
 pub mod exti;
+#[cfg(all(feature = "device-selected", feature = "can"))]
+pub mod fdcan;
 #[cfg(feature = "device-selected")]
 pub mod flash;
 #[cfg(all(feature = "device-selected", feature = "fmc"))]
 pub mod fmc;
 #[cfg(feature = "device-selected")]
 pub mod gpio;
+#[cfg(all(feature = "device-selected", not(feature = "rm0455")))]
+pub mod hrtim;
 #[cfg(feature = "device-selected")]
 pub mod i2c;
 #[cfg(all(feature = "device-selected", feature = "ltdc"))]
 pub mod ltdc;
+#[cfg(all(feature = "device-selected", feature = "rtic"))]
+pub mod monotonic;
+#[cfg(feature = "device-selected")]
+pub mod one_pulse;
 #[cfg(feature = "device-selected")]
 pub mod prelude;
 #[cfg(feature = "device-selected")]
@@ -0,0 +1,362 @@
+//! Monotonic timers for RTIC
+//!
+//! Implementations of the [Monotonic](rtic_monotonic::Monotonic) trait, used
+//! by RTIC to timestamp and schedule software tasks. Requires the `rtic`
+//! feature, which needs Rust 1.51 or later.
+//!
+//! * [MonoTimer](MonoTimer) uses the 32-bit counter of `TIM2` or `TIM5`,
+//!   counting at `FREQ`. Tasks are scheduled by a compare match on channel 1,
+//!   so the timer interrupt is bound to the monotonic.
+//! * [DwtMonotonic](DwtMonotonic) uses the DWT cycle counter, extended to 64
+//!   bits, counting at the core clock. Tasks are scheduled by SysTick, so the
+//!   SysTick exception is bound to the monotonic.
+//!
+//! # Usage
+//!
+//! ```
+//! #[monotonic(binds = TIM2, default = true)]
+//! type Mono = MonoTimer<pac::TIM2, 1_000_000>;
+//!
+//! #[init]
+//! fn init(ctx: init::Context) -> (Shared, Local, init::Monotonics) {
+//!     ...
+//!     let mono = ctx.device.TIM2.monotonic(ccdr.peripheral.TIM2, &ccdr.clocks);
+//!
+//!     blink::spawn_after(500.ms().into()).unwrap();
+//!
+//!     (Shared {}, Local {}, init::Monotonics(mono))
+//! }
+//! ```
+//!
+//! Durations convert from the [time](crate::time) units, and 32-bit durations
+//! convert back.
+//!
+//! ```
+//! let timeout: Duration<1_000_000> = 250.us().into();
+//! let us: MicroSeconds = timeout.into();
+//! ```
+//!
+//! # Range
+//!
+//! The 32-bit [Instant](Instant) of a [MonoTimer](MonoTimer) wraps after
+//! 2<sup>32</sup> ticks, and instants can only be compared if they are less
+//! than 2<sup>31</sup> ticks apart. At 1MHz that is about 35 minutes. The
+//! 64-bit [Instant64](Instant64) of a [DwtMonotonic](DwtMonotonic) does not
+//! wrap in practice.
+
+use core::convert::TryFrom;
+use core::marker::PhantomData;
+
+use cortex_m::peripheral::syst::SystClkSource;
+use cortex_m::peripheral::{DCB, DWT, SYST};
+use rtic_monotonic::Monotonic;
+
+use crate::rcc::{rec, CoreClocks, ResetEnable};
+use crate::stm32::{TIM2, TIM5};
+use crate::time::{MicroSeconds, MilliSeconds, NanoSeconds};
+use crate::timer::GetClk;
+
+/// An instant of a 32-bit monotonic timer counting at `FREQ` Hz
+pub type Instant<const FREQ: u32> = fugit::TimerInstantU32<FREQ>;
+/// A duration of a 32-bit monotonic timer counting at `FREQ` Hz
+pub type Duration<const FREQ: u32> = fugit::TimerDurationU32<FREQ>;
+/// An instant of a 64-bit monotonic timer counting at `FREQ` Hz
+pub type Instant64<const FREQ: u32> = fugit::TimerInstantU64<FREQ>;
+/// A duration of a 64-bit monotonic timer counting at `FREQ` Hz
+pub type Duration64<const FREQ: u32> = fugit::TimerDurationU64<FREQ>;
+
+// Into fugit durations
+macro_rules! fugit_duration {
+    ($($Duration:ident: $widen:path,)+) => {
+        $(
+            impl<const FREQ: u32> From<MilliSeconds> for fugit::$Duration<FREQ> {
+                fn from(ms: MilliSeconds) -> Self {
+                    Self::millis($widen(ms.0))
+                }
+            }
+
+            impl<const FREQ: u32> From<MicroSeconds> for fugit::$Duration<FREQ> {
+                fn from(us: MicroSeconds) -> Self {
+                    Self::micros($widen(us.0))
+                }
+            }
+
+            impl<const FREQ: u32> From<NanoSeconds> for fugit::$Duration<FREQ> {
+                fn from(ns: NanoSeconds) -> Self {
+                    Self::nanos($widen(ns.0))
+                }
+            }
+        )+
+    };
+}
+
+fugit_duration! {
+    TimerDurationU32: core::convert::identity,
+    TimerDurationU64: u64::from,
+}
+
+// From 32-bit fugit durations
+impl<const FREQ: u32> From<fugit::TimerDurationU32<FREQ>> for MilliSeconds {
+    fn from(duration: fugit::TimerDurationU32<FREQ>) -> Self {
+        MilliSeconds(duration.to_millis())
+    }
+}
+impl<const FREQ: u32> From<fugit::TimerDurationU32<FREQ>> for MicroSeconds {
+    fn from(duration: fugit::TimerDurationU32<FREQ>) -> Self {
+        MicroSeconds(duration.to_micros())
+    }
+}
+impl<const FREQ: u32> From<fugit::TimerDurationU32<FREQ>> for NanoSeconds {
+    fn from(duration: fugit::TimerDurationU32<FREQ>) -> Self {
+        NanoSeconds(duration.to_nanos())
+    }
+}
+
+/// Monotonic timer based on a 32-bit general purpose timer
+pub struct MonoTimer<TIM, const FREQ: u32> {
+    tim: TIM,
+}
+
+/// Extension trait for 32-bit timers that can be used as a monotonic timer
+pub trait MonoTimerExt: Sized {
+    type Rec: ResetEnable;
+
+    /// Configures the timer as a monotonic timer counting at `FREQ` Hz
+    ///
+    /// Panics if the timer kernel clock is not an integer multiple of
+    /// `FREQ`, or is more than 65536 times `FREQ`
+    fn monotonic<const FREQ: u32>(
+        self,
+        prec: Self::Rec,
+        clocks: &CoreClocks,
+    ) -> MonoTimer<Self, FREQ>;
+}
+
+/// Prescaler for a timer kernel clock `clk` to count at `freq`. The prescaler
+/// divides by PSC + 1
+fn calculate_prescaler(clk: u32, freq: u32) -> Option<u16> {
+    if freq == 0 || clk % freq != 0 {
+        return None;
+    }
+
+    u16::try_from(clk / freq - 1).ok()
+}
+
+macro_rules! mono_hal {
+    ($($TIMX:ident: $Rec:ident,)+) => {
+        $(
+            impl MonoTimerExt for $TIMX {
+                type Rec = rec::$Rec;
+
+                fn monotonic<const FREQ: u32>(
+                    self,
+                    prec: Self::Rec,
+                    clocks: &CoreClocks,
+                ) -> MonoTimer<Self, FREQ> {
+                    MonoTimer::<$TIMX, FREQ>::new(self, prec, clocks)
+                }
+            }
+
+            impl<const FREQ: u32> MonoTimer<$TIMX, FREQ> {
+                /// Configures the timer as a monotonic timer counting at
+                /// `FREQ` Hz
+                ///
+                /// Panics if the timer kernel clock is not an integer
+                /// multiple of `FREQ`, or is more than 65536 times `FREQ`
+                pub fn new(tim: $TIMX, prec: rec::$Rec, clocks: &CoreClocks) -> Self {
+                    prec.enable().reset();
+
+                    let clk = $TIMX::get_clk(clocks)
+                        .expect("Timer input clock not running!").0;
+                    let psc = calculate_prescaler(clk, FREQ)
+                        .expect("Timer input clock is not a multiple of FREQ");
+
+                    tim.psc.write(|w| w.psc().bits(psc));
+                    tim.arr.write(|w| w.arr().bits(u32::MAX));
+
+                    // Load the prescaler
+                    tim.egr.write(|w| w.ug().set_bit());
+                    tim.sr.modify(|_, w| w.uif().clear_bit());
+
+                    tim.cr1.modify(|_, w| w.cen().set_bit());
+
+                    MonoTimer { tim }
+                }
+
+                /// Releases the TIM peripheral
+                pub fn free(self) -> ($TIMX, rec::$Rec) {
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.tim.dier.modify(|_, w| w.cc1ie().clear_bit());
+
+                    (self.tim, rec::$Rec { _marker: PhantomData })
+                }
+            }
+
+            impl<const FREQ: u32> Monotonic for MonoTimer<$TIMX, FREQ> {
+                type Instant = Instant<FREQ>;
+                type Duration = Duration<FREQ>;
+
+                fn now(&mut self) -> Self::Instant {
+                    Instant::from_ticks(self.tim.cnt.read().bits())
+                }
+
+                fn set_compare(&mut self, instant: Self::Instant) {
+                    // The counter wraps at 32 bits, the same as Instant
+                    let ticks = instant.duration_since_epoch().ticks();
+
+                    self.tim.ccr1.write(|w| w.ccr().bits(ticks));
+                }
+
+                fn clear_compare_flag(&mut self) {
+                    self.tim.sr.modify(|_, w| w.cc1if().clear_bit());
+                }
+
+                fn zero() -> Self::Instant {
+                    Instant::from_ticks(0)
+                }
+
+                unsafe fn reset(&mut self) {
+                    // Clear the counter
+                    self.tim.egr.write(|w| w.ug().set_bit());
+                    self.tim.sr.modify(|_, w| w.uif().clear_bit().cc1if().clear_bit());
+
+                    self.tim.dier.modify(|_, w| w.cc1ie().set_bit());
+                }
+
+                fn enable_timer(&mut self) {
+                    self.tim.dier.modify(|_, w| w.cc1ie().set_bit());
+                }
+
+                fn disable_timer(&mut self) {
+                    self.tim.dier.modify(|_, w| w.cc1ie().clear_bit());
+                }
+            }
+        )+
+    };
+}
+
+mono_hal! {
+    TIM2: Tim2,
+    TIM5: Tim5,
+}
+
+/// Maximum SysTick reload value
+const MAX_RELOAD: u32 = 0x00FF_FFFF;
+
+/// Monotonic timer based on the DWT cycle counter, with scheduling by
+/// SysTick. `FREQ` must be the core clock
+///
+/// The cycle counter is extended to 64 bits in software. For this the
+/// SysTick interrupt fires at least every 2<sup>24</sup> cycles, even when
+/// no tasks are scheduled.
+pub struct DwtMonotonic<const FREQ: u32> {
+    dwt: DWT,
+    systick: SYST,
+    last: u64,
+}
+
+impl<const FREQ: u32> DwtMonotonic<FREQ> {
+    /// Enables the DWT cycle counter and configures SysTick
+    ///
+    /// Panics if `FREQ` is not the core clock
+    pub fn new(
+        dcb: &mut DCB,
+        mut dwt: DWT,
+        systick: SYST,
+        clocks: &CoreClocks,
+    ) -> Self {
+        assert_eq!(clocks.c_ck().0, FREQ, "FREQ must be the core clock");
+
+        dcb.enable_trace();
+        dwt.enable_cycle_counter();
+
+        DwtMonotonic {
+            dwt,
+            systick,
+            last: 0,
+        }
+    }
+
+    /// Releases the DWT and SYST peripherals
+    pub fn free(mut self) -> (DWT, SYST) {
+        self.systick.disable_interrupt();
+        self.systick.disable_counter();
+
+        (self.dwt, self.systick)
+    }
+}
+
+impl<const FREQ: u32> Monotonic for DwtMonotonic<FREQ> {
+    type Instant = Instant64<FREQ>;
+    type Duration = Duration64<FREQ>;
+
+    // SysTick keeps running to extend the cycle counter
+    const DISABLE_INTERRUPT_ON_EMPTY_QUEUE: bool = false;
+
+    fn now(&mut self) -> Self::Instant {
+        // Only valid if called at least once per 2^32 cycles
+        let elapsed = self.dwt.cyccnt.read().wrapping_sub(self.last as u32);
+        self.last += u64::from(elapsed);
+
+        Instant64::from_ticks(self.last)
+    }
+
+    fn set_compare(&mut self, instant: Self::Instant) {
+        let now = self.now();
+        let ticks = instant
+            .checked_duration_since(now)
+            .map_or(0, |duration| duration.ticks());
+
+        // A reload value of 0 would stop SysTick
+        let reload = ticks.max(1).min(MAX_RELOAD as u64) as u32;
+
+        self.systick.set_reload(reload);
+        self.systick.clear_current();
+    }
+
+    fn clear_compare_flag(&mut self) {
+        // The SysTick exception is cleared by hardware
+    }
+
+    fn zero() -> Self::Instant {
+        Instant64::from_ticks(0)
+    }
+
+    unsafe fn reset(&mut self) {
+        self.systick.set_clock_source(SystClkSource::Core);
+        self.systick.set_reload(MAX_RELOAD);
+        self.systick.clear_current();
+        self.systick.enable_counter();
+        self.systick.enable_interrupt();
+
+        self.dwt.cyccnt.write(0);
+        self.last = 0;
+    }
+
+    fn on_interrupt(&mut self) {
+        // Keep the 64-bit extension up to date
+        self.now();
+
+        // Fire again after the maximum interval, unless set_compare is
+        // called for the next task
+        self.systick.set_reload(MAX_RELOAD);
+        self.systick.clear_current();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prescaler() {
+        assert_eq!(calculate_prescaler(200_000_000, 1_000_000), Some(199));
+        assert_eq!(calculate_prescaler(200_000_000, 200_000_000), Some(0));
+        assert_eq!(calculate_prescaler(240_000_000, 10_000), Some(23_999));
+        // Not an integer division
+        assert_eq!(calculate_prescaler(200_000_000, 3_000_000), None);
+        // Prescaler out of range
+        assert_eq!(calculate_prescaler(200_000_000, 1_000), None);
+        assert_eq!(calculate_prescaler(200_000_000, 0), None);
+    }
+}
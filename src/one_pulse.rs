@@ -0,0 +1,832 @@
+//! One-Pulse Mode
+//!
+//! Generates a single pulse with a programmable delay and width, either when
+//! started by software or after an edge on a trigger input. One-pulse mode
+//! is available on the advanced control timers (`TIM1`, `TIM8`), the general
+//! purpose timers with a slave mode controller (`TIM[2-5]`, `TIM12`,
+//! `TIM15`) and the Low-power timers (`LPTIM[1-5]`).
+//!
+//! The output pin is selected from the pins that can be used for PWM output
+//! with the same timer, see the [Pins](../pwm/trait.Pins.html) trait.
+//!
+//! ## Usage
+//!
+//! ```
+//! // 2µs pulse on PE9, 10µs after a rising edge on PE11
+//! let mut pulse = dp.TIM1
+//!     .one_pulse(gpioe.pe9.into_alternate_af1(), ccdr.peripheral.TIM1, &ccdr.clocks)
+//!     .delay(10.us())
+//!     .width(2.us())
+//!     .trigger_pin(gpioe.pe11.into_alternate_af1(), CaptureEdge::Rising)
+//!     .finalize();
+//!
+//! pulse.enable();
+//! ```
+//!
+//! Timers can also be triggered by the TRGO output of another timer (see
+//! [`trigger`](struct.OnePulseBuilder.html#method.trigger)), or started by
+//! software with [`start`](struct.OnePulse.html#method.start).
+//!
+//! ## Retriggerable pulses
+//!
+//! By default, triggers that occur during a pulse are ignored. With
+//! `retriggerable`, each trigger restarts the delay, so that the pulse is
+//! extended for as long as triggers keep arriving.
+//!
+//! ## Timing
+//!
+//! The delay and width are rounded down to a multiple of the counter
+//! period, and are at least one counter period. The pulse starts a few
+//! kernel clock cycles after the trigger, due to input resynchronisation and
+//! any digital filter.
+
+use core::marker::PhantomData;
+
+use crate::capture::CaptureEdge;
+use crate::pwm::{Pins, Polarity, C1, C2, C3, C4};
+use crate::rcc::{rec, CoreClocks, ResetEnable};
+use crate::stm32::{LPTIM1, LPTIM2, LPTIM3};
+#[cfg(not(feature = "rm0455"))]
+use crate::stm32::{LPTIM4, LPTIM5};
+use crate::stm32::{TIM1, TIM12, TIM15, TIM2, TIM3, TIM4, TIM5, TIM8};
+use crate::time::NanoSeconds;
+use crate::timer::{
+    lp_edge_bits, GetClk, TriggerInput, LPTIM_CFGR_PRESC_SHIFT,
+    LPTIM_CFGR_TIMOUT, LPTIM_CFGR_TRIGEN_SHIFT, LPTIM_CFGR_TRIGSEL_SHIFT,
+    LPTIM_CFGR_WAVPOL,
+};
+
+use self::sealed::OutputChannel;
+
+/// External trigger inputs of the Low-power timers
+///
+/// Input 0 is the LPTIMx_ETR pin. Refer to RM0433 Rev 7 - Table 362 for the
+/// other connections.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LpTrigger {
+    Trig0 = 0,
+    Trig1 = 1,
+    Trig2 = 2,
+    Trig3 = 3,
+    Trig4 = 4,
+    Trig5 = 5,
+    Trig6 = 6,
+    Trig7 = 7,
+}
+
+/// Channels that can be used as a trigger input in one-pulse mode
+pub trait TriggerChannel {
+    /// Channel number
+    const INDEX: u8;
+}
+impl TriggerChannel for C1 {
+    const INDEX: u8 = 1;
+}
+impl TriggerChannel for C2 {
+    const INDEX: u8 = 2;
+}
+
+/// Internal enum that keeps track of the trigger before the pulse is
+/// finalized
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum PulseTrigger {
+    Software,
+    /// Slave mode controller trigger, SMCR.TS value
+    Internal(u8),
+    /// Channel 1 or 2 input pin
+    Pin(u8, CaptureEdge),
+    /// LPTIM external trigger
+    LowPower(LpTrigger, CaptureEdge),
+}
+
+/// OnePulseBuilder is used to configure a one-pulse output
+pub struct OnePulseBuilder<TIM, CHANNEL> {
+    tim: TIM,
+    _channel: PhantomData<CHANNEL>,
+    clk: u32,
+    delay: NanoSeconds,
+    width: NanoSeconds,
+    polarity: Polarity,
+    retriggerable: bool,
+    trigger: PulseTrigger,
+}
+
+/// A timer channel in one-pulse mode
+pub struct OnePulse<TIM, CHANNEL> {
+    tim: TIM,
+    _channel: PhantomData<CHANNEL>,
+    clk: u32,
+}
+
+/// Allows the one_pulse() method to be added to the peripheral register
+/// structs from the device crate
+pub trait OnePulseExt: Sized {
+    type Rec: ResetEnable;
+
+    /// Configures a timer to output single pulses on `_pin`. Only a single
+    /// pin may be passed
+    fn one_pulse<PIN, CHANNEL, COMP>(
+        self,
+        _pin: PIN,
+        prec: Self::Rec,
+        clocks: &CoreClocks,
+    ) -> OnePulseBuilder<Self, CHANNEL>
+    where
+        PIN: Pins<Self, CHANNEL, COMP>;
+}
+
+/// Number of `clk` ticks in `ns`, rounded to the nearest tick
+fn ns_to_ticks(clk: u32, ns: NanoSeconds) -> u64 {
+    (u64::from(clk) * u64::from(ns.0) + 500_000_000) / 1_000_000_000
+}
+
+// Pulse calculator for TIMx
+// Returns (PSC, CCR, ARR)
+fn calculate_pulse(
+    clk: u32,
+    delay: NanoSeconds,
+    width: NanoSeconds,
+    counter_max: u32,
+) -> Option<(u16, u32, u32)> {
+    // The output is active from CNT = CCR to CNT = ARR. The delay must be at
+    // least one tick, otherwise the output stays active after the update
+    let delay = ns_to_ticks(clk, delay).max(1);
+    let width = ns_to_ticks(clk, width).max(1);
+    let range = u64::from(counter_max) + 1;
+
+    // Division factor is (PSC + 1)
+    let div = (delay + width + range - 1) / range;
+    if div > 1 << 16 {
+        return None;
+    }
+
+    let ccr = (delay / div).max(1);
+    let arr = ccr + (width / div).max(1) - 1;
+    if arr > u64::from(counter_max) {
+        return None;
+    }
+
+    Some(((div - 1) as u16, ccr as u32, arr as u32))
+}
+
+// Pulse calculator for LPTIMx
+// Returns (CFGR.PRESC, CMP, ARR)
+fn calculate_lp_pulse(
+    clk: u32,
+    delay: NanoSeconds,
+    width: NanoSeconds,
+) -> Option<(u8, u16, u16)> {
+    // Division factor is 2^PRESC
+    (0..=7).find_map(|presc| {
+        let (cmp, arr) = calculate_lp_pulse_presc(clk, delay, width, presc)?;
+        Some((presc, cmp, arr))
+    })
+}
+
+// Pulse calculator for LPTIMx with a fixed CFGR.PRESC
+// Returns (CMP, ARR)
+fn calculate_lp_pulse_presc(
+    clk: u32,
+    delay: NanoSeconds,
+    width: NanoSeconds,
+    presc: u8,
+) -> Option<(u16, u16)> {
+    // The output is set once CNT > CMP, and reset after CNT = ARR. So the
+    // delay is CMP + 1 and the width is ARR - CMP. Ticks are rounded at the
+    // kernel clock, then divided by the prescaler
+    let delay = ns_to_ticks(clk, delay).max(1);
+    let width = ns_to_ticks(clk, width).max(1);
+
+    let cmp = (delay >> presc).max(1) - 1;
+    let arr = cmp + (width >> presc).max(1);
+    if arr > 0xFFFF {
+        return None;
+    }
+
+    Some((cmp as u16, arr as u16))
+}
+
+// CCMRx fields, relative to the channel
+const CCMR_OCXM_PWM_MODE_2: u32 = 0b0111 << 4;
+const CCMR_OCXM_RETRIGGERABLE_OPM_2: u32 = 1 << 16 | 0b001 << 4;
+const CCMR_CCXS_TI_SAME: u32 = 0b01;
+const CCMR_ICXF_SHIFT: u32 = 4;
+const CCMR_MASK: u32 = 1 << 16 | 0xFF;
+
+// CCER fields, relative to the channel
+const CCER_CCXE: u32 = 1 << 0;
+const CCER_CCXP: u32 = 1 << 1;
+
+// SMCR
+const SMCR_SMS_TRIGGER: u32 = 0b110;
+const SMCR_SMS_COMBINED_RESET_TRIGGER: u32 = 1 << 16;
+const SMCR_TS_SHIFT: u32 = 4;
+
+impl<TIM, CHANNEL> OnePulseBuilder<TIM, CHANNEL> {
+    /// Set the delay between the trigger and the start of the pulse
+    pub fn delay<T: Into<NanoSeconds>>(mut self, delay: T) -> Self {
+        self.delay = delay.into();
+        self
+    }
+
+    /// Set the width of the pulse
+    pub fn width<T: Into<NanoSeconds>>(mut self, width: T) -> Self {
+        self.width = width.into();
+        self
+    }
+
+    /// Set the polarity of the pulse. The output is at the opposite level
+    /// outside the pulse
+    pub fn polarity(mut self, polarity: Polarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    /// Restart the delay on each trigger, including triggers that occur
+    /// during a pulse. Requires an external trigger
+    pub fn retriggerable(mut self) -> Self {
+        self.retriggerable = true;
+        self
+    }
+}
+
+macro_rules! one_pulse_hal {
+    ($($TIMX:ident: ($Rec:ident, $typ:ty $(, BDTR: $bdtr:ident)*),)+) => {
+        $(
+            impl OnePulseExt for $TIMX {
+                type Rec = rec::$Rec;
+
+                fn one_pulse<PIN, CHANNEL, COMP>(
+                    self,
+                    _pin: PIN,
+                    prec: Self::Rec,
+                    clocks: &CoreClocks,
+                ) -> OnePulseBuilder<Self, CHANNEL>
+                where
+                    PIN: Pins<Self, CHANNEL, COMP>,
+                {
+                    prec.enable().reset();
+
+                    let clk = $TIMX::get_clk(clocks)
+                        .expect("Timer input clock not running!")
+                        .0;
+
+                    OnePulseBuilder {
+                        tim: self,
+                        _channel: PhantomData,
+                        clk,
+                        delay: NanoSeconds(0),
+                        width: NanoSeconds(0),
+                        polarity: Polarity::ActiveHigh,
+                        retriggerable: false,
+                        trigger: PulseTrigger::Software,
+                    }
+                }
+            }
+
+            impl<CHANNEL> OnePulseBuilder<$TIMX, CHANNEL> {
+                /// Start the pulse on the rising edge of the trigger output
+                /// of another timer, or another trigger input of this
+                /// timer's slave mode controller
+                pub fn trigger<T>(mut self, _trigger: T) -> Self
+                where
+                    T: TriggerInput<$TIMX>,
+                {
+                    self.trigger = PulseTrigger::Internal(T::TS);
+                    self
+                }
+
+                /// Start the pulse on an edge on the channel 1 or channel 2
+                /// input of this timer. The channel cannot also be used as
+                /// the output
+                pub fn trigger_pin<PIN, TCH, COMP>(mut self, _pin: PIN, edge: CaptureEdge) -> Self
+                where
+                    PIN: Pins<$TIMX, TCH, COMP>,
+                    TCH: TriggerChannel,
+                {
+                    self.trigger = PulseTrigger::Pin(TCH::INDEX, edge);
+                    self
+                }
+            }
+
+            impl<CHANNEL> OnePulse<$TIMX, CHANNEL> {
+                /// Starts a pulse, without waiting for a trigger
+                pub fn start(&mut self) {
+                    self.tim.cr1.modify(|_, w| w.cen().set_bit());
+                }
+
+                /// Returns true if a pulse is ongoing, or the delay before it
+                pub fn is_busy(&self) -> bool {
+                    self.tim.cr1.read().cen().bit_is_set()
+                }
+
+                /// Releases the TIM peripheral
+                pub fn free(self) -> $TIMX {
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.tim
+                }
+            }
+
+            impl<CHANNEL> OnePulse<$TIMX, CHANNEL>
+            where
+                Self: OutputChannel,
+            {
+                /// Changes the delay and width of the pulse. Any ongoing
+                /// pulse is cut short
+                ///
+                /// Panics if the timing is not possible with this timer
+                pub fn set_timing<D, W>(&mut self, delay: D, width: W)
+                where
+                    D: Into<NanoSeconds>,
+                    W: Into<NanoSeconds>,
+                {
+                    let (psc, ccr, arr) = calculate_pulse(
+                        self.clk, delay.into(), width.into(), <$typ>::MAX as u32)
+                        .expect("Pulse delay and width are too long for this timer");
+
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.tim.psc.write(|w| w.psc().bits(psc));
+                    self.tim.arr.write(|w| unsafe { w.bits(arr) });
+                    self.set_compare(ccr);
+
+                    // Generate an update event to load the prescaler
+                    self.tim.egr.write(|w| w.ug().set_bit());
+                }
+            }
+
+            impl<CHANNEL> OnePulseBuilder<$TIMX, CHANNEL>
+            where
+                OnePulse<$TIMX, CHANNEL>: OutputChannel,
+            {
+                /// Configures the timer. The output is disabled until
+                /// `enable` is called
+                ///
+                /// Panics if the delay and width are not possible with this
+                /// timer
+                pub fn finalize(self) -> OnePulse<$TIMX, CHANNEL> {
+                    let (psc, ccr, arr) = calculate_pulse(
+                        self.clk, self.delay, self.width, <$typ>::MAX as u32)
+                        .expect("Pulse delay and width are too long for this timer");
+
+                    let tim = self.tim;
+                    tim.psc.write(|w| w.psc().bits(psc));
+                    tim.arr.write(|w| unsafe { w.bits(arr) });
+
+                    let mut pulse = OnePulse { tim, _channel: PhantomData, clk: self.clk };
+                    pulse.set_compare(ccr);
+
+                    // Output compare mode
+                    let ocxm = match (self.retriggerable, self.trigger) {
+                        (false, _) => CCMR_OCXM_PWM_MODE_2,
+                        (true, PulseTrigger::Software) =>
+                            panic!("A retriggerable pulse requires a trigger"),
+                        (true, _) => CCMR_OCXM_RETRIGGERABLE_OPM_2,
+                    };
+                    pulse.set_output_mode(ocxm, self.polarity);
+
+                    // Trigger input
+                    let ts = match self.trigger {
+                        PulseTrigger::Internal(ts) => Some(ts),
+                        PulseTrigger::Pin(index, edge) => {
+                            assert!(index != pulse.index(),
+                                    "The output channel cannot also be the trigger input");
+
+                            // Configure as input, with a short filter
+                            let shift = 8 * u32::from(index - 1);
+                            let ccmr = CCMR_CCXS_TI_SAME | 0b0011 << CCMR_ICXF_SHIFT;
+                            pulse.tim.ccmr1_output().modify(|r, w| unsafe {
+                                w.bits((r.bits() & !(CCMR_MASK << shift)) | ccmr << shift)
+                            });
+
+                            // Edge selection
+                            let shift = 4 * u32::from(index - 1);
+                            let ccer = edge.ccer_bits() | CCER_CCXE;
+                            pulse.tim.ccer.modify(|r, w| unsafe {
+                                w.bits((r.bits() & !(0xF << shift)) | ccer << shift)
+                            });
+
+                            // TI1FP1 or TI2FP2
+                            Some(0b100 + index)
+                        }
+                        _ => None,
+                    };
+
+                    if let Some(ts) = ts {
+                        let sms = if self.retriggerable {
+                            SMCR_SMS_COMBINED_RESET_TRIGGER
+                        } else {
+                            SMCR_SMS_TRIGGER
+                        };
+                        pulse.tim.smcr.write(|w| unsafe { w.bits(u32::from(ts) << SMCR_TS_SHIFT) });
+                        pulse.tim.smcr.modify(|r, w| unsafe { w.bits(r.bits() | sms) });
+                    }
+
+                    // Main output enable: Advanced-control timers
+                    $(
+                        pulse.tim.$bdtr.modify(|_, w| w.moe().set_bit());
+                    )*
+
+                    // One-pulse mode, counter stops at the update event.
+                    // Generate an update event to load the prescaler, without
+                    // setting the update flag
+                    pulse.tim.cr1.modify(|_, w| w.opm().set_bit().urs().set_bit());
+                    pulse.tim.egr.write(|w| w.ug().set_bit());
+
+                    pulse
+                }
+            }
+        )+
+    }
+}
+
+pub(crate) mod sealed {
+    use crate::pwm::Polarity;
+
+    /// Channel specific one-pulse functions
+    pub trait OutputChannel {
+        /// Channel number
+        fn index(&self) -> u8;
+
+        /// Write the CCRx register
+        fn set_compare(&mut self, ccr: u32);
+
+        /// Write the OCxM and CCxP fields
+        fn set_output_mode(&mut self, ocxm: u32, polarity: Polarity);
+    }
+}
+
+macro_rules! one_pulse_channel_hal {
+    ($($TIMX:ident: ($CH:ty, $index:expr, $ccmrx_output:ident, $ccrx:ident),)+) => {
+        $(
+            impl OnePulse<$TIMX, $CH> {
+                /// Enables the output
+                pub fn enable(&mut self) {
+                    self.tim.ccer.modify(|r, w| unsafe {
+                        w.bits(r.bits() | CCER_CCXE << (4 * ($index - 1)))
+                    });
+                }
+
+                /// Disables the output
+                pub fn disable(&mut self) {
+                    self.tim.ccer.modify(|r, w| unsafe {
+                        w.bits(r.bits() & !(CCER_CCXE << (4 * ($index - 1))))
+                    });
+                }
+            }
+
+            impl OutputChannel for OnePulse<$TIMX, $CH> {
+                fn index(&self) -> u8 {
+                    $index
+                }
+
+                fn set_compare(&mut self, ccr: u32) {
+                    self.tim.$ccrx.write(|w| unsafe { w.bits(ccr) });
+                }
+
+                fn set_output_mode(&mut self, ocxm: u32, polarity: Polarity) {
+                    let shift = 8 * (($index - 1) % 2);
+                    self.tim.$ccmrx_output().modify(|r, w| unsafe {
+                        w.bits((r.bits() & !(CCMR_MASK << shift)) | ocxm << shift)
+                    });
+
+                    let ccxp = match polarity {
+                        Polarity::ActiveHigh => 0,
+                        Polarity::ActiveLow => CCER_CCXP,
+                    };
+                    let shift = 4 * ($index - 1);
+                    self.tim.ccer.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !(0xF << shift)) | ccxp << shift)
+                    });
+                }
+            }
+        )+
+    }
+}
+
+one_pulse_hal! {
+    TIM1: (Tim1, u16, BDTR: bdtr),
+    TIM2: (Tim2, u32),
+    TIM3: (Tim3, u16),
+    TIM4: (Tim4, u16),
+    TIM5: (Tim5, u32),
+    TIM8: (Tim8, u16, BDTR: bdtr),
+    TIM12: (Tim12, u16),
+    TIM15: (Tim15, u16, BDTR: bdtr),
+}
+
+one_pulse_channel_hal! {
+    TIM1: (C1, 1, ccmr1_output, ccr1),
+    TIM1: (C2, 2, ccmr1_output, ccr2),
+    TIM1: (C3, 3, ccmr2_output, ccr3),
+    TIM1: (C4, 4, ccmr2_output, ccr4),
+    TIM2: (C1, 1, ccmr1_output, ccr1),
+    TIM2: (C2, 2, ccmr1_output, ccr2),
+    TIM2: (C3, 3, ccmr2_output, ccr3),
+    TIM2: (C4, 4, ccmr2_output, ccr4),
+    TIM3: (C1, 1, ccmr1_output, ccr1),
+    TIM3: (C2, 2, ccmr1_output, ccr2),
+    TIM3: (C3, 3, ccmr2_output, ccr3),
+    TIM3: (C4, 4, ccmr2_output, ccr4),
+    TIM4: (C1, 1, ccmr1_output, ccr1),
+    TIM4: (C2, 2, ccmr1_output, ccr2),
+    TIM4: (C3, 3, ccmr2_output, ccr3),
+    TIM4: (C4, 4, ccmr2_output, ccr4),
+    TIM5: (C1, 1, ccmr1_output, ccr1),
+    TIM5: (C2, 2, ccmr1_output, ccr2),
+    TIM5: (C3, 3, ccmr2_output, ccr3),
+    TIM5: (C4, 4, ccmr2_output, ccr4),
+    TIM8: (C1, 1, ccmr1_output, ccr1),
+    TIM8: (C2, 2, ccmr1_output, ccr2),
+    TIM8: (C3, 3, ccmr2_output, ccr3),
+    TIM8: (C4, 4, ccmr2_output, ccr4),
+    TIM12: (C1, 1, ccmr1_output, ccr1),
+    TIM12: (C2, 2, ccmr1_output, ccr2),
+    TIM15: (C1, 1, ccmr1_output, ccr1),
+    TIM15: (C2, 2, ccmr1_output, ccr2),
+}
+
+macro_rules! lptim_one_pulse_hal {
+    ($($TIMX:ident: $Rec:ident,)+) => {
+        $(
+            impl OnePulseExt for $TIMX {
+                type Rec = rec::$Rec;
+
+                fn one_pulse<PIN, CHANNEL, COMP>(
+                    self,
+                    _pin: PIN,
+                    prec: Self::Rec,
+                    clocks: &CoreClocks,
+                ) -> OnePulseBuilder<Self, CHANNEL>
+                where
+                    PIN: Pins<Self, CHANNEL, COMP>,
+                {
+                    prec.enable().reset();
+
+                    let clk = $TIMX::get_clk(clocks)
+                        .expect("Timer input clock not running!")
+                        .0;
+
+                    OnePulseBuilder {
+                        tim: self,
+                        _channel: PhantomData,
+                        clk,
+                        delay: NanoSeconds(0),
+                        width: NanoSeconds(0),
+                        polarity: Polarity::ActiveHigh,
+                        retriggerable: false,
+                        trigger: PulseTrigger::Software,
+                    }
+                }
+            }
+
+            impl OnePulseBuilder<$TIMX, C1> {
+                /// Start the pulse on an edge of one of the external
+                /// trigger inputs
+                pub fn trigger(mut self, trigger: LpTrigger, edge: CaptureEdge) -> Self {
+                    self.trigger = PulseTrigger::LowPower(trigger, edge);
+                    self
+                }
+
+                /// Configures the timer. The output is disabled until
+                /// `enable` is called
+                ///
+                /// Panics if the delay and width are not possible with this
+                /// timer
+                pub fn finalize(self) -> OnePulse<$TIMX, C1> {
+                    let (presc, cmp, arr) = calculate_lp_pulse(self.clk, self.delay, self.width)
+                        .expect("Pulse delay and width are too long for this timer");
+
+                    let mut cfgr = u32::from(presc) << LPTIM_CFGR_PRESC_SHIFT;
+                    if self.polarity == Polarity::ActiveLow {
+                        cfgr |= LPTIM_CFGR_WAVPOL;
+                    }
+                    match self.trigger {
+                        PulseTrigger::LowPower(trigger, edge) => {
+                            cfgr |= (trigger as u32) << LPTIM_CFGR_TRIGSEL_SHIFT
+                                | lp_edge_bits(edge) << LPTIM_CFGR_TRIGEN_SHIFT;
+
+                            // Timeout mode: a trigger during a pulse restarts
+                            // the counter
+                            if self.retriggerable {
+                                cfgr |= LPTIM_CFGR_TIMOUT;
+                            }
+                        }
+                        _ => assert!(!self.retriggerable,
+                                     "A retriggerable pulse requires a trigger"),
+                    }
+
+                    // Write CFGR: LPTIM must be disabled
+                    self.tim.cr.write(|w| w.enable().disabled());
+                    self.tim.cfgr.write(|w| unsafe { w.bits(cfgr) });
+
+                    let mut pulse = OnePulse {
+                        tim: self.tim,
+                        _channel: PhantomData,
+                        clk: self.clk,
+                    };
+                    // The output is enabled with the timer
+                    pulse.write_timing(cmp, arr);
+
+                    pulse
+                }
+            }
+
+            impl OnePulse<$TIMX, C1> {
+                /// Starts a pulse, without waiting for a trigger. Must be
+                /// enabled
+                pub fn start(&mut self) {
+                    self.tim.cr.write(|w| w.sngstrt().set_bit().enable().enabled());
+                }
+
+                /// Enables the timer and its output. With an external
+                /// trigger, this arms the timer for the next trigger
+                pub fn enable(&mut self) {
+                    self.tim.cr.write(|w| w.enable().enabled());
+
+                    if self.tim.cfgr.read().bits() & (0b11 << LPTIM_CFGR_TRIGEN_SHIFT) != 0 {
+                        self.tim.cr.write(|w| w.sngstrt().set_bit().enable().enabled());
+                    }
+                }
+
+                /// Disables the timer and its output
+                pub fn disable(&mut self) {
+                    self.tim.cr.write(|w| w.enable().disabled());
+                }
+
+                /// Returns true if a pulse has ended since the last call
+                pub fn is_pulse_complete(&mut self) -> bool {
+                    if self.tim.isr.read().arrm().bit_is_set() {
+                        self.tim.icr.write(|w| w.arrmcf().clear());
+                        true
+                    } else {
+                        false
+                    }
+                }
+
+                /// Changes the delay and width of the pulse. The prescaler
+                /// cannot be changed, so the range is limited to that of the
+                /// timing passed to the builder
+                ///
+                /// Panics if the timing is not possible
+                pub fn set_timing<D, W>(&mut self, delay: D, width: W)
+                where
+                    D: Into<NanoSeconds>,
+                    W: Into<NanoSeconds>,
+                {
+                    let presc = (self.tim.cfgr.read().bits() >> LPTIM_CFGR_PRESC_SHIFT) & 0b111;
+
+                    // Calculate with the existing prescaler
+                    let (cmp, arr) = calculate_lp_pulse_presc(
+                        self.clk, delay.into(), width.into(), presc as u8
+                    ).expect("Pulse delay and width are too long for this timer");
+
+                    self.write_timing(cmp, arr);
+                }
+
+                /// Writes CMP and ARR
+                fn write_timing(&mut self, cmp: u16, arr: u16) {
+                    // LPTIM must be enabled
+                    let enabled = self.tim.cr.read().enable().bit_is_set();
+                    self.tim.cr.write(|w| w.enable().enabled());
+
+                    self.tim.arr.write(|w| w.arr().bits(arr));
+                    while !self.tim.isr.read().arrok().is_set() {}
+                    self.tim.icr.write(|w| w.arrokcf().clear());
+
+                    self.tim.cmp.write(|w| w.cmp().bits(cmp));
+                    while !self.tim.isr.read().cmpok().is_set() {}
+                    self.tim.icr.write(|w| w.cmpokcf().clear());
+
+                    if !enabled {
+                        self.tim.cr.write(|w| w.enable().disabled());
+                    }
+                }
+
+                /// Releases the LPTIM peripheral
+                pub fn free(self) -> $TIMX {
+                    self.tim.cr.write(|w| w.enable().disabled());
+                    self.tim
+                }
+            }
+        )+
+    }
+}
+
+lptim_one_pulse_hal! {
+    LPTIM1: Lptim1,
+    LPTIM2: Lptim2,
+    LPTIM3: Lptim3,
+}
+#[cfg(not(feature = "rm0455"))]
+lptim_one_pulse_hal! {
+    LPTIM4: Lptim4,
+    LPTIM5: Lptim5,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pulse() {
+        let ns = NanoSeconds;
+
+        // 200MHz: 10µs delay, 2µs width
+        assert_eq!(
+            calculate_pulse(200_000_000, ns(10_000), ns(2_000), 0xFFFF),
+            Some((0, 2000, 2399))
+        );
+        // 16-bit counter needs a prescaler for 1ms + 1ms
+        assert_eq!(
+            calculate_pulse(200_000_000, ns(1_000_000), ns(1_000_000), 0xFFFF),
+            Some((6, 28571, 57141))
+        );
+        // 32-bit counter doesn't
+        assert_eq!(
+            calculate_pulse(
+                200_000_000,
+                ns(1_000_000),
+                ns(1_000_000),
+                0xFFFF_FFFF
+            ),
+            Some((0, 200_000, 399_999))
+        );
+        // At least one tick
+        assert_eq!(
+            calculate_pulse(200_000_000, ns(0), ns(0), 0xFFFF),
+            Some((0, 1, 1))
+        );
+        // Too long
+        assert_eq!(
+            calculate_pulse(200_000_000, ns(1_000_000_000), ns(1), 0xFF),
+            None
+        );
+    }
+
+    #[test]
+    fn lp_pulse() {
+        let ns = NanoSeconds;
+
+        // 100MHz: 100µs delay, 50µs width
+        assert_eq!(
+            calculate_lp_pulse(100_000_000, ns(100_000), ns(50_000)),
+            Some((0, 9999, 14999))
+        );
+        // 1ms + 1ms needs a prescaler of 4
+        assert_eq!(
+            calculate_lp_pulse(100_000_000, ns(1_000_000), ns(1_000_000)),
+            Some((2, 24999, 49999))
+        );
+        // Prescaler is at most 128
+        assert_eq!(
+            calculate_lp_pulse(100_000_000, ns(100_000_000), ns(1)),
+            None
+        );
+    }
+
+    #[test]
+    fn lp_pulse_ticks() {
+        let ns = NanoSeconds;
+
+        // The delay is CMP + 1 and the width is ARR - CMP, in prescaled ticks
+        for &(delay, width) in
+            [(10, 10), (100_000, 50_000), (20, 1_000_000)].iter()
+        {
+            let (presc, cmp, arr) =
+                calculate_lp_pulse(100_000_000, ns(delay), ns(width)).unwrap();
+            let tick = 10 << presc; // ns
+            assert_eq!(u32::from(cmp) + 1, delay / tick);
+            assert_eq!(u32::from(arr - cmp), width / tick);
+        }
+
+        // At least one tick
+        assert_eq!(
+            calculate_lp_pulse(100_000_000, ns(0), ns(0)),
+            Some((0, 0, 1))
+        );
+    }
+
+    #[test]
+    fn lp_pulse_same_prescaler() {
+        let ns = NanoSeconds;
+
+        // Ticks are rounded at the kernel clock, not the prescaled clock
+        assert_eq!(
+            calculate_lp_pulse(100_000_000, ns(1_310_670), ns(30)),
+            Some((1, 65532, 65533))
+        );
+
+        // Recalculating with the selected prescaler gives the same values
+        for delay in 1_310_000..1_311_000 {
+            for width in 0..100 {
+                let (delay, width) = (ns(delay), ns(width));
+                let (presc, cmp, arr) =
+                    calculate_lp_pulse(100_000_000, delay, width).unwrap();
+                assert_eq!(
+                    calculate_lp_pulse_presc(100_000_000, delay, width, presc),
+                    Some((cmp, arr))
+                );
+            }
+        }
+    }
+}
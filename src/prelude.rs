@@ -2,16 +2,26 @@
 pub use embedded_hal::prelude::*;
 
 pub use crate::adc::AdcExt as _stm32h7xx_hal_adc_AdcExt;
+pub use crate::capture::CaptureExt as _stm32h7xx_hal_capture_CaptureExt;
+pub use crate::capture::PwmInputExt as _stm32h7xx_hal_capture_PwmInputExt;
 #[cfg(feature = "crc")]
 pub use crate::crc::CrcExt as _stm32h7xx_hal_crc_CrcExt;
 pub use crate::dac::DacExt as _stm32h7xx_hal_dac_DacExt;
 pub use crate::delay::DelayExt as _stm32h7xx_hal_delay_DelayExt;
 pub use crate::exti::ExtiExt as _stm32h7xx_hal_delay_ExtiExt;
+#[cfg(feature = "can")]
+pub use crate::fdcan::FdcanExt as _stm32h7xx_hal_fdcan_FdcanExt;
 pub use crate::flash::FlashExt as _stm32h7xx_hal_flash_FlashExt;
 #[cfg(feature = "fmc")]
 pub use crate::fmc::FmcExt as _stm32h7xx_hal_fmc_FmcExt;
 pub use crate::gpio::GpioExt as _stm32h7xx_hal_gpio_GpioExt;
+#[cfg(not(feature = "rm0455"))]
+pub use crate::hrtim::HrtimExt as _stm32h7xx_hal_hrtim_HrtimExt;
 pub use crate::i2c::I2cExt as _stm32h7xx_hal_i2c_I2cExt;
+pub use crate::i2c::I2cTargetExt as _stm32h7xx_hal_i2c_I2cTargetExt;
+#[cfg(feature = "rtic")]
+pub use crate::monotonic::MonoTimerExt as _stm32h7xx_hal_monotonic_MonoTimerExt;
+pub use crate::one_pulse::OnePulseExt as _stm32h7xx_hal_one_pulse_OnePulseExt;
 pub use crate::pwm::PwmAdvExt as _stm32_hal_pwm_PwmAdvExt;
 pub use crate::pwm::PwmExt as _stm32_hal_pwm_PwmExt;
 pub use crate::pwr::PwrExt as _stm32h7xx_hal_pwr_PwrExt;
@@ -26,4 +36,9 @@ pub use crate::sdmmc::SdmmcExt as _stm32h7xx_hal_sdmmc_SdmmcExt;
 pub use crate::serial::SerialExt as _stm32h7xx_hal_serial_SerialExt;
 pub use crate::spi::SpiExt as _stm32h7xx_hal_spi_SpiExt;
 pub use crate::time::U32Ext as _stm32h7xx_hal_time_U32Ext;
+pub use crate::timer::BasicMasterTimer as _stm32h7xx_hal_timer_BasicMasterTimer;
+pub use crate::timer::LpInputExt as _stm32h7xx_hal_timer_LpInputExt;
+pub use crate::timer::MasterTimer as _stm32h7xx_hal_timer_MasterTimer;
+pub use crate::timer::MasterTimer2 as _stm32h7xx_hal_timer_MasterTimer2;
+pub use crate::timer::SlaveTimer as _stm32h7xx_hal_timer_SlaveTimer;
 pub use crate::timer::TimerExt as _stm32h7xx_hal_timer_TimerExt;
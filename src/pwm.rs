@@ -111,7 +111,29 @@
 //!
 //! The fault state puts all PWM pins into high-impedance mode, so pull-ups or pull-downs should be used to set the pins to a safe state.
 //!
-//! Currently only one break input (BKIN or BKIN2) can be enabled, this could be changed to allow two break inputs at the same time.
+//! On `TIM1` and `TIM8`, both break inputs (BKIN and BKIN2) can be enabled by calling [with_break_pin](struct.PwmBuilder.html#method.with_break_pin) twice, each with its own polarity.
+//! Each break input has a digital filter, set by [with_break_filter](struct.PwmBuilder.html#method.with_break_filter) and [with_break2_filter](struct.PwmBuilder.html#method.with_break2_filter).
+//!
+//! By default the outputs stay disabled after a fault until [FaultMonitor::clear_fault](trait.FaultMonitor.html#tymethod.clear_fault) is called.
+//! With [automatic_output_enable](struct.PwmBuilder.html#method.automatic_output_enable) they are re-enabled at the next update event once the break inputs are inactive.
+//!
+//! ```
+//!   let (mut control, (c1, c2, c3)) = device.TIM1
+//!       .pwm_advanced(pins, prec, &clocks)
+//!       .frequency(20.khz())
+//!       .with_deadtime(500.ns())
+//!       .with_break_pin(gpioe.pe15.into_alternate_af1(), Polarity::ActiveLow)
+//!       .with_break_pin(gpioe.pe6.into_alternate_af1(), Polarity::ActiveHigh)
+//!       .with_break2_filter(CaptureFilter::DtsDiv8N8)
+//!       .off_state_idle_inactive()
+//!       .with_lock(LockLevel::Level1)
+//!       .finalize();
+//! ```
+//!
+//! ## Lock levels
+//!
+//! [with_lock](struct.PwmBuilder.html#method.with_lock) write-protects the break, dead time and off state configuration until the next reset, see [LockLevel](enum.LockLevel.html).
+//! The lock is applied by [finalize](struct.PwmBuilder.html#method.finalize), so no later software fault can change the protection settings.
 //!
 //! ## Complementary outputs
 //!
@@ -163,6 +185,45 @@
 //! Although the timers allow quite a bit of configuration here, that would require configuring the PWM pins before configuring other parts of the timer, which would be a challenge with how type states and traits are used for timer configuration.
 //!
 //! Additionally, the GPIO will always be high-impedance during power-up or in reset, so pull-ups or pull-downs to ensure safe state are always a good idea.
+//!
+//! ## Frequency changes
+//!
+//! The frequency, period and prescaler of timers configured through [pwm_advanced](trait.PwmAdvExt.html#tymethod.pwm_advanced) can be changed at runtime with [PwmControl::set_frequency](struct.PwmControl.html#method.set_frequency), [PwmControl::set_period](struct.PwmControl.html#method.set_period) and [PwmControl::set_prescaler](struct.PwmControl.html#method.set_prescaler).
+//! The period and duty cycles are preloaded, so changes take effect at the next update event without glitches. [PwmControl::apply_freq](struct.PwmControl.html#method.apply_freq) applies them immediately.
+//!
+//! ```
+//!   // Sweep from 20kHz to 40kHz, keeping the duty cycle at 50%
+//!   for khz in 20..40 {
+//!       control.set_frequency(khz.khz());
+//!       c1.set_duty(c1.get_max_duty() / 2);
+//!   }
+//! ```
+//!
+//! On `TIM1`, `TIM8` and `TIM15`-`TIM17` the repetition counter reduces the rate of update events, see [PwmControl::set_repetition_counter](struct.PwmControl.html#method.set_repetition_counter).
+//!
+//! ## DMA
+//!
+//! On `TIM1`-`TIM5`, `TIM8` and `TIM15`-`TIM17` the duty cycle of a PWM channel can be streamed from a buffer by the DMA.
+//! A [Pwm](struct.Pwm.html) channel implements `TargetAddress` for its capture/compare register, using the capture/compare DMA request of that channel.
+//! Call [Pwm::enable_dma](struct.Pwm.html#method.enable_dma) to enable the request, and [PwmControl::cc_dma_on_update](struct.PwmControl.html#method.cc_dma_on_update) to issue it once per PWM period rather than on each compare match.
+//!
+//! ```
+//!   control.cc_dma_on_update(true);
+//!
+//!   let mut transfer: Transfer<_, _, MemoryToPeripheral, _, _> =
+//!       Transfer::init(stream, c1, &mut buffer[..], None, config);
+//!
+//!   transfer.start(|c1| {
+//!       c1.enable_dma();
+//!       c1.enable();
+//!   });
+//! ```
+//!
+//! Several registers can be written on each update event with a DMA burst. Configure the burst with [PwmControl::dma_burst](struct.PwmControl.html#method.dma_burst), then wrap the [PwmControl](struct.PwmControl.html) in the [DMAR](../dma/dma/struct.DMAR.html) marker to use it as a DMA target.
+//! Each burst writes `transfers` consecutive registers starting at the base register, so a buffer of `[CCR1, CCR2, CCR3, CCR4]` groups updates all four channels each period.
+//! Similarly the [ARR](../dma/dma/struct.ARR.html) marker streams the period, for frequency modulation.
+//!
+//! `TIM12`-`TIM14` and the Low-power timers do not generate DMA requests.
 
 use core::marker::PhantomData;
 use core::mem::MaybeUninit;
@@ -178,6 +239,7 @@ use crate::stm32::{
     TIM8,
 };
 
+use crate::capture::CaptureFilter;
 use crate::rcc::{rec, CoreClocks, ResetEnable};
 use crate::time::{Hertz, NanoSeconds, U32Ext};
 use crate::timer::GetClk;
@@ -244,6 +306,7 @@ pub struct ComplementaryDisabled;
 pub struct ComplementaryEnabled;
 
 /// Enum for IO polarity
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Polarity {
     ActiveHigh,
     ActiveLow,
@@ -256,6 +319,21 @@ pub enum BreakInput {
     BreakIn2,
 }
 
+/// Lock level for the break, dead time and off state configuration of a timer.
+/// Once locked, the configuration cannot be changed until the next reset
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LockLevel {
+    /// No write protection
+    Off = 0b00,
+    /// Dead time, break inputs, break filters, break polarities, automatic
+    /// output enable and idle states are locked
+    Level1 = 0b01,
+    /// As level 1, plus the off state selection and the channel polarities
+    Level2 = 0b10,
+    /// As level 2, plus the output compare mode and preload of each channel
+    Level3 = 0b11,
+}
+
 /// Internal enum that keeps track of the count settings before PWM is finalized
 enum CountSettings<WIDTH> {
     Frequency(Hertz),
@@ -275,6 +353,26 @@ pub enum Alignment {
     Center,
 }
 
+/// The first register written by a DMA burst, see
+/// [PwmControl::dma_burst](struct.PwmControl.html#method.dma_burst)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DmaBurstBase {
+    /// Prescaler
+    Psc = 10,
+    /// Auto-reload register (period)
+    Arr = 11,
+    /// Repetition counter. Only on `TIM1`, `TIM8` and `TIM15`-`TIM17`
+    Rcr = 12,
+    /// Capture/compare register 1
+    Ccr1 = 13,
+    /// Capture/compare register 2
+    Ccr2 = 14,
+    /// Capture/compare register 3
+    Ccr3 = 15,
+    /// Capture/compare register 4
+    Ccr4 = 16,
+}
+
 /// Pwm represents one PWM channel; it is created by calling TIM?.pwm(...) and lets you control the channel through the PwmPin trait
 pub struct Pwm<TIM, CHANNEL, COMP, POL, NPOL> {
     _channel: PhantomData<CHANNEL>,
@@ -297,6 +395,13 @@ pub struct PwmBuilder<TIM, PINS, CHANNEL, FAULT, COMP, WIDTH> {
     bkin_enabled: bool, // If the FAULT type parameter is FaultEnabled, either bkin or bkin2 must be enabled
     bkin2_enabled: bool,
     fault_polarity: Polarity,
+    fault2_polarity: Polarity,
+    break_filter: CaptureFilter,
+    break2_filter: CaptureFilter,
+    lock: LockLevel,
+    off_state_run: bool,
+    off_state_idle: bool,
+    automatic_output: bool,
     deadtime: NanoSeconds,
 }
 
@@ -313,10 +418,12 @@ pub trait FaultMonitor {
 }
 
 /// Exposes timer wide advanced features, such as [FaultMonitor](trait.FaultMonitor.html)
-/// or future features like trigger outputs for synchronization with ADCs and other peripherals
+/// and trigger outputs for synchronization with ADCs and other peripherals
+/// ([MasterTimer](../timer/trait.MasterTimer.html), [SlaveTimer](../timer/trait.SlaveTimer.html))
 pub struct PwmControl<TIM, FAULT> {
     _tim: PhantomData<TIM>,
     _fault: PhantomData<FAULT>,
+    base_freq: Hertz,
 }
 
 /// Marker struct indicating that a PwmControl is in charge of fault monitoring
@@ -1016,6 +1123,22 @@ macro_rules! pwm_ext_hal {
     };
 }
 
+// Set the second break input fields of BDTR, for timers that have one
+macro_rules! bdtr_brk2 {
+    ($w:ident, $builder:expr;) => {
+        $w
+    };
+    ($w:ident, $builder:expr; $bk2inp_setting:ident) => {{
+        let bk2p = $builder.fault2_polarity == Polarity::ActiveHigh;
+        $w.bk2f()
+            .bits($builder.break2_filter as u8)
+            .bk2e()
+            .bit($builder.bkin2_enabled)
+            .bk2p()
+            .bit(bk2p)
+    }};
+}
+
 // Implement PWM configuration for timer
 macro_rules! tim_hal {
     ($($TIMX:ident: ($timX:ident, $Rec:ident,
@@ -1096,6 +1219,13 @@ macro_rules! tim_hal {
                         bkin_enabled: false,
                         bkin2_enabled: false,
                         fault_polarity: Polarity::ActiveLow,
+                        fault2_polarity: Polarity::ActiveLow,
+                        break_filter: CaptureFilter::CkIntN2,
+                        break2_filter: CaptureFilter::CkIntN2,
+                        lock: LockLevel::Off,
+                        off_state_run: false,
+                        off_state_idle: false,
+                        automatic_output: false,
                         deadtime: 0.ns(),
                     }
                 }
@@ -1135,20 +1265,7 @@ macro_rules! tim_hal {
                             _ => panic!("Should be unreachable, invalid deadtime prescaler"),
                         }
 
-                        let bkp = match self.fault_polarity {
-                            Polarity::ActiveLow => false,
-                            Polarity::ActiveHigh => true,
-                        };
-
                         if self.bkin_enabled {
-                            // BDTR:
-                            //  BKF = 1 -> break pin filtering of 2 cycles of CK_INT (peripheral source clock)
-                            //  AOE = 0 -> after a fault, master output enable MOE can only be set by software, not automatically
-                            //  BKE = 1 -> break is enabled
-                            //  BKP = 0 for active low, 1 for active high
-                            // Safety: bkf is set to a constant value (1) that is a valid value for the field per the reference manual
-                            unsafe { tim.$bdtr.write(|w| w.dtg().bits(dtg).bkf().bits(1).aoe().clear_bit().bke().set_bit().bkp().bit(bkp).moe().$moe_set()); }
-
                             // AF1:
                             //  BKINE = 1 -> break input enabled
                             //  BKINP should make input active high (BDTR BKP will set polarity), bit value varies timer to timer
@@ -1156,26 +1273,49 @@ macro_rules! tim_hal {
                         }
                         $(
                             // Not all timers that have break inputs have break2 inputs
-                            else if self.bkin2_enabled {
-                                // BDTR:
-                                //  BK2F = 1 -> break pin filtering of 2 cycles of CK_INT (peripheral source clock)
-                                //  AOE = 0 -> after a fault, master output enable MOE can only be set by software, not automatically
-                                //  BK2E = 1 -> break is enabled
-                                //  BK2P = 0 for active low, 1 for active high
-                                // Safety: bkf is set to a constant value (1) that is a valid value for the field per the reference manual
-                                unsafe { tim.$bdtr.write(|w| w.dtg().bits(dtg).bk2f().bits(1).aoe().clear_bit().bk2e().set_bit().bk2p().bit(bkp).moe().$moe_set()); }
-
-                                // AF1:
-                                //  BKINE = 1 -> break input enabled
-                                //  BKINP should make input active high (BDTR BKP will set polarity), bit value varies timer to timer
+                            if self.bkin2_enabled {
+                                // AF2:
+                                //  BK2INE = 1 -> break input 2 enabled
+                                //  BK2INP should make input active high (BDTR BK2P will set polarity), bit value varies timer to timer
                                 tim.af2.write(|w| w.bk2ine().set_bit().bk2inp().$bk2inp_setting());
                             }
                         )*
-                        else {
-                            // Safety: the DTG field of BDTR allows any 8-bit deadtime value and the dtg variable is u8
-                            unsafe {
-                                tim.$bdtr.write(|w| w.dtg().bits(dtg).aoe().clear_bit().moe().$moe_set());
-                            }
+
+                        // Level 3 locks the output compare mode, so it must be
+                        // set before the lock takes effect
+                        if self.lock == LockLevel::Level3 {
+                            PwmControl::<$TIMX, FAULT>::preset_output_modes();
+                        }
+
+                        let bkp = self.fault_polarity == Polarity::ActiveHigh;
+
+                        // BDTR: the LOCK bits are frozen by the first write after reset, so
+                        // everything is written at once
+                        //  DTG = dead time
+                        //  LOCK = lock level
+                        //  OSSR/OSSI = 1 -> disabled outputs are driven to their inactive level rather than released
+                        //  BKF = break filter
+                        //  BKE = 1 -> break is enabled
+                        //  BKP = 0 for active low, 1 for active high
+                        //  AOE = 0 -> after a fault, master output enable MOE can only be set by software
+                        //  AOE = 1 -> MOE is also set automatically at the next update event once the break input is inactive
+                        //  BK2F, BK2E, BK2P -> the same for the second break input
+                        // Safety: the DTG field of BDTR allows any 8-bit deadtime value and the dtg variable is u8.
+                        // The lock level and break filters are enums covering all valid values of their fields
+                        unsafe {
+                            tim.$bdtr.write(|w| {
+                                let w = w
+                                    .dtg().bits(dtg)
+                                    .lock().bits(self.lock as u8)
+                                    .ossr().bit(self.off_state_run)
+                                    .ossi().bit(self.off_state_idle)
+                                    .bkf().bits(self.break_filter as u8)
+                                    .bke().bit(self.bkin_enabled)
+                                    .bkp().bit(bkp)
+                                    .aoe().bit(self.automatic_output);
+                                let w = bdtr_brk2!(w, self; $($bk2inp_setting)*);
+                                w.moe().$moe_set()
+                            });
                         }
 
                         // BDTR: Advanced-control timers
@@ -1193,12 +1333,19 @@ macro_rules! tim_hal {
                         }
                     )*
 
+                    // Preload the period, so that it can be changed at runtime
+                    // without glitches
+                    tim.cr1.modify(|_, w| w.arpe().set_bit());
+
                     tim.cr1.modify(|_, w| w.cen().enabled());
 
-                    unsafe {
-                        MaybeUninit::<(PwmControl<$TIMX, FAULT>, PINS::Channel)>::uninit()
-                            .assume_init()
-                    }
+                    let control = PwmControl {
+                        _tim: PhantomData,
+                        _fault: PhantomData,
+                        base_freq: self.base_freq,
+                    };
+
+                    (control, unsafe { MaybeUninit::<PINS::Channel>::uninit().assume_init() })
                 }
 
                 /// Set the PWM frequency; will overwrite the previous prescaler and period
@@ -1274,12 +1421,137 @@ macro_rules! tim_hal {
                 )*
             }
 
+            impl<FAULT> PwmControl<$TIMX, FAULT> {
+                /// Set the PWM frequency; will overwrite the previous prescaler and period
+                ///
+                /// The requested frequency will be rounded to the nearest achievable frequency.
+                /// The new frequency takes effect at the next update event, so the current PWM period completes undisturbed.
+                /// Duty cycles are not rescaled, so should be updated relative to the new [get_max_duty](struct.Pwm.html#method.get_max_duty).
+                pub fn set_frequency<T: Into<Hertz>>(&mut self, freq: T) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    let (period, prescaler) = match $bits {
+                        16 => calculate_frequency_16bit(self.base_freq, freq.into(), self.alignment()),
+                        _ => calculate_frequency_32bit(self.base_freq, freq.into(), self.alignment()),
+                    };
+
+                    tim.psc.write(|w| w.psc().bits(prescaler));
+                    tim.arr.write(|w| w.arr().bits(period as $typ));
+                }
+
+                /// Returns the current PWM frequency
+                pub fn get_frequency(&self) -> Hertz {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    let prescaler = tim.psc.read().psc().bits() as u64 + 1;
+                    let period = tim.arr.read().arr().bits() as u64 + 1;
+                    let counts = match self.alignment() {
+                        Alignment::Center => 2 * prescaler * period,
+                        _ => prescaler * period,
+                    };
+
+                    Hertz((self.base_freq.0 as u64 / counts) as u32)
+                }
+
+                /// Set the period; PWM count runs from 0 to period, repeating every (period+1) counts
+                ///
+                /// Takes effect at the next update event
+                pub fn set_period(&mut self, period: $typ) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.arr.write(|w| w.arr().bits(period));
+                }
+
+                /// Returns the current period
+                pub fn get_period(&self) -> $typ {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.arr.read().arr().bits()
+                }
+
+                /// Set the prescaler; PWM count runs at base_frequency/(prescaler+1)
+                ///
+                /// Takes effect at the next update event
+                pub fn set_prescaler(&mut self, prescaler: u16) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.psc.write(|w| w.psc().bits(prescaler));
+                }
+
+                /// Returns the current prescaler
+                pub fn get_prescaler(&self) -> u16 {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.psc.read().psc().bits()
+                }
+
+                /// Applies frequency/period changes immediately
+                ///
+                /// Generates an update event, which loads the preloaded prescaler, period and duty cycles.
+                /// This restarts the current PWM period and clears the counter.
+                pub fn apply_freq(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.egr.write(|w| w.ug().set_bit());
+                }
+
+                /// Disable update events
+                ///
+                /// The preloaded prescaler, period and duty cycles are not loaded until update events are enabled again.
+                /// This can be used to change several of them together, without an update event occurring in between.
+                pub fn disable_update_event(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.cr1.modify(|_, w| w.udis().set_bit());
+                }
+
+                /// Enable update events. They are enabled by default
+                pub fn enable_update_event(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.cr1.modify(|_, w| w.udis().clear_bit());
+                }
+
+                /// Set Update Request Source to counter overflow/underflow only
+                ///
+                /// Update events generated by [apply_freq](#method.apply_freq) or by a slave mode controller then do not raise an update interrupt or DMA request
+                pub fn urs_counter_only(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.cr1.modify(|_, w| w.urs().set_bit());
+                }
+
+                /// Set Update Request Source to any update event. This is the default
+                pub fn urs_any_event(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.cr1.modify(|_, w| w.urs().clear_bit());
+                }
+
+                // CR1.CMS is reserved (reads zero) on timers without center aligned PWM
+                fn alignment(&self) -> Alignment {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    match (tim.cr1.read().bits() >> 5) & 0b11 {
+                        0 => Alignment::Left,
+                        _ => Alignment::Center,
+                    }
+                }
+            }
+
             // Timers with break/fault, dead time, and complimentary capabilities
             $(
-                impl<PINS, CHANNEL, COMP> PwmBuilder<$TIMX, PINS, CHANNEL, FaultDisabled, COMP, $typ> {
+                impl<PINS, CHANNEL, FAULT, COMP> PwmBuilder<$TIMX, PINS, CHANNEL, FAULT, COMP, $typ> {
                     /// Configure a break pin that will disable PWM when activated (active level based on polarity argument)
+                    ///
+                    /// On timers with a second break input, this can be called twice to enable both break inputs, each with its own polarity.
                     /// Note: not all timers have fault inputs; FaultPins<TIM> is only implemented for valid pins/timers.
                     pub fn with_break_pin<P: FaultPins<$TIMX>>(self, _pin: P, polarity: Polarity) -> PwmBuilder<$TIMX, PINS, CHANNEL, FaultEnabled, COMP, $typ> {
+                        let (fault_polarity, fault2_polarity) = match P::INPUT {
+                            BreakInput::BreakIn => (polarity, self.fault2_polarity),
+                            BreakInput::BreakIn2 => (self.fault_polarity, polarity),
+                        };
+
                         PwmBuilder {
                             _tim: PhantomData,
                             _pins: PhantomData,
@@ -1291,10 +1563,56 @@ macro_rules! tim_hal {
                             count: self.count,
                             bkin_enabled: self.bkin_enabled || P::INPUT == BreakInput::BreakIn,
                             bkin2_enabled: self.bkin2_enabled || P::INPUT == BreakInput::BreakIn2,
-                            fault_polarity: polarity,
+                            fault_polarity,
+                            fault2_polarity,
+                            break_filter: self.break_filter,
+                            break2_filter: self.break2_filter,
+                            lock: self.lock,
+                            off_state_run: self.off_state_run,
+                            off_state_idle: self.off_state_idle,
+                            automatic_output: self.automatic_output,
                             deadtime: self.deadtime,
                         }
                     }
+
+                    /// Set the digital filter of the break input. The default samples at f<sub>CK_INT</sub>, N = 2
+                    pub fn with_break_filter(mut self, filter: CaptureFilter) -> Self {
+                        self.break_filter = filter;
+
+                        self
+                    }
+
+                    /// Protect the break, dead time and off state configuration against further changes, until the next reset
+                    ///
+                    /// Level 2 also locks the channel polarities, so [into_active_low](struct.Pwm.html#method.into_active_low) and similar methods have no effect after finalize.
+                    /// Level 3 also locks the output compare mode, which is set to PWM mode 1 for all channels on finalize.
+                    pub fn with_lock(mut self, level: LockLevel) -> Self {
+                        self.lock = level;
+
+                        self
+                    }
+
+                    /// Drive enabled complementary channels to their inactive level when the channel is disabled, rather than releasing them (OSSR)
+                    pub fn off_state_run_inactive(mut self) -> Self {
+                        self.off_state_run = true;
+
+                        self
+                    }
+
+                    /// Drive channels to their inactive level while a fault is active, rather than releasing them (OSSI)
+                    pub fn off_state_idle_inactive(mut self) -> Self {
+                        self.off_state_idle = true;
+
+                        self
+                    }
+
+                    /// After a fault, re-enable the outputs automatically at the next update event once the break input is inactive (AOE).
+                    /// By default outputs are only re-enabled by [clear_fault](trait.FaultMonitor.html#tymethod.clear_fault)
+                    pub fn automatic_output_enable(mut self) -> Self {
+                        self.automatic_output = true;
+
+                        self
+                    }
                 }
 
                 impl FaultMonitor for PwmControl<$TIMX, FaultEnabled> {
@@ -1611,6 +1929,176 @@ tim_pin_hal! {
     TIM8: (C4, cc4e, cc4p, ccmr2_output, oc4pe, oc4m, ccr4, u16),
 }
 
+// Second break input
+macro_rules! tim_brk2_hal {
+    ($($TIMX:ident: $typ:ty,)+) => {
+        $(
+            impl<PINS, CHANNEL, FAULT, COMP> PwmBuilder<$TIMX, PINS, CHANNEL, FAULT, COMP, $typ> {
+                /// Set the digital filter of the second break input. The
+                /// default samples at f<sub>CK_INT</sub>, N = 2
+                pub fn with_break2_filter(mut self, filter: CaptureFilter) -> Self {
+                    self.break2_filter = filter;
+
+                    self
+                }
+            }
+        )+
+    };
+}
+
+tim_brk2_hal! {
+    TIM1: u16,
+    TIM8: u16,
+}
+
+// Output compare mode of all channels, for lock level 3
+macro_rules! tim_lock_hal {
+    ($($TIMX:ident: [$($ccmrx_output:ident: ($ocxpe:ident, $ocxm:ident)),+],)+) => {
+        $(
+            impl<FAULT> PwmControl<$TIMX, FAULT> {
+                /// Set PWM mode 1 with preload on all channels, as
+                /// [enable](struct.Pwm.html#method.enable) would
+                fn preset_output_modes() {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    $(
+                        tim.$ccmrx_output().modify(|_, w|
+                            w.$ocxpe()
+                                .enabled() // Enable preload
+                                .$ocxm()
+                                .pwm_mode1() // PWM Mode
+                        );
+                    )+
+                }
+            }
+        )+
+    };
+}
+
+tim_lock_hal! {
+    TIM1: [ccmr1_output: (oc1pe, oc1m), ccmr1_output: (oc2pe, oc2m),
+           ccmr2_output: (oc3pe, oc3m), ccmr2_output: (oc4pe, oc4m)],
+    TIM8: [ccmr1_output: (oc1pe, oc1m), ccmr1_output: (oc2pe, oc2m),
+           ccmr2_output: (oc3pe, oc3m), ccmr2_output: (oc4pe, oc4m)],
+    TIM15: [ccmr1_output: (oc1pe, oc1m), ccmr1_output: (oc2pe, oc2m)],
+    TIM16: [ccmr1_output: (oc1pe, oc1m)],
+    TIM17: [ccmr1_output: (oc1pe, oc1m)],
+}
+
+// Repetition counter
+macro_rules! tim_rcr_hal {
+    ($($TIMX:ident: $typ:ty,)+) => {
+        $(
+            impl<FAULT> PwmControl<$TIMX, FAULT> {
+                /// Set the repetition counter. An update event is generated
+                /// every `repetitions + 1` PWM periods, or every
+                /// `repetitions + 1` half periods with center aligned PWM
+                ///
+                /// The preloaded prescaler, period and duty cycles are only
+                /// loaded on update events, as are the update interrupt and
+                /// DMA request. Takes effect at the next update event
+                pub fn set_repetition_counter(&mut self, repetitions: $typ) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.rcr.write(|w| unsafe { w.bits(repetitions as u32) });
+                }
+            }
+        )+
+    };
+}
+
+tim_rcr_hal! {
+    TIM1: u16,
+    TIM8: u16,
+    TIM15: u8,
+    TIM16: u8,
+    TIM17: u8,
+}
+
+// DMA requests
+macro_rules! tim_dma_hal {
+    ($($TIMX:ident: [$($CH:ident: $ccxde:ident),+],)+) => {
+        $(
+            impl<FAULT> PwmControl<$TIMX, FAULT> {
+                /// Enables the DMA request generated on each update event.
+                /// Used with the [DMAR](../dma/dma/struct.DMAR.html) and
+                /// [ARR](../dma/dma/struct.ARR.html) DMA targets
+                pub fn enable_update_dma(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.dier.modify(|_, w| w.ude().set_bit());
+                }
+
+                /// Disables the DMA request generated on each update event
+                pub fn disable_update_dma(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.dier.modify(|_, w| w.ude().clear_bit());
+                }
+
+                /// If `on_update` is true, the capture/compare DMA requests
+                /// of all channels are issued on the update event, once per
+                /// PWM period. Otherwise they are issued on each compare
+                /// match
+                pub fn cc_dma_on_update(&mut self, on_update: bool) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.cr2.modify(|_, w| w.ccds().bit(on_update));
+                }
+
+                /// Configures the DMA burst. On each update DMA request,
+                /// `transfers` consecutive registers starting at `base` are
+                /// written through the [DMAR](../dma/dma/struct.DMAR.html)
+                /// DMA target
+                ///
+                /// Panics if `transfers` is not in the range 1 - 18
+                pub fn dma_burst(&mut self, base: DmaBurstBase, transfers: u8) {
+                    assert!((1..=18).contains(&transfers));
+
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    // DBL: bits 12:8, DBA: bits 4:0
+                    tim.dcr.write(|w| unsafe {
+                        w.bits(((transfers as u32 - 1) << 8) | base as u32)
+                    });
+                }
+            }
+
+            $(
+                impl<COMP, POL, NPOL> Pwm<$TIMX, $CH, COMP, POL, NPOL> {
+                    /// Enables the capture/compare DMA request of this
+                    /// channel. The DMA then writes the duty cycle
+                    pub fn enable_dma(&mut self) {
+                        let tim = unsafe { &*$TIMX::ptr() };
+
+                        tim.dier.modify(|_, w| w.$ccxde().set_bit());
+                    }
+
+                    /// Disables the capture/compare DMA request of this
+                    /// channel
+                    pub fn disable_dma(&mut self) {
+                        let tim = unsafe { &*$TIMX::ptr() };
+
+                        tim.dier.modify(|_, w| w.$ccxde().clear_bit());
+                    }
+                }
+            )+
+        )+
+    };
+}
+
+tim_dma_hal! {
+    TIM1: [C1: cc1de, C2: cc2de, C3: cc3de, C4: cc4de],
+    TIM2: [C1: cc1de, C2: cc2de, C3: cc3de, C4: cc4de],
+    TIM3: [C1: cc1de, C2: cc2de, C3: cc3de, C4: cc4de],
+    TIM4: [C1: cc1de, C2: cc2de, C3: cc3de],
+    TIM5: [C1: cc1de, C2: cc2de, C3: cc3de, C4: cc4de],
+    TIM8: [C1: cc1de, C2: cc2de, C3: cc3de, C4: cc4de],
+    TIM15: [C1: cc1de],
+    TIM16: [C1: cc1de],
+    TIM17: [C1: cc1de],
+}
+
 // Low-power timers
 macro_rules! lptim_hal {
     ($($TIMX:ident: ($timX:ident, $Rec:ident, $timXpac:ident),)+) => {
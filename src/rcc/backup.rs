@@ -20,11 +20,34 @@
 //!     println!("RTC Enabled");
 //! }
 //! ```
+//!
+//! # LSE
+//!
+//! The LSE oscillator is also in the backup power domain. Enable it before
+//! the RCC is frozen, so that its frequency is recorded in
+//! [CoreClocks](crate::rcc::CoreClocks) and it can be used as a kernel
+//! clock.
+//!
+//! ```
+//! let mut backup = pwrcfg.backup().unwrap();
+//! backup.LSE.enable(LseDrive::MEDIUMLOW);
+//!
+//! let ccdr = rcc.freeze(pwrcfg, &dp.SYSCFG);
+//! assert!(ccdr.clocks.lse_ck().is_some());
+//! ```
+
+use crate::stm32::RCC;
+use core::marker::PhantomData;
+use cortex_m::interrupt;
+
+/// LSE oscillator frequency. See RM0433 Rev 7 Section 8.5.6
+pub const LSE: u32 = 32_768; // Hz
 
 /// Backup Power Domain Peripheral Reset and Enable Control
 #[allow(non_snake_case, missing_docs)]
 #[non_exhaustive]
 pub struct BackupREC {
+    pub LSE: Lse,
     #[cfg(feature = "rtc")]
     pub RTC: Rtc,
 }
@@ -38,6 +61,9 @@ impl BackupREC {
     /// protection is disabled.
     pub(crate) unsafe fn new_singleton() -> Self {
         Self {
+            LSE: Lse {
+                _marker: PhantomData,
+            },
             #[cfg(feature = "rtc")]
             RTC: Rtc {
                 _marker: core::marker::PhantomData,
@@ -46,6 +72,68 @@ impl BackupREC {
     }
 }
 
+/// LSE oscillator drive capability
+pub type LseDrive = crate::stm32::rcc::bdcr::LSEDRV_A;
+
+/// Control of the LSE oscillator
+pub struct Lse {
+    _marker: PhantomData<*const ()>,
+}
+
+unsafe impl Send for Lse {}
+
+impl Lse {
+    /// Enables the LSE oscillator with a 32.768kHz crystal, and waits for
+    /// it to be ready
+    ///
+    /// If the LSE is already running, for example after a reset that did
+    /// not reset the backup domain, it is not reconfigured.
+    pub fn enable(&mut self, drive: LseDrive) {
+        self.start(false, drive);
+    }
+
+    /// Enables the LSE in bypass mode, with an external 32.768kHz clock on
+    /// OSC32_IN, and waits for it to be ready
+    ///
+    /// If the LSE is already running, for example after a reset that did
+    /// not reset the backup domain, it is not reconfigured.
+    pub fn enable_bypass(&mut self) {
+        self.start(true, LseDrive::LOWEST);
+    }
+
+    /// Disables the LSE oscillator
+    pub fn disable(&mut self) {
+        // unsafe: Owned exclusive access to this bitfield
+        interrupt::free(|_| {
+            let bdcr = unsafe { &(*RCC::ptr()).bdcr };
+            bdcr.modify(|_, w| w.lseon().off());
+        });
+    }
+
+    /// Returns true if the LSE is running
+    pub fn is_ready(&self) -> bool {
+        // unsafe: We only read from this bitfield
+        let bdcr = unsafe { &(*RCC::ptr()).bdcr };
+        bdcr.read().lserdy().is_ready()
+    }
+
+    fn start(&mut self, bypass: bool, drive: LseDrive) {
+        // unsafe: Owned exclusive access to this bitfield
+        interrupt::free(|_| {
+            let bdcr = unsafe { &(*RCC::ptr()).bdcr };
+
+            // LSEBYP and LSEDRV can only be written when the LSE is off
+            if bdcr.read().lseon().is_off() {
+                bdcr.modify(|_, w| {
+                    w.lsebyp().bit(bypass).lsedrv().variant(drive)
+                });
+                bdcr.modify(|_, w| w.lseon().on());
+            }
+        });
+        while !self.is_ready() {}
+    }
+}
+
 #[cfg(feature = "rtc")]
 pub use rtc::{Rtc, RtcClkSel};
 
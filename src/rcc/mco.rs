@@ -78,7 +78,13 @@ impl Rcc {
     pub(super) fn mco1_setup(&mut self) {
         // HSI always runs
 
-        // LSE unimplemented
+        // LSE must be enabled in the backup domain
+        if self.config.mco1.source == MCO1::LSE {
+            assert!(
+                self.rb.bdcr.read().lserdy().is_ready(),
+                "LSE is required for MCO1. Enable it in the backup domain before freezing"
+            );
+        }
 
         // HSE must be explicitly stated
         if self.config.mco1.source == MCO1::HSE {
@@ -137,7 +143,10 @@ impl Rcc {
 
         // CSI always runs
 
-        // LSI unimplemented
+        // Enable LSI based on requirement
+        if self.config.mco2.source == MCO2::LSI {
+            self.config.lsi = true;
+        }
     }
 }
 
@@ -166,7 +175,7 @@ macro_rules! mco1_setters {
 }
 mco1_setters! {
     mco1_from_hsi: HSI "the HSI",
-    //mco1_from_lse: LSE "the LSE",    UNIMPLEMENTED
+    mco1_from_lse: LSE "the LSE",
     mco1_from_hse: HSE "the HSE",
     mco1_from_pll1_q_ck: PLL1_Q "pll1_q_ck",
     mco1_from_hsi48: HSI48 "HSI48"
@@ -200,6 +209,6 @@ mco2_setters! {
     mco2_from_pll2_p_ck: PLL2_P "pll2_p_ck",
     mco2_from_hse: HSE "the HSE",
     mco2_from_pll1_p_ck: PLL1_P "pll1_p_ck",
-    mco2_from_csi: CSI "CSI"
-    //mco2_from_lsi: LSI "the LSI",    UNIMPLEMENTED
+    mco2_from_csi: CSI "CSI",
+    mco2_from_lsi: LSI "the LSI"
 }
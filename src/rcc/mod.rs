@@ -169,6 +169,7 @@ use mco::{MCO1Config, MCO2Config, MCO1, MCO2};
 pub struct Config {
     hse: Option<u32>,
     bypass_hse: bool,
+    lsi: bool,
     sys_ck: Option<u32>,
     per_ck: Option<u32>,
     rcc_hclk: Option<u32>,
@@ -196,6 +197,7 @@ impl RccExt for RCC {
             config: Config {
                 hse: None,
                 bypass_hse: false,
+                lsi: false,
                 sys_ck: None,
                 per_ck: None,
                 rcc_hclk: None,
@@ -254,7 +256,7 @@ pub struct Ccdr {
 const HSI: u32 = 64_000_000; // Hz
 const CSI: u32 = 4_000_000; // Hz
 const HSI48: u32 = 48_000_000; // Hz
-const LSI: u32 = 32_000; // Hz
+pub(crate) const LSI: u32 = 32_000; // Hz
 
 /// Setter defintion for pclk 1 - 4
 macro_rules! pclk_setter {
@@ -325,6 +327,17 @@ impl Rcc {
         self
     }
 
+    /// Enables the LSI (internal low speed RC oscillator). The LSI is
+    /// required to use it as a kernel clock for the LPTIMs, RNG or DAC
+    /// sample and hold.
+    ///
+    /// The RTC and IWDG start the LSI themselves. The LSI is recorded as
+    /// running if it was already started, even without calling this method.
+    pub fn use_lsi(mut self) -> Self {
+        self.config.lsi = true;
+        self
+    }
+
     /// Set input frequency to the SCGU
     pub fn sys_ck<F>(mut self, freq: F) -> Self
     where
@@ -663,10 +676,24 @@ impl Rcc {
         let csi = CSI;
         let hsi48 = HSI48;
 
-        // Enable LSI for RTC, IWDG, AWU, or MCO2
-        let lsi = LSI;
-        rcc.csr.modify(|_, w| w.lsion().on());
-        while rcc.csr.read().lsirdy().is_not_ready() {}
+        // Enable LSI if requested, or required for MCO2. It may also have
+        // been started by the IWDG
+        if self.config.lsi {
+            rcc.csr.modify(|_, w| w.lsion().on());
+            while rcc.csr.read().lsirdy().is_not_ready() {}
+        }
+        let lsi_ck = if rcc.csr.read().lsirdy().is_ready() {
+            Some(Hertz(LSI))
+        } else {
+            None
+        };
+
+        // LSE is enabled in the backup domain, see backup::Lse
+        let lse_ck = if rcc.bdcr.read().lserdy().is_ready() {
+            Some(Hertz(backup::LSE))
+        } else {
+            None
+        };
 
         // per_ck from HSI by default
         let (per_ck, ckpersel) =
@@ -749,7 +776,7 @@ impl Rcc {
         let mco1_in = match self.config.mco1.source {
             // We set the required clock earlier, so can unwrap() here.
             MCO1::HSI => HSI,
-            MCO1::LSE => unimplemented!(),
+            MCO1::LSE => backup::LSE,
             MCO1::HSE => self.config.hse.unwrap(),
             MCO1::PLL1_Q => pll1_q_ck.unwrap().0,
             MCO1::HSI48 => HSI48,
@@ -911,7 +938,8 @@ impl Rcc {
                 csi_ck: Some(Hertz(csi)),
                 hsi_ck: Some(Hertz(hsi)),
                 hsi48_ck: Some(Hertz(hsi48)),
-                lsi_ck: Some(Hertz(lsi)),
+                lsi_ck,
+                lse_ck,
                 per_ck: Some(Hertz(per_ck)),
                 hse_ck,
                 mco1_ck,
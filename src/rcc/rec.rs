@@ -643,6 +643,7 @@ peripheral_reset_and_enable_control! {
 
         (Auto) I2c4 [kernel clk: I2c4 d3ccip "I2C4"],
         (Auto) Spi6 [kernel clk: Spi6(Variant) d3ccip "SPI6"],
+        (Auto) Lpuart1 [kernel clk: Lpuart1(Variant) d3ccip "LPUART1"],
         (Auto) Sai4 [kernel clk_a: Sai4A(Variant) d3ccip
             "Sub-Block A of SAI4"]
             [kernel clk_b: Sai4B(Variant) d3ccip
@@ -656,6 +657,7 @@ peripheral_reset_and_enable_control! {
         (Auto) Lptim3,// TODO [group clk: Lptim3(Variant) srdccip "LPTIM3"],
 
         (Auto) I2c4 [kernel clk: I2c4 srdccip "I2C4"],
-        (Auto) Spi6 [kernel clk: Spi6(Variant) srdccip "SPI6"]
+        (Auto) Spi6 [kernel clk: Spi6(Variant) srdccip "SPI6"],
+        (Auto) Lpuart1 [kernel clk: Lpuart1(Variant) srdccip "LPUART1"]
     ];
 }
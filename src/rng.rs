@@ -24,8 +24,8 @@ impl KerClk for RNG {
         match prec.get_kernel_clk_mux() {
             RngClkSel::HSI48 => clocks.hsi48_ck(),
             RngClkSel::PLL1_Q => clocks.pll1_q_ck(),
-            RngClkSel::LSE => unimplemented!(),
-            RngClkSel::LSI => unimplemented!(),
+            RngClkSel::LSE => clocks.lse_ck(),
+            RngClkSel::LSI => clocks.lsi_ck(),
         }
     }
 }
@@ -47,7 +47,8 @@ pub enum RtcClock {
     ///
     /// This clock remains functional in Stop or Standby mode,
     /// but requires VDD to remain powered. LSI is an RC
-    /// oscillator and has poor accuracy.
+    /// oscillator and has poor accuracy. The RTC starts the LSI if it
+    /// is not already running.
     Lsi,
     /// HSE (High-Speed External) divided by 2..=63
     ///
@@ -127,7 +128,11 @@ impl Rtc {
         }
 
         let clock_source_running = match clock_source {
-            RtcClock::Lsi => clocks.lsi_ck().is_some(),
+            RtcClock::Lsi => {
+                // The LSI is stopped by a system reset
+                enable_lsi(rcc);
+                true
+            }
             RtcClock::Hse { .. } => clocks.hse_ck().is_some(),
             RtcClock::Lse { .. } => bdcr.lserdy().is_ready(),
         };
@@ -167,7 +172,11 @@ impl Rtc {
 
                 clocks.hse_ck().map(|x| Hertz(x.0 / u32(divider)))
             }
-            RtcClock::Lsi => clocks.lsi_ck(),
+            RtcClock::Lsi => {
+                enable_lsi(rcc);
+
+                Some(Hertz(crate::rcc::LSI))
+            }
         }
         .expect("rtc_ker_ck not running")
         .0;
@@ -764,7 +773,8 @@ impl Rtc {
 
     /// Handle a Clock Security Subsystem failure for the LSE clock
     ///
-    /// Disables the LSE, disables the LSE CSS, and changes the RTC to use the LSI clock.
+    /// Disables the LSE, disables the LSE CSS, and changes the RTC to use the LSI clock, starting
+    /// the LSI if required.
     /// You may want to call `clear_date_time()`. You still need to unpend the LSECSS interrupt.
     pub fn handle_lse_css(&mut self) {
         if !self.is_pending(Event::LseCss) {
@@ -777,6 +787,14 @@ impl Rtc {
             .modify(|_, w| w.lsecsson().security_off().lseon().off());
 
         // We're allowed to change this once after the LSE fails
+        enable_lsi(rcc);
         self.prec.kernel_clk_mux(backup::RtcClkSel::LSI);
     }
 }
+
+/// Starts the LSI, if it is not already running. The LSI is only enabled by
+/// `freeze` if it was requested with [use_lsi](crate::rcc::Rcc::use_lsi)
+fn enable_lsi(rcc: &crate::stm32::rcc::RegisterBlock) {
+    rcc.csr.modify(|_, w| w.lsion().on());
+    while rcc.csr.read().lsirdy().is_not_ready() {}
+}
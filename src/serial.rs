@@ -18,7 +18,7 @@ use crate::stm32::rcc::cdccip2r::{USART16910SEL_A, USART234578SEL_A};
 #[cfg(not(feature = "rm0455"))]
 use crate::stm32::rcc::d2ccip2r::{USART16SEL_A, USART234578SEL_A};
 
-use crate::stm32::{UART4, UART5, UART7, UART8};
+use crate::stm32::{LPUART1, UART4, UART5, UART7, UART8};
 use crate::stm32::{USART1, USART2, USART3, USART6};
 
 use crate::gpio::gpioa::{
@@ -37,7 +37,7 @@ use crate::gpio::gpioi::PI9;
 #[cfg(not(feature = "stm32h7b0"))]
 use crate::gpio::gpioj::{PJ8, PJ9};
 
-use crate::gpio::{Alternate, AF11, AF14, AF4, AF6, AF7, AF8};
+use crate::gpio::{Alternate, AF11, AF14, AF3, AF4, AF6, AF7, AF8};
 use crate::rcc::{rec, CoreClocks, ResetEnable};
 use crate::time::Hertz;
 
@@ -348,6 +348,17 @@ uart_pins! {
             #[cfg(not(feature = "stm32h7b0"))]
             PJ9<Alternate<AF8>>
         ]
+    LPUART1:
+        TX: [
+            NoTx,
+            PA9<Alternate<AF3>>,
+            PB6<Alternate<AF8>>
+        ]
+        RX: [
+            NoRx,
+            PA10<Alternate<AF3>>,
+            PB7<Alternate<AF8>>
+        ]
 }
 
 /// Serial abstraction
@@ -499,7 +510,23 @@ macro_rules! usart {
 
                     Ok(Serial { usart })
                 }
+            }
+        )+
+
+        serial_common! {
+            $(
+                $USARTX: ($usartX, $Rec),
+            )+
+        }
+    }
+}
 
+macro_rules! serial_common {
+    ($(
+        $USARTX:ident: ($usartX:ident, $Rec:ident),
+    )+) => {
+        $(
+            impl Serial<$USARTX> {
                 /// Enables the Rx DMA stream.
                 pub fn enable_dma_rx(&mut self) {
                     self.usart.cr3.modify(|_, w| w.dmar().set_bit());
@@ -841,7 +868,7 @@ macro_rules! usart_sel {
                         Val($SEL::PLL3_Q) => clocks.pll3_q_ck(),
                         Val($SEL::HSI_KER) => clocks.hsi_ck(),
                         Val($SEL::CSI_KER) => clocks.csi_ck(),
-                        Val($SEL::LSE) => unimplemented!(),
+                        Val($SEL::LSE) => clocks.lse_ck(),
                         _ => unreachable!(),
                     }
                 }
@@ -902,6 +929,208 @@ usart_sel! {
     UART7: "UART7",
 }
 
+/// LPUART kernel clock prescaler values, in order of the PRESC register
+const LPUART_PRESC: [u32; 12] = [1, 2, 4, 6, 8, 10, 12, 16, 32, 64, 128, 256];
+
+/// Calculates the PRESC and BRR register values for LPUART. See RM0433
+/// Rev 7 Section 48.4.7
+///
+/// baud = 256 * lpuart_ker_ck_pres / BRR
+///
+/// Where BRR must be in the range 0x300 to 0xFFFFF. The smallest possible
+/// prescaler is chosen to give the best resolution. Returns `None` if the
+/// baudrate cannot be generated from this kernel clock
+fn calculate_lpuart_brr(ker_ck: u32, baudrate: u32) -> Option<(u8, u32)> {
+    if baudrate == 0 {
+        return None;
+    }
+
+    for (presc, div) in LPUART_PRESC.iter().enumerate() {
+        let ker_ck_pres = u64::from(ker_ck / div);
+        let brr =
+            (256 * ker_ck_pres + u64::from(baudrate) / 2) / u64::from(baudrate);
+
+        if brr < 0x300 {
+            return None; // Larger prescalers only decrease BRR
+        }
+        if brr <= 0xF_FFFF {
+            return Some((presc as u8, brr as u32));
+        }
+    }
+    None
+}
+
+/// Source of a wakeup from Stop mode
+#[derive(Copy, Clone, PartialEq)]
+pub enum Wakeup {
+    /// Wakeup when the receiver detects a start bit
+    StartBit,
+    /// Wakeup when a data frame contains this address in its least
+    /// significant bits. Uses 4-bit address detection if the address fits
+    /// in 4 bits, otherwise 7-bit address detection
+    AddressMatch(u8),
+    /// Wakeup when a complete frame has been received
+    RxNotEmpty,
+}
+
+macro_rules! lpuart {
+    ($(
+        $LPUARTX:ident: ($lpuartX:ident, $Rec:ident),
+    )+) => {
+        $(
+            /// Configures a LPUART peripheral to provide serial
+            /// communication
+            impl Serial<$LPUARTX> {
+                pub fn $lpuartX(
+                    lpuart: $LPUARTX,
+                    config: impl Into<config::Config>,
+                    prec: rec::$Rec,
+                    clocks: &CoreClocks
+                ) -> Result<Self, config::InvalidConfig>
+                {
+                    use self::config::*;
+
+                    let config = config.into();
+
+                    // LPUART only supports 1 or 2 stop bits
+                    let stop = match config.stopbits {
+                        StopBits::STOP1 => 0b00,
+                        StopBits::STOP2 => 0b10,
+                        _ => return Err(InvalidConfig),
+                    };
+
+                    // Get kernel clock
+                    let lpuart_ker_ck = match Self::kernel_clk(clocks) {
+                        Some(ker_hz) => ker_hz.0,
+                        _ => panic!("$LPUARTX kernel clock not running!")
+                    };
+
+                    // Calculate prescaler and baudrate divisor
+                    let (presc, brr) = calculate_lpuart_brr(
+                        lpuart_ker_ck, config.baudrate.0
+                    ).ok_or(InvalidConfig)?;
+
+                    // Enable clock for LPUART and reset
+                    prec.enable().reset();
+
+                    lpuart.presc.write(|w| unsafe { w.presc().bits(presc) });
+                    lpuart.brr.write(|w| unsafe { w.brr().bits(brr) });
+
+                    // Reset registers to disable advanced LPUART features
+                    lpuart.cr2.reset();
+                    lpuart.cr3.reset();
+
+                    // Set stop bits
+                    lpuart.cr2.write(|w| unsafe { w.stop().bits(stop) });
+
+                    // Enable transmission and receiving
+                    // and configure frame
+                    lpuart.cr1.write(|w| {
+                        w.fifoen()
+                            .set_bit() // FIFO mode enabled
+                            .ue()
+                            .set_bit()
+                            .te()
+                            .set_bit()
+                            .re()
+                            .set_bit()
+                            .m1()
+                            .clear_bit()
+                            .m0()
+                            .bit(config.wordlength == WordLength::DataBits9)
+                            .pce()
+                            .bit(config.parity != Parity::ParityNone)
+                            .ps()
+                            .bit(config.parity == Parity::ParityOdd)
+                    });
+
+                    Ok(Serial { usart: lpuart })
+                }
+
+                /// Returns the frequency of the current kernel clock
+                fn kernel_clk(clocks: &CoreClocks) -> Option<Hertz> {
+                    // unsafe: read only
+                    #[cfg(not(feature = "rm0455"))]
+                    let srdccipr = unsafe { (*stm32::RCC::ptr()).d3ccipr.read() };
+                    #[cfg(feature = "rm0455")]
+                    let srdccipr = unsafe { (*stm32::RCC::ptr()).srdccipr.read() };
+
+                    match srdccipr.lpuart1sel().bits() {
+                        0 => Some(clocks.pclk4()),
+                        1 => clocks.pll2_q_ck(),
+                        2 => clocks.pll3_q_ck(),
+                        3 => clocks.hsi_ck(),
+                        4 => clocks.csi_ck(),
+                        5 => clocks.lse_ck(),
+                        _ => unreachable!(),
+                    }
+                }
+
+                /// Enables wakeup from Stop mode on `source`
+                ///
+                /// The kernel clock must be the HSI, CSI or LSE, since
+                /// these are the only kernel clocks that can run in Stop
+                /// mode. The wakeup interrupt is enabled, and the LPUART
+                /// must also be enabled as a wakeup source in the EXTI.
+                pub fn enable_wakeup(&mut self, source: Wakeup) {
+                    let (wus, add) = match source {
+                        Wakeup::StartBit => (0b10, None),
+                        Wakeup::AddressMatch(addr) => (0b00, Some(addr)),
+                        Wakeup::RxNotEmpty => (0b11, None),
+                    };
+
+                    // WUS, ADD and ADDM7 can only be written when UE = 0
+                    self.usart.cr1.modify(|_, w| w.ue().clear_bit());
+
+                    if let Some(addr) = add {
+                        self.usart.cr2.modify(|_, w| unsafe {
+                            w.add().bits(addr).addm7().bit(addr > 0xF)
+                        });
+                    }
+                    self.usart.cr3.modify(|_, w| unsafe {
+                        w.wus()
+                            .bits(wus)
+                            .wufie()
+                            .set_bit()
+                            .ucesm()
+                            .set_bit() // Keep kernel clock in Stop mode
+                    });
+
+                    self.usart.cr1.modify(|_, w| w.ue().set_bit().uesm().set_bit());
+                }
+
+                /// Disables wakeup from Stop mode
+                pub fn disable_wakeup(&mut self) {
+                    self.usart.cr1.modify(|_, w| w.uesm().clear_bit());
+                    self.usart.cr3.modify(|_, w| w.wufie().clear_bit().ucesm().clear_bit());
+                }
+
+                /// Return true if the wakeup from Stop mode flag is set
+                pub fn is_wakeup_pending(&self) -> bool {
+                    self.usart.isr.read().wuf().bit_is_set()
+                }
+
+                /// Clear the wakeup from Stop mode flag
+                pub fn clear_wakeup(&mut self) {
+                    self.usart.icr.write(|w| w.wucf().set_bit());
+                    let _ = self.usart.isr.read();
+                    let _ = self.usart.isr.read(); // Delay 2 peripheral clocks
+                }
+            }
+        )+
+
+        serial_common! {
+            $(
+                $LPUARTX: ($lpuartX, $Rec),
+            )+
+        }
+    }
+}
+
+lpuart! {
+    LPUART1: (lpuart1, Lpuart1),
+}
+
 impl<USART> fmt::Write for Tx<USART>
 where
     Tx<USART>: serial::Write<u8>,
@@ -921,3 +1150,25 @@ where
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lpuart_brr() {
+        // LSE
+        assert_eq!(calculate_lpuart_brr(32_768, 9_600), Some((0, 874)));
+        assert_eq!(calculate_lpuart_brr(32_768, 19_200), None);
+        // HSI
+        assert_eq!(
+            calculate_lpuart_brr(64_000_000, 115_200),
+            Some((0, 142_222))
+        );
+        assert_eq!(calculate_lpuart_brr(64_000_000, 9_600), Some((1, 853_333)));
+        // PCLK4
+        assert_eq!(calculate_lpuart_brr(100_000_000, 300), Some((10, 666_667)));
+        assert_eq!(calculate_lpuart_brr(100_000_000, 50), None);
+        assert_eq!(calculate_lpuart_brr(100_000_000, 0), None);
+    }
+}
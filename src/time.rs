@@ -234,38 +234,6 @@ impl Into<Duration> for NanoSeconds {
     }
 }
 
-// /// A monotonic nondecreasing timer
-// #[derive(Clone, Copy)]
-// pub struct MonoTimer {
-//     frequency: Hertz,
-// }
-
-// impl MonoTimer {
-//     /// Creates a new `Monotonic` timer
-//     pub fn new(mut dwt: DWT, clocks: Clocks) -> Self {
-//         dwt.enable_cycle_counter();
-
-//         // now the CYCCNT counter can't be stopped or resetted
-//         drop(dwt);
-
-//         MonoTimer {
-//             frequency: clocks.sysclk(),
-//         }
-//     }
-
-//     /// Returns the frequency at which the monotonic timer is operating at
-//     pub fn frequency(&self) -> Hertz {
-//         self.frequency
-//     }
-
-//     /// Returns an `Instant` corresponding to "now"
-//     pub fn now(&self) -> Instant {
-//         Instant {
-//             now: DWT::get_cycle_count(),
-//         }
-//     }
-// }
-
 /// A measurement of a monotonically nondecreasing clock
 #[derive(Clone, Copy)]
 pub struct Instant {
@@ -1,7 +1,37 @@
 //! Timers
-
-// TODO: on the h7x3 at least, only TIM2, TIM3, TIM4, TIM5 can support 32 bits.
-// TIM1 is 16 bit.
+//!
+//! `TIM2` and `TIM5` have 32-bit counters, all other timers and the
+//! Low-power timers have 16-bit counters. The width is given by the
+//! [CounterWidth](CounterWidth) trait, and
+//! [Timer::counter](struct.Timer.html#method.counter) returns a value of that
+//! width.
+//!
+//! Timeouts are set with a 16-bit prescaler in front of the counter, so a
+//! timer with a 16-bit counter can count up to 2<sup>32</sup> kernel clock
+//! cycles, and a timer with a 32-bit counter up to 2<sup>48</sup> cycles. The
+//! prescaler is kept as small as possible, for the best resolution. Timeouts
+//! outside this range return an [Error](Error), or panic if they are passed
+//! to [CountDown::start](embedded_hal::timer::CountDown::start).
+//!
+//! # Low-power timers
+//!
+//! Besides periodic timeouts, the Low-power timers `LPTIM1` and `LPTIM2` can
+//! count pulses on an external input, or decode a quadrature encoder (see
+//! [LpInputExt](LpInputExt)). Pulse counting is asynchronous, so it
+//! continues in Stop mode. All Low-power timers have a timeout mode, where
+//! an external trigger resets the counter (see
+//! [timeout_trigger](struct.LpTimer.html#method.timeout_trigger)).
+//!
+//! ```
+//! let counter = dp.LPTIM1.pulse_counter(
+//!     gpiod.pd12.into_alternate_af1(),
+//!     CaptureEdge::Rising,
+//!     ccdr.peripheral.LPTIM1,
+//!     &ccdr.clocks,
+//! );
+//! let counter = counter.resume();
+//! let pulses = counter.counter();
+//! ```
 
 use core::convert::TryFrom;
 use core::marker::PhantomData;
@@ -18,7 +48,7 @@ use crate::stm32::{
     TIM6, TIM7, TIM8,
 };
 
-use cast::{u16, u32};
+use cast::u16;
 use void::Void;
 
 #[cfg(feature = "rm0455")]
@@ -26,9 +56,19 @@ use crate::stm32::rcc::{cdccip2r as ccip2r, srdccipr};
 #[cfg(not(feature = "rm0455"))]
 use crate::stm32::rcc::{d2ccip2r as ccip2r, d3ccipr as srdccipr};
 
+use crate::capture::CaptureEdge;
+use crate::gpio::gpiob::PB10;
+use crate::gpio::gpiod::{PD11, PD12};
+use crate::gpio::gpioe::PE1;
+use crate::gpio::gpiog::PG12;
+use crate::gpio::gpioh::PH2;
+use crate::gpio::{Alternate, AF1, AF3};
+use crate::one_pulse::LpTrigger;
+use crate::pwm::PwmControl;
 use crate::rcc::{rec, CoreClocks, ResetEnable};
 use crate::stm32;
 use crate::time::Hertz;
+use crate::{adc, dac};
 use stm32h7::Variant::Val;
 
 /// Associate clocks with timers
@@ -69,8 +109,8 @@ impl GetClk for LPTIM1 {
             Val(ccip2r::LPTIM1SEL_A::RCC_PCLK1) => Some(clocks.pclk1()),
             Val(ccip2r::LPTIM1SEL_A::PLL2_P) => clocks.pll2_p_ck(),
             Val(ccip2r::LPTIM1SEL_A::PLL3_R) => clocks.pll3_r_ck(),
-            Val(ccip2r::LPTIM1SEL_A::LSE) => unimplemented!(),
-            Val(ccip2r::LPTIM1SEL_A::LSI) => unimplemented!(),
+            Val(ccip2r::LPTIM1SEL_A::LSE) => clocks.lse_ck(),
+            Val(ccip2r::LPTIM1SEL_A::LSI) => clocks.lsi_ck(),
             Val(ccip2r::LPTIM1SEL_A::PER) => clocks.per_ck(),
             _ => unreachable!(),
         }
@@ -90,8 +130,8 @@ impl GetClk for LPTIM2 {
             Val(srdccipr::LPTIM2SEL_A::RCC_PCLK4) => Some(clocks.pclk4()),
             Val(srdccipr::LPTIM2SEL_A::PLL2_P) => clocks.pll2_p_ck(),
             Val(srdccipr::LPTIM2SEL_A::PLL3_R) => clocks.pll3_r_ck(),
-            Val(srdccipr::LPTIM2SEL_A::LSE) => unimplemented!(),
-            Val(srdccipr::LPTIM2SEL_A::LSI) => unimplemented!(),
+            Val(srdccipr::LPTIM2SEL_A::LSE) => clocks.lse_ck(),
+            Val(srdccipr::LPTIM2SEL_A::LSI) => clocks.lsi_ck(),
             Val(srdccipr::LPTIM2SEL_A::PER) => clocks.per_ck(),
             _ => unreachable!(),
         }
@@ -110,8 +150,8 @@ impl GetClk for LPTIM3 {
             0 => Some(clocks.pclk4()),
             1 => clocks.pll2_p_ck(),
             2 => clocks.pll3_r_ck(),
-            3 => unimplemented!(),
-            4 => unimplemented!(),
+            3 => clocks.lse_ck(),
+            4 => clocks.lsi_ck(),
             5 => clocks.per_ck(),
             _ => unreachable!(),
         }
@@ -133,8 +173,8 @@ macro_rules! impl_clk_lptim345 {
                         Val(srdccipr::LPTIM345SEL_A::RCC_PCLK4) => Some(clocks.pclk4()),
                         Val(srdccipr::LPTIM345SEL_A::PLL2_P) => clocks.pll2_p_ck(),
                         Val(srdccipr::LPTIM345SEL_A::PLL3_R) => clocks.pll3_r_ck(),
-                        Val(srdccipr::LPTIM345SEL_A::LSE) => unimplemented!(),
-                        Val(srdccipr::LPTIM345SEL_A::LSI) => unimplemented!(),
+                        Val(srdccipr::LPTIM345SEL_A::LSE) => clocks.lse_ck(),
+                        Val(srdccipr::LPTIM345SEL_A::LSI) => clocks.lsi_ck(),
                         Val(srdccipr::LPTIM345SEL_A::PER) => clocks.per_ck(),
                         _ => unreachable!(),
                     }
@@ -193,6 +233,26 @@ pub struct LpTimer<TIM, ED> {
     _enabled: PhantomData<ED>,
 }
 
+/// Timer errors
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The timeout is longer than the timer can count, even with the
+    /// maximum prescaler
+    TimeoutTooLong,
+    /// The timeout is shorter than one cycle of the timer kernel clock
+    TimeoutTooShort,
+}
+
+/// The width of a timer's counter
+pub trait CounterWidth {
+    /// `u16` or `u32`
+    type Counter;
+
+    /// Maximum value of the counter and the auto-reload register
+    const COUNTER_MAX: u32;
+}
+
 /// Timer Events
 ///
 /// Each event is a possible interrupt source, if enabled
@@ -204,6 +264,12 @@ pub enum Event {
 macro_rules! hal {
     ($($TIMX:ident: ($timX:ident, $Rec:ident, $cntType:ty),)+) => {
         $(
+            impl CounterWidth for $TIMX {
+                type Counter = $cntType;
+
+                const COUNTER_MAX: u32 = <$cntType>::MAX as u32;
+            }
+
             impl Periodic for Timer<$TIMX> {}
 
             impl CountDown for Timer<$TIMX> {
@@ -224,7 +290,7 @@ macro_rules! hal {
                     self.clear_irq();
 
                     // Set PSC and ARR
-                    self.set_freq(timeout);
+                    self.set_freq(timeout).expect("Timeout out of range");
 
                     // Generate an update event to force an update of the ARR register. This ensures
                     // the first timer cycle is of the specified duration.
@@ -306,21 +372,27 @@ macro_rules! hal {
 
                 /// Configures the timer's frequency and counter reload value
                 /// so that it underflows at the timeout's frequency
-                pub fn set_freq<T>(&mut self, timeout: T)
+                ///
+                /// Returns an error if the frequency is out of range for this
+                /// timer
+                pub fn set_freq<T>(&mut self, timeout: T) -> Result<(), Error>
                 where
                     T: Into<Hertz>,
                 {
                     let timeout = timeout.into();
+                    if timeout.0 == 0 {
+                        return Err(Error::TimeoutTooLong);
+                    }
                     let ticks = self.clk / timeout.0;
 
-                    self.set_timeout_ticks(ticks);
+                    self.set_timeout_ticks(u64::from(ticks))
                 }
 
                 /// Sets the timer period from a time duration
                 ///
                 /// ```
                 /// // Set timeout to 100ms
-                /// timer.set_timeout(100.ms());
+                /// timer.set_timeout(100.ms()).unwrap();
                 /// ```
                 ///
                 /// Alternatively, the duration can be set using the
@@ -330,23 +402,27 @@ macro_rules! hal {
                 /// let duration = core::time::Duration::from_nanos(2_500);
                 ///
                 /// // Set timeout to 2.5µs
-                /// timer.set_timeout(duration);
+                /// timer.set_timeout(duration).unwrap();
                 /// ```
-                pub fn set_timeout<T>(&mut self, timeout: T)
+                ///
+                /// Returns an error if the timeout is out of range for this
+                /// timer
+                pub fn set_timeout<T>(&mut self, timeout: T) -> Result<(), Error>
                 where
                     T: Into<core::time::Duration>
                 {
                     const NANOS_PER_SECOND: u64 = 1_000_000_000;
                     let timeout = timeout.into();
 
-                    let clk = self.clk as u64;
-                    let ticks = u32::try_from(
-                        clk * timeout.as_secs() +
-                        clk * u64::from(timeout.subsec_nanos()) / NANOS_PER_SECOND,
-                    )
-                    .unwrap_or(u32::max_value());
+                    let clk = u64::from(self.clk);
+                    let ticks = clk
+                        .checked_mul(timeout.as_secs())
+                        .and_then(|ticks| ticks.checked_add(
+                            clk * u64::from(timeout.subsec_nanos()) / NANOS_PER_SECOND
+                        ))
+                        .ok_or(Error::TimeoutTooLong)?;
 
-                    self.set_timeout_ticks(ticks.max(1));
+                    self.set_timeout_ticks(ticks)
                 }
 
                 /// Sets the timer's prescaler and auto reload register so that the timer will reach
@@ -362,10 +438,15 @@ macro_rules! hal {
                 /// // Set auto reload register to 50000 and prescaler to divide by 2.
                 /// timer.set_timeout_ticks(100001);
                 /// ```
-                fn set_timeout_ticks(&mut self, ticks: u32) {
-                    let (psc, arr) = calculate_timeout_ticks_register_values(ticks);
+                fn set_timeout_ticks(&mut self, ticks: u64) -> Result<(), Error> {
+                    let (psc, arr) = calculate_timeout_ticks_register_values(
+                        ticks,
+                        $TIMX::COUNTER_MAX,
+                    )?;
                     self.tim.psc.write(|w| w.psc().bits(psc));
-                    self.tim.arr.write(|w| unsafe { w.bits(u32(arr)) });
+                    self.tim.arr.write(|w| unsafe { w.bits(arr) });
+
+                    Ok(())
                 }
 
                 /// Configures the timer to count up at the given frequency
@@ -383,8 +464,7 @@ macro_rules! hal {
                     let psc = u16(div - 1).unwrap();
                     self.tim.psc.write(|w| w.psc().bits(psc));
 
-                    let counter_max = u32(<$cntType>::MAX);
-                    self.tim.arr.write(|w| unsafe { w.bits(counter_max) });
+                    self.tim.arr.write(|w| unsafe { w.bits($TIMX::COUNTER_MAX) });
                 }
 
                 /// Applies frequency/timeout changes immediately
@@ -417,8 +497,8 @@ macro_rules! hal {
                 }
 
                 /// Read the counter of the TIM peripheral
-                pub fn counter(&self) -> u32 {
-                    self.tim.cnt.read().bits()
+                pub fn counter(&self) -> $cntType {
+                    self.tim.cnt.read().bits() as $cntType
                 }
 
                 /// Start listening for `event`
@@ -474,19 +554,30 @@ macro_rules! hal {
 /// But `ticks` may have a higher value than what the timer can hold directly.
 /// So we'll use the prescaler to extend the range.
 ///
-/// To know how many times we would overflow with a prescaler of 1, we divide `ticks` by 2^16 (the max amount of ticks per overflow).
+/// To know how many times we would overflow with a prescaler of 1, we divide `ticks` by the number of ticks per
+/// overflow (2^16 or 2^32, depending on the counter width).
 /// If the result is e.g. 3, then we need to increase our range by 4 times to fit all the ticks.
 /// We can increase the range enough by setting the prescaler to 3 (which will divide the clock freq by 4).
 /// Because every tick is now 4x as long, we need to divide `ticks` by 4 to keep the same timeout.
 ///
-/// This function returns the prescaler register value and auto reload register value.
-fn calculate_timeout_ticks_register_values(ticks: u32) -> (u16, u16) {
-    // Note (unwrap): Never panics because 32-bit value is shifted right by 16 bits,
-    // resulting in a value that always fits in 16 bits.
-    let psc = u16(ticks / (1 << 16)).unwrap();
-    // Note (unwrap): Never panics because the divisor is always such that the result fits in 16 bits.
-    let arr = u16(ticks / (u32(psc) + 1)).unwrap();
-    (psc, arr)
+/// This function returns the prescaler register value and auto reload register value, or an
+/// error if the timeout cannot be reached with a 16-bit prescaler. The counter does not run if
+/// ARR is zero, so the shortest timeout is 1 tick.
+fn calculate_timeout_ticks_register_values(
+    ticks: u64,
+    counter_max: u32,
+) -> Result<(u16, u32), Error> {
+    if ticks == 0 {
+        return Err(Error::TimeoutTooShort);
+    }
+
+    let psc = ticks / (u64::from(counter_max) + 1);
+    let psc = u16::try_from(psc).map_err(|_| Error::TimeoutTooLong)?;
+
+    // Note (as u32): the divisor is always such that the result fits in the counter
+    let arr = (ticks / (u64::from(psc) + 1)) as u32;
+
+    Ok((psc, arr))
 }
 
 hal! {
@@ -515,9 +606,470 @@ hal! {
     TIM17: (tim17, Tim17, u16),
 }
 
+/// Master mode selection: the signal output on TRGO
+///
+/// Refer to the description of CR2.MMS in the reference manual
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MasterMode {
+    /// The UG bit in the EGR register, or a reset from the slave mode
+    /// controller
+    Reset = 0b000,
+    /// The counter enable signal CNT_EN
+    Enable = 0b001,
+    /// The update event
+    Update = 0b010,
+    /// A pulse when the CC1IF flag is set, on capture or compare match
+    ComparePulse = 0b011,
+    /// OC1REF
+    Oc1Ref = 0b100,
+    /// OC2REF
+    Oc2Ref = 0b101,
+    /// OC3REF
+    Oc3Ref = 0b110,
+    /// OC4REF
+    Oc4Ref = 0b111,
+}
+
+/// Master mode selection for the basic timers (`TIM6`, `TIM7`), which have
+/// no capture/compare channels
+///
+/// Refer to the description of CR2.MMS in the reference manual
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BasicMasterMode {
+    /// The UG bit in the EGR register
+    Reset = 0b000,
+    /// The counter enable signal CNT_EN
+    Enable = 0b001,
+    /// The update event
+    Update = 0b010,
+}
+
+/// Master mode 2 selection: the signal output on TRGO2. Only available on
+/// the advanced control timers
+///
+/// TRGO2 can be used to trigger the ADCs
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MasterMode2 {
+    Reset = 0b0000,
+    Enable = 0b0001,
+    Update = 0b0010,
+    ComparePulse = 0b0011,
+    Oc1Ref = 0b0100,
+    Oc2Ref = 0b0101,
+    Oc3Ref = 0b0110,
+    Oc4Ref = 0b0111,
+    Oc5Ref = 0b1000,
+    Oc6Ref = 0b1001,
+    /// A pulse on each rising or falling edge of OC4REF
+    ComparePulseOc4 = 0b1010,
+    /// A pulse on each rising or falling edge of OC6REF
+    ComparePulseOc6 = 0b1011,
+    /// A pulse on the rising edges of OC4REF or OC6REF
+    ComparePulseOc4RisingOc6Rising = 0b1100,
+    /// A pulse on the rising edge of OC4REF or the falling edge of OC6REF
+    ComparePulseOc4RisingOc6Falling = 0b1101,
+    /// A pulse on the rising edges of OC5REF or OC6REF
+    ComparePulseOc5RisingOc6Rising = 0b1110,
+    /// A pulse on the rising edge of OC5REF or the falling edge of OC6REF
+    ComparePulseOc5RisingOc6Falling = 0b1111,
+}
+
+/// Slave mode selection
+///
+/// Refer to the description of SMCR.SMS in the reference manual
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SlaveMode {
+    /// A rising edge of the trigger input resets the counter and updates the
+    /// registers
+    Reset = 0b0100,
+    /// The counter runs while the trigger input is high
+    Gated = 0b0101,
+    /// A rising edge of the trigger input starts the counter
+    Trigger = 0b0110,
+    /// Rising edges of the trigger input clock the counter
+    ExternalClock1 = 0b0111,
+    /// A rising edge of the trigger input resets the counter, updates the
+    /// registers and starts the counter
+    CombinedResetTrigger = 0b1000,
+}
+
+/// Handle for the TRGO output of a timer
+///
+/// Obtained from [`MasterTimer::master_mode`] or
+/// [`BasicMasterTimer::master_mode`], and used to select the timer as a
+/// trigger source for the slave mode controller of another timer,
+/// the ADCs and the DACs.
+#[derive(Copy, Clone, Debug)]
+pub struct Trgo<TIM> {
+    _tim: PhantomData<TIM>,
+}
+
+/// Handle for the TRGO2 output of an advanced control timer
+///
+/// Obtained from [`MasterTimer2::master_mode2`], and used to select the
+/// timer as a trigger source for the ADCs.
+#[derive(Copy, Clone, Debug)]
+pub struct Trgo2<TIM> {
+    _tim: PhantomData<TIM>,
+}
+
+/// Trigger input: edge detector on TI1 (TI1F_ED)
+#[derive(Copy, Clone, Debug)]
+pub struct Ti1Edge;
+/// Trigger input: filtered timer input 1 (TI1FP1)
+#[derive(Copy, Clone, Debug)]
+pub struct Ti1Fp1;
+/// Trigger input: filtered timer input 2 (TI2FP2)
+#[derive(Copy, Clone, Debug)]
+pub struct Ti2Fp2;
+
+/// Trigger inputs that can be selected by the slave mode controller of
+/// `TIM`
+///
+/// Implemented for the [`Trgo`] outputs of the timers that are connected to
+/// the internal trigger inputs (ITRx) of `TIM`, and for the timer's own
+/// inputs.
+pub trait TriggerInput<TIM> {
+    /// SMCR.TS value
+    const TS: u8;
+}
+
+impl<TIM> TriggerInput<TIM> for Ti1Edge {
+    const TS: u8 = 0b00100;
+}
+impl<TIM> TriggerInput<TIM> for Ti1Fp1 {
+    const TS: u8 = 0b00101;
+}
+impl<TIM> TriggerInput<TIM> for Ti2Fp2 {
+    const TS: u8 = 0b00110;
+}
+
+// Internal trigger connections
+//
+// Refer to RM0433 Rev 7 - Table 330, Table 340, Table 350 and Table 360
+macro_rules! internal_triggers {
+    ($($SLAVE:ident: [$($MASTER:ident: $ts:expr),+])+) => {
+        $(
+            $(
+                impl TriggerInput<$SLAVE> for Trgo<$MASTER> {
+                    const TS: u8 = $ts;
+                }
+            )+
+        )+
+    }
+}
+
+internal_triggers! {
+    TIM1: [TIM15: 0, TIM2: 1, TIM3: 2, TIM4: 3]
+    TIM8: [TIM1: 0, TIM2: 1, TIM4: 2, TIM5: 3]
+    TIM2: [TIM1: 0, TIM8: 1, TIM3: 2, TIM4: 3]
+    TIM3: [TIM1: 0, TIM2: 1, TIM15: 2, TIM4: 3]
+    TIM4: [TIM1: 0, TIM2: 1, TIM3: 2, TIM8: 3]
+    TIM5: [TIM1: 0, TIM8: 1, TIM3: 2, TIM4: 3]
+    TIM12: [TIM4: 0, TIM5: 1]
+    TIM15: [TIM1: 0, TIM3: 1]
+}
+
+/// Timers that can output a trigger on TRGO
+pub trait MasterTimer {
+    type Timer;
+
+    /// Selects the signal output on TRGO
+    fn master_mode(&mut self, mode: MasterMode) -> Trgo<Self::Timer>;
+}
+
+/// Basic timers that can output a trigger on TRGO
+pub trait BasicMasterTimer {
+    type Timer;
+
+    /// Selects the signal output on TRGO
+    fn master_mode(&mut self, mode: BasicMasterMode) -> Trgo<Self::Timer>;
+}
+
+/// Advanced control timers that can output a second trigger on TRGO2
+pub trait MasterTimer2: MasterTimer {
+    /// Selects the signal output on TRGO2
+    fn master_mode2(&mut self, mode: MasterMode2) -> Trgo2<Self::Timer>;
+}
+
+/// Timers with a slave mode controller
+pub trait SlaveTimer {
+    type Timer;
+
+    /// Enables the slave mode controller, driven by `trigger`
+    ///
+    /// `trigger` can be another timer's [`Trgo`], if it is connected to one
+    /// of the internal trigger inputs of this timer, or one of the timer's
+    /// own inputs [`Ti1Edge`], [`Ti1Fp1`] or [`Ti2Fp2`].
+    ///
+    /// ```
+    /// let trgo = master.master_mode(MasterMode::Enable);
+    /// slave.slave_mode(SlaveMode::Gated, trgo);
+    /// ```
+    fn slave_mode<T>(&mut self, mode: SlaveMode, trigger: T)
+    where
+        T: TriggerInput<Self::Timer>;
+
+    /// Disables the slave mode controller. The counter is clocked by the
+    /// internal clock
+    fn disable_slave_mode(&mut self);
+
+    /// Delays the trigger input of the slave mode controller, so that this
+    /// timer is synchronised with the timers it controls through TRGO
+    /// (SMCR.MSM)
+    fn master_slave_mode(&mut self, enable: bool);
+}
+
+// CR2 fields
+const CR2_MMS_SHIFT: u32 = 4;
+const CR2_MMS_MASK: u32 = 0b111 << CR2_MMS_SHIFT;
+const CR2_MMS2_SHIFT: u32 = 20;
+const CR2_MMS2_MASK: u32 = 0b1111 << CR2_MMS2_SHIFT;
+
+// SMCR fields. TS and SMS are split into two parts
+const SMCR_SMS_MASK: u32 = 0b111 | 1 << 16;
+const SMCR_TS_MASK: u32 = 0b111 << 4 | 0b11 << 20;
+const SMCR_MSM: u32 = 1 << 7;
+
+/// SMCR bits for the given slave mode and trigger selection
+fn smcr_bits(sms: u8, ts: u8) -> u32 {
+    let sms = u32::from(sms);
+    let ts = u32::from(ts);
+
+    (sms & 0b111) | (sms >> 3) << 16 | (ts & 0b111) << 4 | (ts >> 3) << 20
+}
+
+macro_rules! master_hal {
+    ($($TIMX:ident,)+) => {
+        $(
+            impl MasterTimer for Timer<$TIMX> {
+                type Timer = $TIMX;
+
+                fn master_mode(&mut self, mode: MasterMode) -> Trgo<$TIMX> {
+                    self.tim.cr2.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !CR2_MMS_MASK) | (mode as u32) << CR2_MMS_SHIFT)
+                    });
+
+                    Trgo { _tim: PhantomData }
+                }
+            }
+        )+
+    }
+}
+
+macro_rules! basic_master_hal {
+    ($($TIMX:ident,)+) => {
+        $(
+            impl BasicMasterTimer for Timer<$TIMX> {
+                type Timer = $TIMX;
+
+                fn master_mode(&mut self, mode: BasicMasterMode) -> Trgo<$TIMX> {
+                    self.tim.cr2.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !CR2_MMS_MASK) | (mode as u32) << CR2_MMS_SHIFT)
+                    });
+
+                    Trgo { _tim: PhantomData }
+                }
+            }
+        )+
+    }
+}
+
+macro_rules! master_pwm_hal {
+    ($($TIMX:ident,)+) => {
+        $(
+            impl<FAULT> MasterTimer for PwmControl<$TIMX, FAULT> {
+                type Timer = $TIMX;
+
+                fn master_mode(&mut self, mode: MasterMode) -> Trgo<$TIMX> {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.cr2.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !CR2_MMS_MASK) | (mode as u32) << CR2_MMS_SHIFT)
+                    });
+
+                    Trgo { _tim: PhantomData }
+                }
+            }
+        )+
+    }
+}
+
+macro_rules! master2_hal {
+    ($($TIMX:ident,)+) => {
+        $(
+            impl MasterTimer2 for Timer<$TIMX> {
+                fn master_mode2(&mut self, mode: MasterMode2) -> Trgo2<$TIMX> {
+                    self.tim.cr2.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !CR2_MMS2_MASK) | (mode as u32) << CR2_MMS2_SHIFT)
+                    });
+
+                    Trgo2 { _tim: PhantomData }
+                }
+            }
+
+            impl<FAULT> MasterTimer2 for PwmControl<$TIMX, FAULT> {
+                fn master_mode2(&mut self, mode: MasterMode2) -> Trgo2<$TIMX> {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.cr2.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !CR2_MMS2_MASK) | (mode as u32) << CR2_MMS2_SHIFT)
+                    });
+
+                    Trgo2 { _tim: PhantomData }
+                }
+            }
+        )+
+    }
+}
+
+macro_rules! slave_hal {
+    ($($TIMX:ident,)+) => {
+        $(
+            impl SlaveTimer for Timer<$TIMX> {
+                type Timer = $TIMX;
+
+                fn slave_mode<T>(&mut self, mode: SlaveMode, _trigger: T)
+                where
+                    T: TriggerInput<$TIMX>,
+                {
+                    // Disable the slave mode controller before changing the
+                    // trigger selection
+                    self.tim.smcr.modify(|r, w| unsafe {
+                        w.bits(r.bits() & !SMCR_SMS_MASK)
+                    });
+                    self.tim.smcr.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !SMCR_TS_MASK) | smcr_bits(0, T::TS))
+                    });
+                    self.tim.smcr.modify(|r, w| unsafe {
+                        w.bits(r.bits() | smcr_bits(mode as u8, 0))
+                    });
+                }
+
+                fn disable_slave_mode(&mut self) {
+                    self.tim.smcr.modify(|r, w| unsafe {
+                        w.bits(r.bits() & !SMCR_SMS_MASK)
+                    });
+                }
+
+                fn master_slave_mode(&mut self, enable: bool) {
+                    self.tim.smcr.modify(|r, w| unsafe {
+                        w.bits(if enable { r.bits() | SMCR_MSM } else { r.bits() & !SMCR_MSM })
+                    });
+                }
+            }
+
+            impl<FAULT> SlaveTimer for PwmControl<$TIMX, FAULT> {
+                type Timer = $TIMX;
+
+                fn slave_mode<T>(&mut self, mode: SlaveMode, _trigger: T)
+                where
+                    T: TriggerInput<$TIMX>,
+                {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    // Disable the slave mode controller before changing the
+                    // trigger selection
+                    tim.smcr.modify(|r, w| unsafe {
+                        w.bits(r.bits() & !SMCR_SMS_MASK)
+                    });
+                    tim.smcr.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !SMCR_TS_MASK) | smcr_bits(0, T::TS))
+                    });
+                    tim.smcr.modify(|r, w| unsafe {
+                        w.bits(r.bits() | smcr_bits(mode as u8, 0))
+                    });
+                }
+
+                fn disable_slave_mode(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.smcr.modify(|r, w| unsafe {
+                        w.bits(r.bits() & !SMCR_SMS_MASK)
+                    });
+                }
+
+                fn master_slave_mode(&mut self, enable: bool) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.smcr.modify(|r, w| unsafe {
+                        w.bits(if enable { r.bits() | SMCR_MSM } else { r.bits() & !SMCR_MSM })
+                    });
+                }
+            }
+        )+
+    }
+}
+
+master_hal! {
+    TIM1, TIM2, TIM3, TIM4, TIM5, TIM8, TIM15,
+}
+basic_master_hal! {
+    TIM6, TIM7,
+}
+master_pwm_hal! {
+    TIM1, TIM2, TIM3, TIM4, TIM5, TIM8, TIM15,
+}
+master2_hal! {
+    TIM1, TIM8,
+}
+slave_hal! {
+    TIM1, TIM2, TIM3, TIM4, TIM5, TIM8, TIM12, TIM15,
+}
+
+// Trigger handles as ADC and DAC trigger sources
+macro_rules! trigger_source {
+    ($($Handle:ident<$TIMX:ident> => $Target:ty: $variant:ident,)+) => {
+        $(
+            impl From<$Handle<$TIMX>> for $Target {
+                fn from(_: $Handle<$TIMX>) -> Self {
+                    <$Target>::$variant
+                }
+            }
+        )+
+    }
+}
+
+trigger_source! {
+    Trgo<TIM1> => adc::AdcExternalTrigger: Tim1Trgo,
+    Trgo2<TIM1> => adc::AdcExternalTrigger: Tim1Trgo2,
+    Trgo<TIM2> => adc::AdcExternalTrigger: Tim2Trgo,
+    Trgo<TIM3> => adc::AdcExternalTrigger: Tim3Trgo,
+    Trgo<TIM4> => adc::AdcExternalTrigger: Tim4Trgo,
+    Trgo<TIM6> => adc::AdcExternalTrigger: Tim6Trgo,
+    Trgo<TIM8> => adc::AdcExternalTrigger: Tim8Trgo,
+    Trgo2<TIM8> => adc::AdcExternalTrigger: Tim8Trgo2,
+    Trgo<TIM15> => adc::AdcExternalTrigger: Tim15Trgo,
+
+    Trgo<TIM1> => adc::AdcInjectedTrigger: Tim1Trgo,
+    Trgo2<TIM1> => adc::AdcInjectedTrigger: Tim1Trgo2,
+    Trgo<TIM2> => adc::AdcInjectedTrigger: Tim2Trgo,
+    Trgo<TIM3> => adc::AdcInjectedTrigger: Tim3Trgo,
+    Trgo<TIM4> => adc::AdcInjectedTrigger: Tim4Trgo,
+    Trgo<TIM6> => adc::AdcInjectedTrigger: Tim6Trgo,
+    Trgo<TIM8> => adc::AdcInjectedTrigger: Tim8Trgo,
+    Trgo2<TIM8> => adc::AdcInjectedTrigger: Tim8Trgo2,
+    Trgo<TIM15> => adc::AdcInjectedTrigger: Tim15Trgo,
+
+    Trgo<TIM1> => dac::Trigger: Tim1Trgo,
+    Trgo<TIM2> => dac::Trigger: Tim2Trgo,
+    Trgo<TIM4> => dac::Trigger: Tim4Trgo,
+    Trgo<TIM5> => dac::Trigger: Tim5Trgo,
+    Trgo<TIM6> => dac::Trigger: Tim6Trgo,
+    Trgo<TIM7> => dac::Trigger: Tim7Trgo,
+    Trgo<TIM8> => dac::Trigger: Tim8Trgo,
+    Trgo<TIM15> => dac::Trigger: Tim15Trgo,
+}
+
 macro_rules! lptim_hal {
     ($($TIMX:ident: ($timx:ident, $Rec:ident, $timXpac:ident),)+) => {
         $(
+            impl CounterWidth for $TIMX {
+                type Counter = u16;
+
+                const COUNTER_MAX: u32 = u16::MAX as u32;
+            }
+
             impl Periodic for LpTimer<$TIMX, Enabled> {}
 
             impl CountDown for LpTimer<$TIMX, Enabled> {
@@ -663,6 +1215,47 @@ macro_rules! lptim_hal {
                     }
                 }
 
+                /// Sets the auto-reload value of the LPTIM counter. The
+                /// counter counts from 0 to `reload`, then wraps
+                ///
+                /// The counter must be disabled.
+                pub fn set_reload(&mut self, reload: u16) {
+                    // ARR is written using the kernel clock, even when the
+                    // counter is clocked by Input 1
+                    let cfgr = self.tim.cfgr.read().bits();
+                    self.tim.cfgr.write(|w| unsafe { w.bits(cfgr & !LPTIM_CFGR_CKSEL) });
+
+                    // Write ARR: LPTIM must be enabled
+                    self.tim.cr.write(|w| w.enable().enabled());
+                    self.tim.arr.write(|w| w.arr().bits(reload));
+                    while self.tim.isr.read().arrok().bit_is_clear() {}
+                    self.tim.icr.write(|w| w.arrokcf().clear());
+
+                    // Write CFGR: LPTIM must be disabled
+                    self.tim.cr.write(|w| w.enable().disabled());
+                    self.tim.cfgr.write(|w| unsafe { w.bits(cfgr) });
+                }
+
+                /// Timeout mode. The counter starts on the first edge of
+                /// `trigger`, and each following edge resets it. The
+                /// TimeOut event only occurs if there is no trigger for a
+                /// whole period
+                ///
+                /// The counter must be disabled.
+                pub fn timeout_trigger(&mut self, trigger: LpTrigger, edge: CaptureEdge) {
+                    let mask = 0b111 << LPTIM_CFGR_TRIGSEL_SHIFT
+                        | 0b11 << LPTIM_CFGR_TRIGEN_SHIFT;
+
+                    self.tim.cfgr.modify(|r, w| unsafe {
+                        w.bits(
+                            (r.bits() & !mask)
+                                | (trigger as u32) << LPTIM_CFGR_TRIGSEL_SHIFT
+                                | lp_edge_bits(edge) << LPTIM_CFGR_TRIGEN_SHIFT
+                                | LPTIM_CFGR_TIMOUT,
+                        )
+                    });
+                }
+
                 /// Enables the LPTIM, and starts counting
                 pub fn resume(self) -> LpTimer<$TIMX, Enabled> {
                     // Enable and start counting
@@ -723,7 +1316,7 @@ macro_rules! lptim_hal {
                 }
 
                 /// Read the counter of the LPTIM peripheral
-                pub fn counter(&self) -> u32 {
+                pub fn counter(&self) -> u16 {
                     loop {
                         // Read once
                         let count1 = self.tim.cnt.read().bits();
@@ -731,7 +1324,7 @@ macro_rules! lptim_hal {
                         // Read twice - see RM0433 Rev 7. 43.4.14
                         let count2 = self.tim.cnt.read().bits();
 
-                        if count1 == count2 { return count2; }
+                        if count1 == count2 { return count2 as u16; }
                     }
                 }
 
@@ -765,24 +1358,288 @@ lptim_hal! {
     LPTIM5: (lptim5, Lptim5, lptim3),
 }
 
+// LPTIM CFGR
+const LPTIM_CFGR_CKSEL: u32 = 1 << 0;
+const LPTIM_CFGR_CKPOL_SHIFT: u32 = 1;
+pub(crate) const LPTIM_CFGR_PRESC_SHIFT: u32 = 9;
+pub(crate) const LPTIM_CFGR_TRIGSEL_SHIFT: u32 = 13;
+pub(crate) const LPTIM_CFGR_TRIGEN_SHIFT: u32 = 17;
+pub(crate) const LPTIM_CFGR_TIMOUT: u32 = 1 << 19;
+pub(crate) const LPTIM_CFGR_WAVPOL: u32 = 1 << 21;
+const LPTIM_CFGR_ENC: u32 = 1 << 24;
+
+/// LPTIM CFGR.CKPOL and CFGR.TRIGEN value for an input edge
+pub(crate) fn lp_edge_bits(edge: CaptureEdge) -> u32 {
+    match edge {
+        CaptureEdge::Rising => 0b01,
+        CaptureEdge::Falling => 0b10,
+        CaptureEdge::Both => 0b11,
+    }
+}
+
+/// Pins that can be used as Input 1 of a Low-power timer
+pub trait LpPinIn1<LPTIM> {}
+/// Pins that can be used as Input 2 of a Low-power timer
+pub trait LpPinIn2<LPTIM> {}
+
+/// Pairs of pins that can be used as the inputs of a Low-power timer in
+/// encoder mode
+pub trait LpEncoderPins<LPTIM> {}
+
+impl<LPTIM, PIN1, PIN2> LpEncoderPins<LPTIM> for (PIN1, PIN2)
+where
+    PIN1: LpPinIn1<LPTIM>,
+    PIN2: LpPinIn2<LPTIM>,
+{
+}
+
+macro_rules! lp_pins {
+    ($($LPTIMX:ty:
+       IN1: [$($IN1:ty),*]
+       IN2: [$($IN2:ty),*])+) => {
+        $(
+            $(
+                impl LpPinIn1<$LPTIMX> for $IN1 {}
+            )*
+            $(
+                impl LpPinIn2<$LPTIMX> for $IN2 {}
+            )*
+        )+
+    }
+}
+
+lp_pins! {
+    LPTIM1:
+        IN1: [
+            PD12<Alternate<AF1>>,
+            PG12<Alternate<AF1>>
+        ]
+        IN2: [
+            PE1<Alternate<AF1>>,
+            PH2<Alternate<AF1>>
+        ]
+
+    LPTIM2:
+        IN1: [
+            PB10<Alternate<AF3>>,
+            PD12<Alternate<AF3>>
+        ]
+        IN2: [
+            PD11<Alternate<AF3>>
+        ]
+}
+
+/// Extension trait for Low-power timers with external inputs
+pub trait LpInputExt: Sized {
+    type Rec: ResetEnable;
+
+    /// Configures the timer to count edges on Input 1
+    ///
+    /// The counter is clocked by the input itself, so it keeps counting in
+    /// Stop mode, without a kernel clock. Counting both edges additionally
+    /// requires the kernel clock to be running, at least four times faster
+    /// than the input.
+    ///
+    /// The counter counts from 0 to 0xFFFF unless changed with
+    /// [set_reload](struct.LpTimer.html#method.set_reload). Reaching the
+    /// reload value is a TimeOut event, which can wake the core from Stop
+    /// mode. Call `resume` to start counting.
+    fn pulse_counter<PIN>(
+        self,
+        _pin: PIN,
+        edge: CaptureEdge,
+        prec: Self::Rec,
+        clocks: &CoreClocks,
+    ) -> LpTimer<Self, Disabled>
+    where
+        PIN: LpPinIn1<Self>;
+
+    /// Configures the timer as a quadrature encoder interface, counting on
+    /// both edges of both inputs
+    ///
+    /// The counter counts up or down, between 0 and 0xFFFF unless changed
+    /// with [set_reload](struct.LpTimer.html#method.set_reload). The kernel
+    /// clock must be running, at least four times faster than the inputs.
+    /// Call `resume` to start counting.
+    fn encoder<PINS>(
+        self,
+        _pins: PINS,
+        prec: Self::Rec,
+        clocks: &CoreClocks,
+    ) -> LpTimer<Self, Disabled>
+    where
+        PINS: LpEncoderPins<Self>;
+}
+
+macro_rules! lptim_input_hal {
+    ($($TIMX:ident: $Rec:ident,)+) => {
+        $(
+            impl LpInputExt for $TIMX {
+                type Rec = rec::$Rec;
+
+                fn pulse_counter<PIN>(
+                    self,
+                    _pin: PIN,
+                    edge: CaptureEdge,
+                    prec: Self::Rec,
+                    clocks: &CoreClocks,
+                ) -> LpTimer<Self, Disabled>
+                where
+                    PIN: LpPinIn1<Self>,
+                {
+                    // CKPOL = 0b11 is reserved, both edges are 0b10
+                    let ckpol = lp_edge_bits(edge) - 1;
+
+                    LpTimer::<$TIMX, Disabled>::input_mode(
+                        self,
+                        LPTIM_CFGR_CKSEL | ckpol << LPTIM_CFGR_CKPOL_SHIFT,
+                        prec,
+                        clocks,
+                    )
+                }
+
+                fn encoder<PINS>(
+                    self,
+                    _pins: PINS,
+                    prec: Self::Rec,
+                    clocks: &CoreClocks,
+                ) -> LpTimer<Self, Disabled>
+                where
+                    PINS: LpEncoderPins<Self>,
+                {
+                    // Encoder sub-mode 3: both edges of both inputs
+                    LpTimer::<$TIMX, Disabled>::input_mode(
+                        self,
+                        LPTIM_CFGR_ENC | 0b10 << LPTIM_CFGR_CKPOL_SHIFT,
+                        prec,
+                        clocks,
+                    )
+                }
+            }
+
+            impl LpTimer<$TIMX, Disabled> {
+                /// Private method to configure the LPTIM in an external
+                /// input mode
+                fn input_mode(
+                    tim: $TIMX,
+                    cfgr: u32,
+                    prec: rec::$Rec,
+                    clocks: &CoreClocks,
+                ) -> Self {
+                    // enable and reset peripheral to a clean state
+                    prec.enable().reset();
+
+                    let clk = $TIMX::get_clk(clocks)
+                        .expect("Timer input clock not running!").0;
+
+                    // Write CFGR: LPTIM must be disabled
+                    tim.cfgr.write(|w| unsafe { w.bits(cfgr) });
+
+                    let mut timer = LpTimer {
+                        clk,
+                        tim,
+                        timeout: Hertz(0),
+                        _enabled: PhantomData,
+                    };
+                    timer.set_reload(0xFFFF);
+
+                    timer
+                }
+            }
+        )+
+    }
+}
+
+lptim_input_hal! {
+    LPTIM1: Lptim1,
+    LPTIM2: Lptim2,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn timeout_ticks_register_values() {
-        assert_eq!(calculate_timeout_ticks_register_values(0), (0, 0));
-        assert_eq!(calculate_timeout_ticks_register_values(50000), (0, 50000));
-        assert_eq!(calculate_timeout_ticks_register_values(100000), (1, 50000));
-        assert_eq!(calculate_timeout_ticks_register_values(65535), (0, 65535));
-        assert_eq!(calculate_timeout_ticks_register_values(65536), (1, 32768));
+        const MAX16: u32 = u16::MAX as u32;
+
+        assert_eq!(
+            calculate_timeout_ticks_register_values(0, MAX16),
+            Err(Error::TimeoutTooShort)
+        );
+        assert_eq!(
+            calculate_timeout_ticks_register_values(1, MAX16),
+            Ok((0, 1))
+        );
+        assert_eq!(
+            calculate_timeout_ticks_register_values(50000, MAX16),
+            Ok((0, 50000))
+        );
+        assert_eq!(
+            calculate_timeout_ticks_register_values(100000, MAX16),
+            Ok((1, 50000))
+        );
+        assert_eq!(
+            calculate_timeout_ticks_register_values(65535, MAX16),
+            Ok((0, 65535))
+        );
+        assert_eq!(
+            calculate_timeout_ticks_register_values(65536, MAX16),
+            Ok((1, 32768))
+        );
+        assert_eq!(
+            calculate_timeout_ticks_register_values(1000000, MAX16),
+            Ok((15, 62500))
+        );
+        assert_eq!(
+            calculate_timeout_ticks_register_values(u32::MAX as u64, MAX16),
+            Ok((u16::MAX, u16::MAX as u32))
+        );
         assert_eq!(
-            calculate_timeout_ticks_register_values(1000000),
-            (15, 62500)
+            calculate_timeout_ticks_register_values(1 << 32, MAX16),
+            Err(Error::TimeoutTooLong)
         );
+    }
+
+    #[test]
+    fn timeout_ticks_register_values_32bit() {
+        assert_eq!(
+            calculate_timeout_ticks_register_values(1000000, u32::MAX),
+            Ok((0, 1000000))
+        );
+        assert_eq!(
+            calculate_timeout_ticks_register_values(u32::MAX as u64, u32::MAX),
+            Ok((0, u32::MAX))
+        );
+        assert_eq!(
+            calculate_timeout_ticks_register_values(1 << 32, u32::MAX),
+            Ok((1, 1 << 31))
+        );
+        assert_eq!(
+            calculate_timeout_ticks_register_values((1 << 40) - 1, u32::MAX),
+            Ok((255, u32::MAX))
+        );
+        assert_eq!(
+            calculate_timeout_ticks_register_values((1 << 48) - 1, u32::MAX),
+            Ok((u16::MAX, u32::MAX))
+        );
+        assert_eq!(
+            calculate_timeout_ticks_register_values(1 << 48, u32::MAX),
+            Err(Error::TimeoutTooLong)
+        );
+    }
+
+    #[test]
+    fn slave_mode_register_values() {
+        assert_eq!(smcr_bits(0, 0), 0);
+        // Reset mode, ITR3
+        assert_eq!(smcr_bits(SlaveMode::Reset as u8, 3), 0b011_0100);
+        // Combined reset + trigger mode, TI1FP1
         assert_eq!(
-            calculate_timeout_ticks_register_values(u32::MAX),
-            (u16::MAX, u16::MAX)
+            smcr_bits(SlaveMode::CombinedResetTrigger as u8, 0b00101),
+            1 << 16 | 0b101_0000
         );
+        // ITR8
+        assert_eq!(smcr_bits(0, 0b01000), 1 << 20);
     }
 }
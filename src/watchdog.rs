@@ -1,7 +1,11 @@
-//! System Window Watchdog
+//! Watchdogs
+//!
+//! The [SystemWindowWatchdog] is clocked from the APB bus, whilst the
+//! [IndependentWatchdog] is clocked from the LSI oscillator and keeps
+//! running even if the system clocks fail.
 
 use crate::hal::watchdog::{Watchdog, WatchdogEnable};
-use crate::rcc::Ccdr;
+use crate::rcc::{Ccdr, CoreClocks};
 use crate::time::{Hertz, MilliSeconds};
 use cast::u8;
 
@@ -13,6 +17,14 @@ use crate::stm32::WWDG1 as WWDG;
 #[cfg(all(feature = "rm0399", feature = "cm4"))]
 use crate::stm32::WWDG2 as WWDG;
 
+/// Select Independent Watchdog hardware based on core
+#[cfg(any(feature = "rm0433", feature = "rm0455"))]
+use crate::stm32::IWDG;
+#[cfg(all(feature = "rm0399", feature = "cm7"))]
+use crate::stm32::IWDG1 as IWDG;
+#[cfg(all(feature = "rm0399", feature = "cm4"))]
+use crate::stm32::IWDG2 as IWDG;
+
 /// Event enum for [SystemWindowWatchdog]
 pub enum Event {
     /// Early wakeup interrupt. This will generate an interrupt when the watchdog would otherwise reset.
@@ -140,3 +152,154 @@ impl WatchdogEnable for SystemWindowWatchdog {
         self.wwdg.cr.modify(|_, w| w.wdga().set_bit());
     }
 }
+
+/// Nominal LSI frequency, used if the LSI is not running when the
+/// [IndependentWatchdog] is created. The IWDG starts the LSI itself
+const LSI: u32 = 32_000; // Hz
+
+/// IWDG key register values. See RM0433 Rev 7 Section 45.4.6
+const KEY_RELOAD: u16 = 0xAAAA;
+const KEY_START: u16 = 0xCCCC;
+const KEY_ACCESS: u16 = 0x5555;
+
+/// Maximum value of the reload and window registers
+const MAX_RELOAD: u32 = 0xFFF;
+
+/// Implements the Independent Watchdog
+///
+/// The Independent Watchdog is clocked by the LSI. Once started it cannot
+/// be stopped except by a system reset.
+pub struct IndependentWatchdog {
+    iwdg: IWDG,
+    lsi_frequency: Hertz,
+}
+
+/// Returns the prescaler divider exponent `pr` and the reload value `rl`
+/// for a timeout of `period_ms` with an LSI of `lsi_hz`
+///
+/// timeout = 4 * 2^pr * (rl + 1) / lsi
+///
+/// The smallest possible prescaler is chosen to give the finest
+/// resolution. Returns `None` if the timeout is too long
+fn prescaler_reload(lsi_hz: u32, period_ms: u32) -> Option<(u8, u16)> {
+    let ticks = (u64::from(period_ms) * u64::from(lsi_hz) / 1000).max(4);
+
+    (0..=6).find_map(|pr| {
+        let divider = 4u64 << pr;
+        let reload = (ticks + divider - 1) / divider - 1; // Round up
+        if reload <= u64::from(MAX_RELOAD) {
+            Some((pr, reload as u16))
+        } else {
+            None
+        }
+    })
+}
+
+impl IndependentWatchdog {
+    /// Returns an Independent Watchdog object. The watchdog is not started
+    /// until [start](WatchdogEnable::start) or
+    /// [start_windowed](IndependentWatchdog::start_windowed) is called
+    pub fn new(iwdg: IWDG, clocks: &CoreClocks) -> Self {
+        IndependentWatchdog {
+            iwdg,
+            lsi_frequency: clocks.lsi_ck().unwrap_or(Hertz(LSI)),
+        }
+    }
+
+    /// Returns the maximum timeout period that can be set
+    pub fn max_period(&self) -> MilliSeconds {
+        MilliSeconds(
+            ((4u64 << 6) * u64::from(MAX_RELOAD + 1) * 1000
+                / u64::from(self.lsi_frequency.0)) as u32,
+        )
+    }
+
+    /// Starts the watchdog in window mode. The watchdog must be fed no
+    /// earlier than `min_period` and no later than `max_period` after it
+    /// was last fed, otherwise the system is reset.
+    ///
+    /// Panics if `max_period` is out of bounds or `min_period` is not
+    /// shorter than `max_period`
+    pub fn start_windowed<T: Into<MilliSeconds>>(
+        &mut self,
+        min_period: T,
+        max_period: T,
+    ) {
+        let min_period_ms = min_period.into().0;
+        let max_period_ms = max_period.into().0;
+        assert!(min_period_ms < max_period_ms);
+
+        let (pr, rl) = self.setup(max_period_ms);
+
+        // The counter must be below the window value when fed
+        let divider = 4u64 << pr;
+        let min_ticks = u64::from(min_period_ms)
+            * u64::from(self.lsi_frequency.0)
+            / 1000
+            / divider;
+        let win = u64::from(rl).saturating_sub(min_ticks) as u16;
+
+        // Writing the window register also reloads the counter
+        self.iwdg.winr.write(|w| w.win().bits(win));
+    }
+
+    /// Start the watchdog and configure the prescaler and reload value
+    /// for `period_ms`
+    fn setup(&mut self, period_ms: u32) -> (u8, u16) {
+        let (pr, rl) = prescaler_reload(self.lsi_frequency.0, period_ms)
+            .expect("Independent Watchdog period out of bounds");
+
+        // Start the watchdog. This also enables the LSI
+        self.iwdg.kr.write(|w| unsafe { w.key().bits(KEY_START) });
+
+        // Enable register access
+        self.iwdg.kr.write(|w| unsafe { w.key().bits(KEY_ACCESS) });
+        self.iwdg.pr.write(|w| w.pr().bits(pr));
+        self.iwdg.rlr.write(|w| w.rl().bits(rl));
+
+        // Wait for the prescaler and reload values to be updated
+        while self.iwdg.sr.read().bits() != 0 {}
+
+        (pr, rl)
+    }
+}
+
+impl Watchdog for IndependentWatchdog {
+    /// Feeds the watchdog in order to avoid a reset
+    fn feed(&mut self) {
+        self.iwdg.kr.write(|w| unsafe { w.key().bits(KEY_RELOAD) });
+    }
+}
+
+impl WatchdogEnable for IndependentWatchdog {
+    type Time = MilliSeconds;
+    /// Starts the watchdog with a given timeout period, if this period is
+    /// out of bounds the function is going to panic
+    fn start<T>(&mut self, period: T)
+    where
+        T: Into<Self::Time>,
+    {
+        self.setup(period.into().0);
+
+        // Disable the window. Writing the window register also reloads the
+        // counter
+        self.iwdg.winr.write(|w| w.win().bits(MAX_RELOAD as u16));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iwdg_prescaler_reload() {
+        // 4 * (rl + 1) / 32kHz
+        assert_eq!(prescaler_reload(32_000, 1), Some((0, 7)));
+        assert_eq!(prescaler_reload(32_000, 100), Some((0, 799)));
+        assert_eq!(prescaler_reload(32_000, 512), Some((0, 4095)));
+        assert_eq!(prescaler_reload(32_000, 513), Some((1, 2051)));
+        assert_eq!(prescaler_reload(32_000, 1000), Some((1, 3999)));
+        assert_eq!(prescaler_reload(32_000, 32_768), Some((6, 4095)));
+        assert_eq!(prescaler_reload(32_000, 32_769), None);
+    }
+}